@@ -0,0 +1,145 @@
+//! The kinds of members an attribute value can carry.
+
+use std::fmt;
+
+use errors::ConversionError;
+use super::{AttributeValue, ConversionResult};
+
+
+/// The data type of a DynamoDB attribute, named after its wire format
+/// descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeType {
+    S,
+    N,
+    B,
+    Bool,
+    Null,
+    M,
+    L,
+    SS,
+    NS,
+    BS,
+}
+
+impl AttributeType {
+    /// Lists the types of all the members which are set on an attribute
+    /// value. A well formed attribute value has exactly one.
+    pub fn of(attribute_value: &AttributeValue) -> Vec<AttributeType> {
+        let members = [
+            (attribute_value.s.is_some(), AttributeType::S),
+            (attribute_value.n.is_some(), AttributeType::N),
+            (attribute_value.b.is_some(), AttributeType::B),
+            (attribute_value.bool.is_some(), AttributeType::Bool),
+            (attribute_value.null.is_some(), AttributeType::Null),
+            (attribute_value.m.is_some(), AttributeType::M),
+            (attribute_value.l.is_some(), AttributeType::L),
+            (attribute_value.ss.is_some(), AttributeType::SS),
+            (attribute_value.ns.is_some(), AttributeType::NS),
+            (attribute_value.bs.is_some(), AttributeType::BS),
+        ];
+        members.iter()
+            .filter(|&&(is_set, _)| is_set)
+            .map(|&(_, attribute_type)| attribute_type)
+            .collect()
+    }
+
+    /// Returns the type of the single member set on an attribute value.
+    ///
+    /// Fails with `MissingValue` when no member is set and with
+    /// `MalformedValue` when more than one is.
+    pub fn single(attribute_value: &AttributeValue) -> ConversionResult<AttributeType> {
+        let mut types = AttributeType::of(attribute_value);
+        match types.len() {
+            0 => Err(ConversionError::MissingValue),
+            1 => Ok(types.remove(0)),
+            _ => Err(ConversionError::MalformedValue(types)),
+        }
+    }
+
+    /// Checks that the single member set on an attribute value is of the
+    /// expected type and hands the attribute value back.
+    pub fn expect(
+        self, attribute_value: AttributeValue
+    ) -> ConversionResult<AttributeValue> {
+        let found = AttributeType::single(&attribute_value)?;
+        if found == self {
+            Ok(attribute_value)
+        } else {
+            Err(ConversionError::TypeMismatch { expected: self, found })
+        }
+    }
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            AttributeType::S => "S",
+            AttributeType::N => "N",
+            AttributeType::B => "B",
+            AttributeType::Bool => "BOOL",
+            AttributeType::Null => "NULL",
+            AttributeType::M => "M",
+            AttributeType::L => "L",
+            AttributeType::SS => "SS",
+            AttributeType::NS => "NS",
+            AttributeType::BS => "BS",
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use AttributeValue;
+
+    use errors::ConversionError;
+    use super::AttributeType;
+
+    #[test]
+    fn finds_the_single_member() {
+        let av = AttributeValue {
+            ns: Some(vec!["1".to_string()]),
+            ..AttributeValue::default()
+        };
+        assert_eq!(AttributeType::NS, AttributeType::single(&av).unwrap());
+    }
+
+    #[test]
+    fn fails_when_no_member_is_set() {
+        match AttributeType::single(&AttributeValue::default()) {
+            Err(ConversionError::MissingValue) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fails_when_multiple_members_are_set() {
+        let av = AttributeValue {
+            s: Some("value".to_string()),
+            n: Some("1".to_string()),
+            ..AttributeValue::default()
+        };
+        match AttributeType::single(&av) {
+            Err(ConversionError::MalformedValue(types)) => {
+                assert_eq!(vec![AttributeType::S, AttributeType::N], types)
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fails_on_unexpected_member() {
+        let av = AttributeValue {
+            n: Some("1".to_string()),
+            ..AttributeValue::default()
+        };
+        match AttributeType::S.expect(av) {
+            Err(ConversionError::TypeMismatch { expected, found }) => {
+                assert_eq!(AttributeType::S, expected);
+                assert_eq!(AttributeType::N, found);
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
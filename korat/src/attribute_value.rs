@@ -0,0 +1,38 @@
+//! Korat's own representation of DynamoDB attribute values.
+//!
+//! Converters, items and the rest of the crate work on this type so that they
+//! don't depend on a particular client. The `backend` module converts it to
+//! and from the attribute values of the rusoto and AWS SDK clients.
+
+use std::collections::HashMap;
+
+
+/// A DynamoDB attribute value. Exactly one member should be set on a well
+/// formed attribute value, while a value with no member set stands for an
+/// absent attribute.
+///
+/// The members mirror the data type descriptors of the DynamoDB wire format,
+/// see `AttributeType`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttributeValue {
+    /// A binary.
+    pub b: Option<Vec<u8>>,
+    /// A boolean.
+    pub bool: Option<bool>,
+    /// A binary set.
+    pub bs: Option<Vec<Vec<u8>>>,
+    /// A list of attribute values.
+    pub l: Option<Vec<AttributeValue>>,
+    /// A map of attribute names to attribute values.
+    pub m: Option<HashMap<String, AttributeValue>>,
+    /// A number, kept as its string representation.
+    pub n: Option<String>,
+    /// A number set.
+    pub ns: Option<Vec<String>>,
+    /// A null, set to `true` when present.
+    pub null: Option<bool>,
+    /// A string.
+    pub s: Option<String>,
+    /// A string set.
+    pub ss: Option<Vec<String>>,
+}
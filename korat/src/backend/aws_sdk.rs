@@ -0,0 +1,149 @@
+//! The `aws-sdk-dynamodb` backend. Its attribute value is an enum with a
+//! variant per data type, so a korat attribute value converts to the variant
+//! of the member it has set.
+
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::operation::update_item::builders::UpdateItemInputBuilder;
+use aws_sdk_dynamodb::operation::update_item::UpdateItemInput;
+use aws_sdk_dynamodb::primitives::Blob;
+
+use errors::ConversionError;
+use update::UpdateExpression;
+use super::{Backend, into_attribute_map};
+
+pub use aws_sdk_dynamodb::types::AttributeValue;
+
+
+/// The `aws-sdk-dynamodb` client.
+#[derive(Debug, Clone, Copy)]
+pub struct AwsSdk;
+
+impl Backend for AwsSdk {
+    type AttributeValue = AttributeValue;
+
+    /// Attribute values with no member set, such as the ones of `None`
+    /// options, become `NULL` since the SDK has no empty attribute value.
+    /// When more than one member is set the first one in the order of
+    /// `AttributeType::of` is kept.
+    fn to_backend(attribute_value: ::AttributeValue) -> Self::AttributeValue {
+        let ::AttributeValue { b, bool, bs, l, m, n, ns, null, s, ss } = attribute_value;
+        if let Some(string) = s {
+            AttributeValue::S(string)
+        } else if let Some(number) = n {
+            AttributeValue::N(number)
+        } else if let Some(binary) = b {
+            AttributeValue::B(Blob::new(binary))
+        } else if let Some(boolean) = bool {
+            AttributeValue::Bool(boolean)
+        } else if let Some(null) = null {
+            AttributeValue::Null(null)
+        } else if let Some(map) = m {
+            AttributeValue::M(
+                map.into_iter().map(|(name, value)| (name, Self::to_backend(value))).collect()
+            )
+        } else if let Some(list) = l {
+            AttributeValue::L(list.into_iter().map(Self::to_backend).collect())
+        } else if let Some(set) = ss {
+            AttributeValue::Ss(set)
+        } else if let Some(set) = ns {
+            AttributeValue::Ns(set)
+        } else if let Some(set) = bs {
+            AttributeValue::Bs(set.into_iter().map(Blob::new).collect())
+        } else {
+            AttributeValue::Null(true)
+        }
+    }
+
+    fn from_backend(
+        attribute_value: Self::AttributeValue
+    ) -> Result<::AttributeValue, ConversionError> {
+        let mut converted = ::AttributeValue::default();
+        match attribute_value {
+            AttributeValue::S(string) => converted.s = Some(string),
+            AttributeValue::N(number) => converted.n = Some(number),
+            AttributeValue::B(binary) => converted.b = Some(binary.into_inner()),
+            AttributeValue::Bool(boolean) => converted.bool = Some(boolean),
+            AttributeValue::Null(null) => converted.null = Some(null),
+            AttributeValue::M(map) => converted.m = Some(
+                map.into_iter()
+                    .map(|(name, value)| Ok((name, Self::from_backend(value)?)))
+                    .collect::<Result<_, ConversionError>>()?
+            ),
+            AttributeValue::L(list) => converted.l = Some(
+                list.into_iter().map(Self::from_backend).collect::<Result<_, _>>()?
+            ),
+            AttributeValue::Ss(set) => converted.ss = Some(set),
+            AttributeValue::Ns(set) => converted.ns = Some(set),
+            AttributeValue::Bs(set) => converted.bs = Some(
+                set.into_iter().map(Blob::into_inner).collect()
+            ),
+            _ => return Err(ConversionError::Custom(
+                "attribute value of a data type unknown to korat".to_string()
+            )),
+        }
+        Ok(converted)
+    }
+}
+
+/// Builds the request updating the item with the given key, see the `update`
+/// module. Updates without values leave out the attribute values, which
+/// DynamoDB doesn't allow to be empty. Further options can be set on the
+/// builder before calling `build`.
+pub fn update_item_input(
+    table_name: &str, key: HashMap<String, ::AttributeValue>, update: UpdateExpression
+) -> UpdateItemInputBuilder {
+    UpdateItemInput::builder()
+        .table_name(table_name)
+        .set_key(Some(into_attribute_map::<AwsSdk>(key)))
+        .set_expression_attribute_values(update.expression_attribute_values::<AwsSdk>())
+        .update_expression(update.expression)
+        .set_condition_expression(update.condition)
+        .set_expression_attribute_names(Some(update.names))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::primitives::Blob;
+    use aws_sdk_dynamodb::types::AttributeValue as SdkAttributeValue;
+
+    use {AttributeValue, AttributeValueConverter};
+    use backend::{Backend, from_attribute_map, into_attribute_map};
+    use super::AwsSdk;
+
+    #[test]
+    fn converts_attribute_values_both_ways() {
+        let mut map = HashMap::new();
+        map.insert("avatar".to_string(), vec![1u8, 2, 3].to_attribute_value());
+        map.insert("tags".to_string(), AttributeValue {
+            ss: Some(vec!["a".to_string()]),
+            ..AttributeValue::default()
+        });
+        let mut item = HashMap::new();
+        item.insert("profile".to_string(), AttributeValue {
+            m: Some(map),
+            ..AttributeValue::default()
+        });
+        item.insert("count".to_string(), 3.to_attribute_value());
+        item.insert("scores".to_string(), vec![1u16, 2].to_attribute_value());
+
+        let converted = into_attribute_map::<AwsSdk>(item.clone());
+        assert_eq!(SdkAttributeValue::N("3".to_string()), converted["count"]);
+        let profile = converted["profile"].as_m().unwrap();
+        assert_eq!(SdkAttributeValue::B(Blob::new(vec![1, 2, 3])), profile["avatar"]);
+
+        assert_eq!(item, from_attribute_map::<AwsSdk>(converted).unwrap());
+    }
+
+    #[test]
+    fn converts_absent_values_to_null() {
+        let converted = AwsSdk::to_backend(None::<String>.to_attribute_value());
+        assert_eq!(SdkAttributeValue::Null(true), converted);
+
+        let value = AwsSdk::from_backend(converted).unwrap();
+        assert_eq!(None, Option::<String>::from_attribute_value(value).unwrap());
+    }
+}
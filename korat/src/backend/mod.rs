@@ -0,0 +1,145 @@
+//! Conversions between korat's attribute values and the ones of the DynamoDB
+//! clients, each behind a cargo feature:
+//!
+//! * `rusoto` (enabled by default) for `rusoto_dynamodb`,
+//! * `aws-sdk` for the official `aws-sdk-dynamodb`.
+//!
+//! Items deriving `DynamoDBItem` convert to and from the attribute maps of
+//! every enabled backend as well as korat's own:
+//!
+//! ```rust,ignore
+//! let request = PutItemInput {
+//!     item: item.into(),
+//!     ..PutItemInput::default()
+//! };
+//! let item = Item::try_from(output.item.unwrap())?;
+//! ```
+//!
+//! Other values go through `Backend`, for example
+//! `backend::into_attribute_map::<AwsSdk>(item.get_key())`. The modules of
+//! the backends re-export the attribute value type of their client.
+
+#[cfg(feature = "aws-sdk")]
+pub mod aws_sdk;
+#[cfg(feature = "rusoto")]
+pub mod rusoto;
+
+use std::collections::HashMap;
+
+use AttributeValue;
+use errors::ConversionError;
+
+#[cfg(feature = "aws-sdk")]
+pub use self::aws_sdk::AwsSdk;
+#[cfg(feature = "rusoto")]
+pub use self::rusoto::Rusoto;
+
+
+/// A DynamoDB client whose attribute values korat converts to and from.
+pub trait Backend {
+    /// The attribute value type of the client.
+    type AttributeValue;
+
+    fn to_backend(attribute_value: AttributeValue) -> Self::AttributeValue;
+
+    /// Fails on attribute values korat has no representation for, such as
+    /// data types introduced after the client was released.
+    fn from_backend(
+        attribute_value: Self::AttributeValue
+    ) -> Result<AttributeValue, ConversionError>;
+}
+
+/// Converts an attribute map, such as an item or a key, for a backend.
+pub fn into_attribute_map<B: Backend>(
+    attribute_map: HashMap<String, AttributeValue>
+) -> HashMap<String, B::AttributeValue> {
+    attribute_map.into_iter()
+        .map(|(name, attribute_value)| (name, B::to_backend(attribute_value)))
+        .collect()
+}
+
+/// Converts an attribute map returned by a backend.
+pub fn from_attribute_map<B: Backend>(
+    attribute_map: HashMap<String, B::AttributeValue>
+) -> Result<HashMap<String, AttributeValue>, ConversionError> {
+    attribute_map.into_iter()
+        .map(|(name, attribute_value)| Ok((name, B::from_backend(attribute_value)?)))
+        .collect()
+}
+
+
+/// Implements the conversions of an item to and from the attribute maps of a
+/// backend. Used by the derive through `item_backend_conversions`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! backend_conversions {
+    ($name:ident, $backend:ty, $attribute_value:ty) => {
+        impl ::std::convert::From<$name> for ::std::collections::HashMap<
+            String, $attribute_value
+        > {
+            fn from(item: $name) -> Self {
+                $crate::backend::into_attribute_map::<$backend>(item.into())
+            }
+        }
+
+        impl ::std::convert::TryFrom<::std::collections::HashMap<
+            String, $attribute_value
+        >> for $name {
+            type Error = $crate::errors::ConversionError;
+
+            fn try_from(
+                attribute_map: ::std::collections::HashMap<
+                    String, $attribute_value
+                >
+            ) -> Result<Self, Self::Error> {
+                let attribute_map = $crate::backend::from_attribute_map::<$backend>(
+                    attribute_map
+                )?;
+                <$name as ::std::convert::TryFrom<_>>::try_from(attribute_map)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rusoto")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! rusoto_conversions {
+    ($name:ident) => { $crate::backend_conversions!(
+        $name, $crate::backend::Rusoto, $crate::backend::rusoto::AttributeValue
+    ); }
+}
+
+#[cfg(not(feature = "rusoto"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! rusoto_conversions {
+    ($name:ident) => {}
+}
+
+#[cfg(feature = "aws-sdk")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! aws_sdk_conversions {
+    ($name:ident) => { $crate::backend_conversions!(
+        $name, $crate::backend::AwsSdk, $crate::backend::aws_sdk::AttributeValue
+    ); }
+}
+
+#[cfg(not(feature = "aws-sdk"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! aws_sdk_conversions {
+    ($name:ident) => {}
+}
+
+/// Implements the conversions of an item for every enabled backend. The
+/// features are those of korat, so the derive doesn't need to know them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! item_backend_conversions {
+    ($name:ident) => {
+        $crate::rusoto_conversions!($name);
+        $crate::aws_sdk_conversions!($name);
+    }
+}
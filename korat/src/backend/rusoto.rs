@@ -0,0 +1,231 @@
+//! The `rusoto_dynamodb` backend. Its attribute value is a struct with the
+//! same members as korat's, so conversions copy them over. Attribute values
+//! with no member set, such as the ones of `None` options, become `NULL` like
+//! with the other backends, since DynamoDB rejects empty attribute values.
+
+use std::collections::HashMap;
+
+use rusoto_dynamodb::{
+    AttributeDefinition, CreateTableInput, GlobalSecondaryIndex, KeySchemaElement,
+    LocalSecondaryIndex, Projection, ProvisionedThroughput, QueryInput,
+    TimeToLiveSpecification, UpdateItemInput, UpdateTimeToLiveInput
+};
+
+use errors::ConversionError;
+use query::KeyCondition;
+use table::{BillingMode, KeySchema, TableDefinition};
+use update::UpdateExpression;
+use super::{Backend, into_attribute_map};
+
+pub use rusoto_dynamodb::AttributeValue;
+
+
+/// The `rusoto_dynamodb` client.
+#[derive(Debug, Clone, Copy)]
+pub struct Rusoto;
+
+impl Backend for Rusoto {
+    type AttributeValue = AttributeValue;
+
+    fn to_backend(attribute_value: ::AttributeValue) -> Self::AttributeValue {
+        attribute_value.into()
+    }
+
+    fn from_backend(
+        attribute_value: Self::AttributeValue
+    ) -> Result<::AttributeValue, ConversionError> {
+        Ok(attribute_value.into())
+    }
+}
+
+impl From<::AttributeValue> for AttributeValue {
+    fn from(attribute_value: ::AttributeValue) -> Self {
+        if attribute_value == ::AttributeValue::default() {
+            return AttributeValue {
+                null: Some(true),
+                ..AttributeValue::default()
+            };
+        }
+        AttributeValue {
+            b: attribute_value.b.map(Into::into),
+            bool: attribute_value.bool,
+            bs: attribute_value.bs.map(|set| set.into_iter().map(Into::into).collect()),
+            l: attribute_value.l.map(|list| list.into_iter().map(Into::into).collect()),
+            m: attribute_value.m.map(|map| {
+                map.into_iter().map(|(name, value)| (name, value.into())).collect()
+            }),
+            n: attribute_value.n,
+            ns: attribute_value.ns,
+            null: attribute_value.null,
+            s: attribute_value.s,
+            ss: attribute_value.ss,
+        }
+    }
+}
+
+impl From<AttributeValue> for ::AttributeValue {
+    fn from(attribute_value: AttributeValue) -> Self {
+        ::AttributeValue {
+            b: attribute_value.b.map(Into::into),
+            bool: attribute_value.bool,
+            bs: attribute_value.bs.map(|set| set.into_iter().map(Into::into).collect()),
+            l: attribute_value.l.map(|list| list.into_iter().map(Into::into).collect()),
+            m: attribute_value.m.map(|map| {
+                map.into_iter().map(|(name, value)| (name, value.into())).collect()
+            }),
+            n: attribute_value.n,
+            ns: attribute_value.ns,
+            null: attribute_value.null,
+            s: attribute_value.s,
+            ss: attribute_value.ss,
+        }
+    }
+}
+
+/// Builds the request creating the table of an item, for example
+/// `create_table_input(&Order::table_definition())`.
+pub fn create_table_input(definition: &TableDefinition) -> CreateTableInput {
+    let provisioned_throughput = match definition.billing_mode {
+        BillingMode::PayPerRequest => None,
+        BillingMode::Provisioned { read_capacity_units, write_capacity_units } => {
+            Some(ProvisionedThroughput { read_capacity_units, write_capacity_units })
+        },
+    };
+    let global_indexes: Vec<_> = definition.global_indexes()
+        .map(|index| GlobalSecondaryIndex {
+            index_name: index.name.clone(),
+            key_schema: key_schema_elements(&index.key_schema),
+            projection: projection_all(),
+            provisioned_throughput: provisioned_throughput.clone(),
+        })
+        .collect();
+    let local_indexes: Vec<_> = definition.local_indexes()
+        .map(|index| LocalSecondaryIndex {
+            index_name: index.name.clone(),
+            key_schema: key_schema_elements(&index.key_schema),
+            projection: projection_all(),
+        })
+        .collect();
+
+    CreateTableInput {
+        table_name: definition.name.clone(),
+        key_schema: key_schema_elements(&definition.key_schema),
+        attribute_definitions: definition.attribute_definitions().into_iter()
+            .map(|attribute| AttributeDefinition {
+                attribute_name: attribute.name,
+                attribute_type: attribute.attribute_type.to_string(),
+            })
+            .collect(),
+        billing_mode: Some(match definition.billing_mode {
+            BillingMode::PayPerRequest => "PAY_PER_REQUEST",
+            BillingMode::Provisioned { .. } => "PROVISIONED",
+        }.to_string()),
+        provisioned_throughput,
+        global_secondary_indexes: Some(global_indexes).filter(|indexes| !indexes.is_empty()),
+        local_secondary_indexes: Some(local_indexes).filter(|indexes| !indexes.is_empty()),
+        ..CreateTableInput::default()
+    }
+}
+
+/// Builds the request enabling time to live on the table of an item, to send
+/// once the table is active. `None` when the item has no `ttl` attribute.
+pub fn update_time_to_live_input(
+    definition: &TableDefinition
+) -> Option<UpdateTimeToLiveInput> {
+    definition.ttl_attribute.as_ref().map(|attribute_name| UpdateTimeToLiveInput {
+        table_name: definition.name.clone(),
+        time_to_live_specification: TimeToLiveSpecification {
+            attribute_name: attribute_name.clone(),
+            enabled: true,
+        },
+    })
+}
+
+/// Builds a query request for the items matching a key condition, see the
+/// `query` module.
+pub fn query_input(table_name: &str, condition: KeyCondition) -> QueryInput {
+    QueryInput {
+        table_name: table_name.to_string(),
+        index_name: condition.index_name,
+        key_condition_expression: Some(condition.expression),
+        expression_attribute_names: Some(condition.names),
+        expression_attribute_values: Some(into_attribute_map::<Rusoto>(condition.values)),
+        ..QueryInput::default()
+    }
+}
+
+/// Builds the request updating the item with the given key, see the `update`
+/// module. Updates without values leave out the attribute values, which
+/// DynamoDB doesn't allow to be empty.
+pub fn update_item_input(
+    table_name: &str, key: HashMap<String, ::AttributeValue>, update: UpdateExpression
+) -> UpdateItemInput {
+    UpdateItemInput {
+        table_name: table_name.to_string(),
+        key: into_attribute_map::<Rusoto>(key),
+        expression_attribute_values: update.expression_attribute_values::<Rusoto>(),
+        update_expression: Some(update.expression),
+        condition_expression: update.condition,
+        expression_attribute_names: Some(update.names),
+        ..UpdateItemInput::default()
+    }
+}
+
+fn key_schema_elements(key_schema: &KeySchema) -> Vec<KeySchemaElement> {
+    let hash = KeySchemaElement {
+        attribute_name: key_schema.hash.name.clone(),
+        key_type: "HASH".to_string(),
+    };
+    let range = key_schema.range.as_ref().map(|range| KeySchemaElement {
+        attribute_name: range.name.clone(),
+        key_type: "RANGE".to_string(),
+    });
+    Some(hash).into_iter().chain(range).collect()
+}
+
+fn projection_all() -> Projection {
+    Projection {
+        projection_type: Some("ALL".to_string()),
+        non_key_attributes: None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use {AttributeValue, AttributeValueConverter};
+    use backend::{Backend, from_attribute_map, into_attribute_map};
+    use super::Rusoto;
+
+    #[test]
+    fn converts_attribute_values_both_ways() {
+        let mut map = HashMap::new();
+        map.insert("avatar".to_string(), vec![1u8, 2, 3].to_attribute_value());
+        map.insert("tags".to_string(), AttributeValue {
+            ss: Some(vec!["a".to_string()]),
+            ..AttributeValue::default()
+        });
+        let mut item = HashMap::new();
+        item.insert("profile".to_string(), AttributeValue {
+            m: Some(map),
+            ..AttributeValue::default()
+        });
+
+        let converted = into_attribute_map::<Rusoto>(item.clone());
+        let profile = converted["profile"].m.as_ref().unwrap();
+        assert_eq!(&[1u8, 2, 3][..], &profile["avatar"].b.as_ref().unwrap()[..]);
+
+        assert_eq!(item, from_attribute_map::<Rusoto>(converted).unwrap());
+    }
+
+    #[test]
+    fn converts_absent_values_to_null() {
+        let converted = Rusoto::to_backend(None::<String>.to_attribute_value());
+        assert_eq!(Some(true), converted.null);
+
+        let value = Rusoto::from_backend(converted).unwrap();
+        assert_eq!(None, Option::<String>::from_attribute_value(value).unwrap());
+    }
+}
@@ -0,0 +1,145 @@
+//! Lenient decoding of scalar attributes stored by legacy writers.
+//!
+//! By default the converters only accept the attribute type they encode to.
+//! Coercion lets the numeric, boolean and string converters also accept
+//! compatible alternate representations: numbers stored as `S`, booleans
+//! stored as `"true"`/`"false"` strings or `0`/`1` numbers, and numbers or
+//! booleans read into strings.
+//!
+//! Coercion is requested per field with `#[korat(coerce)]` or for every
+//! conversion in the process with `set_lenient_decoding(true)`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use errors::ConversionError;
+use super::{AttributeType, AttributeValue, ConversionResult};
+
+
+static LENIENT_DECODING: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables coercion for all conversions in the process.
+pub fn set_lenient_decoding(enabled: bool) {
+    LENIENT_DECODING.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_lenient_decoding() -> bool {
+    LENIENT_DECODING.load(Ordering::SeqCst)
+}
+
+/// Reads the string representation of a number from an `N` attribute or from
+/// an `S` attribute holding a number.
+pub fn number_string(attribute_value: AttributeValue) -> ConversionResult<String> {
+    match AttributeType::single(&attribute_value)? {
+        AttributeType::N => attribute_value.n.ok_or(ConversionError::MissingValue),
+        AttributeType::S => attribute_value.s
+            .map(|string| string.trim().to_string())
+            .ok_or(ConversionError::MissingValue),
+        found => Err(ConversionError::TypeMismatch {
+            expected: AttributeType::N, found
+        }),
+    }
+}
+
+/// Reads a boolean from a `BOOL` attribute, from an `S` attribute holding
+/// `"true"` or `"false"` or from an `N` attribute holding `1` or `0`.
+pub fn boolean(attribute_value: AttributeValue) -> ConversionResult<bool> {
+    match AttributeType::single(&attribute_value)? {
+        AttributeType::Bool => attribute_value.bool
+            .ok_or(ConversionError::MissingValue),
+        AttributeType::S => {
+            let string = attribute_value.s.unwrap_or_default();
+            match string.trim().to_lowercase().as_str() {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(ConversionError::InvalidValue),
+            }
+        },
+        AttributeType::N => match attribute_value.n.unwrap_or_default().trim() {
+            "1" => Ok(true),
+            "0" => Ok(false),
+            _ => Err(ConversionError::InvalidValue),
+        },
+        found => Err(ConversionError::TypeMismatch {
+            expected: AttributeType::Bool, found
+        }),
+    }
+}
+
+/// Reads a string from an `S` attribute or the textual representation of an
+/// `N` or `BOOL` attribute.
+pub fn string(attribute_value: AttributeValue) -> ConversionResult<String> {
+    match AttributeType::single(&attribute_value)? {
+        AttributeType::S => attribute_value.s.ok_or(ConversionError::MissingValue),
+        AttributeType::N => attribute_value.n.ok_or(ConversionError::MissingValue),
+        AttributeType::Bool => attribute_value.bool
+            .map(|boolean| boolean.to_string())
+            .ok_or(ConversionError::MissingValue),
+        found => Err(ConversionError::TypeMismatch {
+            expected: AttributeType::S, found
+        }),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use AttributeValue;
+
+    use AttributeValueConverter;
+    use super::{boolean, number_string, string};
+
+    fn string_value(value: &str) -> AttributeValue {
+        AttributeValue {
+            s: Some(value.to_string()),
+            ..AttributeValue::default()
+        }
+    }
+
+    fn number_value(value: &str) -> AttributeValue {
+        AttributeValue {
+            n: Some(value.to_string()),
+            ..AttributeValue::default()
+        }
+    }
+
+    #[test]
+    fn reads_numbers_from_strings() {
+        assert_eq!("12", number_string(string_value(" 12 ")).unwrap());
+        assert_eq!("12", number_string(number_value("12")).unwrap());
+    }
+
+    #[test]
+    fn reads_booleans_from_strings_and_numbers() {
+        assert!(boolean(string_value("TRUE")).unwrap());
+        assert!(!boolean(string_value("false")).unwrap());
+        assert!(boolean(number_value("1")).unwrap());
+        assert!(!boolean(number_value("0")).unwrap());
+        assert!(boolean(string_value("yes")).is_err());
+        assert!(boolean(number_value("2")).is_err());
+    }
+
+    #[test]
+    fn reads_strings_from_numbers_and_booleans() {
+        assert_eq!("12.5", string(number_value("12.5")).unwrap());
+        let av = AttributeValue {
+            bool: Some(true),
+            ..AttributeValue::default()
+        };
+        assert_eq!("true", string(av).unwrap());
+    }
+
+    #[test]
+    fn coercion_is_only_used_when_requested() {
+        let strict: Result<i32, _> = AttributeValueConverter
+            ::from_attribute_value(string_value("12"));
+        assert!(strict.is_err());
+
+        let coerced: i32 = AttributeValueConverter
+            ::coerce_from_attribute_value(string_value("12")).unwrap();
+        assert_eq!(12, coerced);
+
+        let coerced: Option<bool> = AttributeValueConverter
+            ::coerce_from_attribute_value(string_value("true")).unwrap();
+        assert_eq!(Some(true), coerced);
+    }
+}
@@ -0,0 +1,421 @@
+//! Converters for common standard library and ecosystem types.
+//!
+//! Types with a canonical textual form (`char`, `PathBuf`, IP and socket
+//! addresses and, with the features of the same name, `uuid::Uuid` and
+//! `url::Url`) are stored as `S` attributes. `Duration` is stored as an `N`
+//! attribute holding seconds with up to nanosecond precision. With the
+//! `serde_json` feature, `serde_json::Value` is stored as the equivalent
+//! native attribute value.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use errors::ConversionError;
+use update::{AddAttribute, NumberAttribute};
+use super::{
+    AttributeType, AttributeValue, AttributeValueConverter, ConversionResult, ScalarKey
+};
+
+
+/// Builds the error reported when an attribute can't be parsed into the
+/// expected type.
+pub(crate) fn invalid_format<E: ToString>(
+    expected: &'static str, value: &str, error: E
+) -> ConversionError {
+    ConversionError::InvalidFormat {
+        expected,
+        value: value.to_string(),
+        reason: error.to_string(),
+    }
+}
+
+macro_rules! parsed_string_converter {
+    ($type:ty, $description:expr) => {
+        impl AttributeValueConverter for $type {
+            fn from_attribute_value(
+                attribute_value: AttributeValue
+            ) -> ConversionResult<Self> {
+                let string = String::from_attribute_value(attribute_value)?;
+                string.parse()
+                    .map_err(|error| invalid_format($description, &string, error))
+            }
+
+            fn to_attribute_value(self) -> AttributeValue {
+                self.to_string().to_attribute_value()
+            }
+        }
+
+        impl ScalarKey for $type {
+            const ATTRIBUTE_TYPE: AttributeType = AttributeType::S;
+        }
+    }
+}
+
+parsed_string_converter!(char, "a single character");
+parsed_string_converter!(IpAddr, "an IP address");
+parsed_string_converter!(Ipv4Addr, "an IPv4 address");
+parsed_string_converter!(Ipv6Addr, "an IPv6 address");
+parsed_string_converter!(SocketAddr, "a socket address");
+
+/// Paths are stored as strings, so paths which are not valid unicode can't
+/// be stored: `try_to_attribute_value` reports them as an invalid value and
+/// `to_attribute_value` panics.
+impl AttributeValueConverter for PathBuf {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        String::from_attribute_value(attribute_value).map(PathBuf::from)
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        match self.into_os_string().into_string() {
+            Ok(path) => path.to_attribute_value(),
+            Err(path) => panic!("the path {:?} isn't valid unicode", path),
+        }
+    }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        self.into_os_string().into_string()
+            .map(String::to_attribute_value)
+            .map_err(|_| ConversionError::InvalidValue)
+    }
+}
+
+impl ScalarKey for PathBuf {
+    const ATTRIBUTE_TYPE: AttributeType = AttributeType::S;
+}
+
+impl AttributeValueConverter for Duration {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        let number = AttributeType::N.expect(attribute_value)?.n
+            .ok_or(ConversionError::MissingValue)?;
+        parse_seconds(&number)
+            .map_err(|reason| invalid_format("a non-negative number of seconds", &number, reason))
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        let mut seconds = self.as_secs().to_string();
+        if self.subsec_nanos() > 0 {
+            let fraction = format!("{:09}", self.subsec_nanos());
+            seconds.push('.');
+            seconds.push_str(fraction.trim_end_matches('0'));
+        }
+        AttributeValue {
+            n: Some(seconds),
+            ..AttributeValue::default()
+        }
+    }
+}
+
+impl ScalarKey for Duration {
+    const ATTRIBUTE_TYPE: AttributeType = AttributeType::N;
+}
+
+impl NumberAttribute for Duration {}
+
+impl AddAttribute for Duration {}
+
+/// Parses a decimal number of seconds without going through floating point,
+/// so that nanosecond precision is kept.
+fn parse_seconds(number: &str) -> Result<Duration, &'static str> {
+    let mut parts = number.splitn(2, '.');
+    let whole = parts.next().unwrap_or_default();
+    let fraction = parts.next();
+    let is_digits = |part: &str| {
+        !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit())
+    };
+    let is_valid = match fraction {
+        Some(fraction) => (whole.is_empty() || is_digits(whole)) && is_digits(fraction),
+        None => is_digits(whole),
+    };
+    if !is_valid {
+        return Err("expected digits with an optional fraction");
+    }
+    let fraction = fraction.unwrap_or_default();
+    if fraction.len() > 9 {
+        return Err("more than nanosecond precision");
+    }
+
+    let seconds = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| "too many seconds")?
+    };
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u32>().map_err(|_| "invalid fraction")?
+            * 10u32.pow(9 - fraction.len() as u32)
+    };
+    Ok(Duration::new(seconds, nanos))
+}
+
+#[cfg(feature = "url")]
+parsed_string_converter!(::url::Url, "a URL");
+
+#[cfg(feature = "uuid")]
+parsed_string_converter!(::uuid::Uuid, "a UUID");
+
+/// Stores UUIDs as 16 byte `B` attributes rather than as strings. Select it
+/// with `#[korat(with = "korat::common_types::binary_uuid")]`.
+#[cfg(feature = "uuid")]
+pub mod binary_uuid {
+    use AttributeValue;
+    use uuid::Uuid;
+
+    use super::{AttributeValueConverter, ConversionResult, invalid_format};
+    use super::super::optional;
+
+    /// A field which can be stored as a binary UUID; either a `Uuid` or an
+    /// optional one.
+    pub trait BinaryUuidField: Sized {
+        fn to_binary_attribute_value(self) -> AttributeValue;
+        fn from_binary_attribute_value(
+            attribute_value: AttributeValue
+        ) -> ConversionResult<Self>;
+    }
+
+    impl BinaryUuidField for Uuid {
+        fn to_binary_attribute_value(self) -> AttributeValue {
+            self.as_bytes().to_vec().to_attribute_value()
+        }
+
+        fn from_binary_attribute_value(
+            attribute_value: AttributeValue
+        ) -> ConversionResult<Self> {
+            let bytes = Vec::<u8>::from_attribute_value(attribute_value)?;
+            Uuid::from_slice(&bytes).map_err(|error| invalid_format(
+                "a 16 byte UUID", &format!("{} bytes", bytes.len()), error
+            ))
+        }
+    }
+
+    impl BinaryUuidField for Option<Uuid> {
+        fn to_binary_attribute_value(self) -> AttributeValue {
+            self.map_or_else(AttributeValue::default, Uuid::to_binary_attribute_value)
+        }
+
+        fn from_binary_attribute_value(
+            attribute_value: AttributeValue
+        ) -> ConversionResult<Self> {
+            optional(Uuid::from_binary_attribute_value(attribute_value))
+        }
+    }
+
+    pub fn to_attribute_value<T: BinaryUuidField>(value: T) -> AttributeValue {
+        value.to_binary_attribute_value()
+    }
+
+    pub fn from_attribute_value<T: BinaryUuidField>(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<T> {
+        T::from_binary_attribute_value(attribute_value)
+    }
+}
+
+#[cfg(feature = "serde_json")]
+mod json_converters {
+    use serde_json::{Map, Number, Value};
+
+    use errors::ConversionError;
+    use super::{
+        AttributeType, AttributeValue, AttributeValueConverter, ConversionResult,
+        invalid_format
+    };
+
+    fn number(number: String) -> ConversionResult<Value> {
+        number.parse::<Number>()
+            .map(Value::Number)
+            .map_err(|error| invalid_format("a JSON number", &number, error))
+    }
+
+    impl AttributeValueConverter for Value {
+        fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+            let missing = ConversionError::MissingValue;
+            match AttributeType::single(&attribute_value)? {
+                AttributeType::S => attribute_value.s.map(Value::String).ok_or(missing),
+                AttributeType::N => number(attribute_value.n.ok_or(missing)?),
+                AttributeType::Bool => attribute_value.bool.map(Value::Bool).ok_or(missing),
+                AttributeType::Null => Ok(Value::Null),
+                AttributeType::M => attribute_value.m.ok_or(missing)?
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, Value::from_attribute_value(value)?)))
+                    .collect::<ConversionResult<Map<String, Value>>>()
+                    .map(Value::Object),
+                AttributeType::L => attribute_value.l.ok_or(missing)?
+                    .into_iter()
+                    .map(Value::from_attribute_value)
+                    .collect::<ConversionResult<Vec<Value>>>()
+                    .map(Value::Array),
+                AttributeType::SS => Ok(Value::Array(
+                    attribute_value.ss.ok_or(missing)?
+                        .into_iter().map(Value::String).collect()
+                )),
+                AttributeType::NS => attribute_value.ns.ok_or(missing)?
+                    .into_iter()
+                    .map(number)
+                    .collect::<ConversionResult<Vec<Value>>>()
+                    .map(Value::Array),
+                found @ AttributeType::B | found @ AttributeType::BS => Err(
+                    ConversionError::Custom(format!(
+                        "{} attributes have no JSON representation", found
+                    ))
+                ),
+            }
+        }
+
+        fn to_attribute_value(self) -> AttributeValue {
+            match self {
+                Value::Null => AttributeValue {
+                    null: Some(true),
+                    ..AttributeValue::default()
+                },
+                Value::Bool(boolean) => boolean.to_attribute_value(),
+                Value::Number(number) => AttributeValue {
+                    n: Some(number.to_string()),
+                    ..AttributeValue::default()
+                },
+                Value::String(string) => string.to_attribute_value(),
+                Value::Array(elements) => AttributeValue {
+                    l: Some(elements.into_iter()
+                        .map(Value::to_attribute_value)
+                        .collect()),
+                    ..AttributeValue::default()
+                },
+                Value::Object(entries) => AttributeValue {
+                    m: Some(entries.into_iter()
+                        .map(|(key, value)| (key, value.to_attribute_value()))
+                        .collect()),
+                    ..AttributeValue::default()
+                },
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use AttributeValue;
+
+    use AttributeValueConverter;
+    use errors::ConversionError;
+
+    fn string_value(value: &str) -> AttributeValue {
+        AttributeValue {
+            s: Some(value.to_string()),
+            ..AttributeValue::default()
+        }
+    }
+
+    #[test]
+    fn can_convert_string_encoded_types() {
+        let address: IpAddr = "2001:db8::1".parse().unwrap();
+        let converted = address.to_attribute_value();
+        assert_eq!("2001:db8::1", &converted.clone().s.unwrap());
+        assert_eq!(address, IpAddr::from_attribute_value(converted).unwrap());
+
+        let path = PathBuf::from("/var/lib/korat");
+        let converted = path.clone().to_attribute_value();
+        assert_eq!(path, PathBuf::from_attribute_value(converted).unwrap());
+
+        assert_eq!('k', char::from_attribute_value(string_value("k")).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_paths_which_are_not_unicode() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = PathBuf::from(OsStr::from_bytes(b"/var/lib/\xff"));
+        match path.try_to_attribute_value() {
+            Err(ConversionError::InvalidValue) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_the_unparsable_value() {
+        match IpAddr::from_attribute_value(string_value("256.0.0.1")) {
+            Err(ConversionError::InvalidFormat { expected, value, .. }) => {
+                assert_eq!("an IP address", expected);
+                assert_eq!("256.0.0.1", value);
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(char::from_attribute_value(string_value("ko")).is_err());
+    }
+
+    #[test]
+    fn can_convert_durations() {
+        let duration = Duration::new(90, 500_000_000);
+        let converted = duration.to_attribute_value();
+        assert_eq!("90.5", &converted.clone().n.unwrap());
+        assert_eq!(duration, Duration::from_attribute_value(converted).unwrap());
+
+        let converted = Duration::from_secs(3).to_attribute_value();
+        assert_eq!("3", &converted.n.unwrap());
+
+        for invalid in &["-1", "1.", "1.0000000001", "1e3", ""] {
+            let av = AttributeValue {
+                n: Some(invalid.to_string()),
+                ..AttributeValue::default()
+            };
+            assert!(Duration::from_attribute_value(av).is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn can_convert_urls() {
+        use url::Url;
+
+        let url = Url::parse("https://github.com/behos/korat").unwrap();
+        let converted = url.clone().to_attribute_value();
+        assert_eq!(url, Url::from_attribute_value(converted).unwrap());
+        assert!(Url::from_attribute_value(string_value("not a url")).is_err());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn can_convert_json_values_to_native_attributes() {
+        use serde_json::Value;
+
+        let document: Value = ::serde_json::from_str(
+            r#"{"name": "korat", "tags": ["a", 1, null], "ratio": 0.5, "ok": true}"#
+        ).unwrap();
+        let converted = document.clone().to_attribute_value();
+        let attribute_map = converted.clone().m.unwrap();
+        assert_eq!("0.5", &attribute_map["ratio"].clone().n.unwrap());
+        let tags = attribute_map["tags"].clone().l.unwrap();
+        assert_eq!(Some(true), tags[2].null);
+        assert_eq!(document, Value::from_attribute_value(converted).unwrap());
+
+        let binary = vec![1u8].to_attribute_value();
+        assert!(Value::from_attribute_value(binary).is_err());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn can_convert_uuids_as_strings_and_bytes() {
+        use uuid::Uuid;
+        use super::binary_uuid;
+
+        let uuid = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let converted = uuid.to_attribute_value();
+        assert_eq!("67e55044-10b1-426f-9247-bb680e5fe0c8", &converted.clone().s.unwrap());
+        assert_eq!(uuid, Uuid::from_attribute_value(converted).unwrap());
+
+        let converted = binary_uuid::to_attribute_value(uuid);
+        let bytes: Vec<u8> = converted.clone().b.unwrap();
+        assert_eq!(uuid.as_bytes().to_vec(), bytes);
+        assert_eq!(uuid, binary_uuid::from_attribute_value::<Uuid>(converted).unwrap());
+
+        let short = vec![1u8, 2, 3].to_attribute_value();
+        assert!(binary_uuid::from_attribute_value::<Uuid>(short).is_err());
+    }
+}
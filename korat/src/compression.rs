@@ -0,0 +1,342 @@
+//! Compressed encodings for large text and binary fields.
+//!
+//! Fields selecting `#[korat(compress = "gzip")]` or
+//! `#[korat(compress = "zstd")]` are stored as a `B` attribute holding a
+//! header byte identifying the compression followed by the compressed
+//! payload. The header is read back when decoding, so a field can switch
+//! compressions without rewriting the stored items, as long as the features
+//! of both compressions are enabled.
+//!
+//! `String` and `Vec<u8>` fields are compressed as they are. Combined with
+//! `#[korat(serde)]`, any serde type is compressed as its JSON
+//! representation, which requires the `serde_json` feature.
+
+use errors::ConversionError;
+use super::{AttributeValue, AttributeValueConverter, ConversionResult, optional};
+
+
+const GZIP_HEADER: u8 = 1;
+const ZSTD_HEADER: u8 = 2;
+
+/// The compressions a payload can be written with, each available with the
+/// feature of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Compresses a payload and prefixes it with the header byte.
+    pub fn compress(self, bytes: &[u8]) -> Vec<u8> {
+        match (self, bytes) {
+            #[cfg(feature = "gzip")]
+            (Compression::Gzip, bytes) => gzip_compress(bytes),
+            #[cfg(feature = "zstd")]
+            (Compression::Zstd, bytes) => zstd_compress(bytes),
+        }
+    }
+}
+
+/// Decompresses a payload with the compression named by its header byte,
+/// which fails for the compressions whose feature is disabled.
+pub fn decompress(bytes: &[u8]) -> ConversionResult<Vec<u8>> {
+    let (&header, payload) = bytes.split_first().ok_or_else(|| corrupt_payload(
+        "a compressed payload", "0 bytes".to_string(), "missing header"
+    ))?;
+    let (expected, decompressed) = match header {
+        GZIP_HEADER => ("a gzip compressed payload", gzip_decompress(payload)),
+        ZSTD_HEADER => ("a zstd compressed payload", zstd_decompress(payload)),
+        _ => return Err(corrupt_payload(
+            "a known compression header", format!("header {}", header), "unknown header"
+        )),
+    };
+    decompressed.map_err(|reason| corrupt_payload(
+        expected, format!("{} bytes", bytes.len()), reason
+    ))
+}
+
+fn corrupt_payload<E: ToString>(
+    expected: &'static str, value: String, reason: E
+) -> ConversionError {
+    ConversionError::InvalidFormat {
+        expected,
+        value,
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_compress(bytes: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    use flate2::write::GzEncoder;
+
+    let mut encoder = GzEncoder::new(vec![GZIP_HEADER], ::flate2::Compression::default());
+    encoder.write_all(bytes)
+        .and_then(|_| encoder.finish())
+        .expect("Writing to a vector can't fail")
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_decompress(payload: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+    use flate2::read::GzDecoder;
+
+    let mut bytes = vec![];
+    GzDecoder::new(payload).read_to_end(&mut bytes)
+        .map(|_| bytes)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = vec![ZSTD_HEADER];
+    compressed.extend(::zstd::encode_all(bytes, 0).expect("Writing to a vector can't fail"));
+    compressed
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(payload: &[u8]) -> Result<Vec<u8>, String> {
+    ::zstd::decode_all(payload).map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_decompress(_payload: &[u8]) -> Result<Vec<u8>, String> {
+    Err("gzip decompression requires the gzip feature".to_string())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_payload: &[u8]) -> Result<Vec<u8>, String> {
+    Err("zstd decompression requires the zstd feature".to_string())
+}
+
+
+/// A value which can be turned into the bytes to compress.
+pub trait Compressible: Sized {
+    fn into_bytes(self) -> Vec<u8>;
+    fn from_bytes(bytes: Vec<u8>) -> ConversionResult<Self>;
+}
+
+impl Compressible for Vec<u8> {
+    fn into_bytes(self) -> Vec<u8> {
+        self
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> ConversionResult<Self> {
+        Ok(bytes)
+    }
+}
+
+impl Compressible for String {
+    fn into_bytes(self) -> Vec<u8> {
+        self.into_bytes()
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> ConversionResult<Self> {
+        String::from_utf8(bytes).map_err(|error| corrupt_payload(
+            "a UTF-8 string", format!("{} bytes", error.as_bytes().len()), error
+        ))
+    }
+}
+
+/// A field which can be stored compressed; either a `Compressible` value or
+/// an optional one.
+pub trait CompressedField: Sized {
+    fn to_attribute_value_with(self, compression: Compression) -> AttributeValue;
+    fn from_compressed_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self>;
+}
+
+impl<T: Compressible> CompressedField for T {
+    fn to_attribute_value_with(self, compression: Compression) -> AttributeValue {
+        compression.compress(&self.into_bytes()).to_attribute_value()
+    }
+
+    fn from_compressed_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        let bytes = Vec::<u8>::from_attribute_value(attribute_value)?;
+        T::from_bytes(decompress(&bytes)?)
+    }
+}
+
+impl<T: Compressible> CompressedField for Option<T> {
+    fn to_attribute_value_with(self, compression: Compression) -> AttributeValue {
+        match self {
+            Some(value) => value.to_attribute_value_with(compression),
+            None => AttributeValue::default(),
+        }
+    }
+
+    fn from_compressed_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        optional(T::from_compressed_attribute_value(attribute_value))
+    }
+}
+
+macro_rules! compression_module {
+    ($name:ident, $compression:ident, $feature:tt) => {
+        #[cfg(feature = $feature)]
+        pub mod $name {
+            use AttributeValue;
+
+            use super::{CompressedField, Compression, ConversionResult};
+
+            pub fn to_attribute_value<T: CompressedField>(value: T) -> AttributeValue {
+                value.to_attribute_value_with(Compression::$compression)
+            }
+
+            pub fn from_attribute_value<T: CompressedField>(
+                attribute_value: AttributeValue
+            ) -> ConversionResult<T> {
+                T::from_compressed_attribute_value(attribute_value)
+            }
+
+            /// Compresses serde types as their JSON representation.
+            #[cfg(feature = "serde_json")]
+            pub mod serde {
+                use AttributeValue;
+                use serde_crate::Serialize;
+                use serde_crate::de::DeserializeOwned;
+
+                use super::super::compressed_json;
+                use super::{Compression, ConversionResult};
+
+                /// # Panics
+                ///
+                /// Panics when the value can't be represented as JSON, see
+                /// `try_to_attribute_value`.
+                pub fn to_attribute_value<T: Serialize>(value: T) -> AttributeValue {
+                    try_to_attribute_value(value).unwrap_or_else(
+                        |error| panic!("the value can't be stored as JSON: {}", error)
+                    )
+                }
+
+                pub fn try_to_attribute_value<T: Serialize>(
+                    value: T
+                ) -> ConversionResult<AttributeValue> {
+                    compressed_json::try_to_attribute_value(value, Compression::$compression)
+                }
+
+                pub fn from_attribute_value<T: DeserializeOwned>(
+                    attribute_value: AttributeValue
+                ) -> ConversionResult<T> {
+                    compressed_json::from_attribute_value(attribute_value)
+                }
+            }
+        }
+    }
+}
+
+compression_module!(gzip, Gzip, "gzip");
+compression_module!(zstd, Zstd, "zstd");
+
+#[cfg(all(feature = "serde_json", any(feature = "gzip", feature = "zstd")))]
+mod compressed_json {
+    use AttributeValue;
+    use serde_crate::Serialize;
+    use serde_crate::de::DeserializeOwned;
+
+    use common_types::invalid_format;
+    use errors::ConversionError;
+    use super::{AttributeValueConverter, Compression, ConversionResult, decompress};
+
+    pub fn try_to_attribute_value<T: Serialize>(
+        value: T, compression: Compression
+    ) -> ConversionResult<AttributeValue> {
+        let document = ::serde_json::to_vec(&value)
+            .map_err(|error| ConversionError::Custom(error.to_string()))?;
+        Ok(compression.compress(&document).to_attribute_value())
+    }
+
+    pub fn from_attribute_value<T: DeserializeOwned>(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<T> {
+        let bytes = Vec::<u8>::from_attribute_value(attribute_value)?;
+        let document = decompress(&bytes)?;
+        ::serde_json::from_slice(&document).map_err(|error| invalid_format(
+            "a JSON document", &String::from_utf8_lossy(&document), error
+        ))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use AttributeValue;
+
+    use AttributeValueConverter;
+    use errors::ConversionError;
+    use super::{CompressedField, GZIP_HEADER, ZSTD_HEADER, decompress};
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    use super::Compression;
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn round_trip(compression: Compression, header: u8) {
+        let text = "korat ".repeat(1000);
+        let converted = text.clone().to_attribute_value_with(compression);
+        let bytes: Vec<u8> = converted.clone().b.unwrap();
+        assert_eq!(header, bytes[0]);
+        assert!(bytes.len() < text.len() / 10);
+        assert_eq!(text, String::from_compressed_attribute_value(converted).unwrap());
+
+        let none: Option<Vec<u8>> = None;
+        let converted = none.to_attribute_value_with(compression);
+        assert_eq!(None, Option::<Vec<u8>>::from_compressed_attribute_value(converted).unwrap());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn can_compress_with_gzip() {
+        round_trip(Compression::Gzip, GZIP_HEADER);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn can_compress_with_zstd() {
+        round_trip(Compression::Zstd, ZSTD_HEADER);
+    }
+
+    #[cfg(all(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn reads_payloads_by_their_header() {
+        let converted = "korat".to_string().to_attribute_value_with(Compression::Gzip);
+        let read: String = super::zstd::from_attribute_value(converted).unwrap();
+        assert_eq!("korat", read);
+    }
+
+    #[cfg(all(feature = "gzip", feature = "serde_json"))]
+    #[test]
+    fn can_compress_serde_types() {
+        use std::collections::BTreeMap;
+
+        let mut document = BTreeMap::new();
+        document.insert("name".to_string(), vec![1, 2, 3]);
+        let converted = super::gzip::serde::to_attribute_value(&document);
+        assert_eq!(document, super::gzip::serde::from_attribute_value(converted).unwrap());
+    }
+
+    #[test]
+    fn reports_corrupt_payloads() {
+        let corrupt = [
+            vec![],
+            vec![9, 1, 2],
+            vec![GZIP_HEADER, 1, 2],
+            vec![ZSTD_HEADER, 1, 2],
+        ];
+        for bytes in corrupt.iter() {
+            match decompress(bytes) {
+                Err(ConversionError::InvalidFormat { .. }) => (),
+                other => panic!("unexpected result {:?} for {:?}", other, bytes),
+            }
+        }
+
+        let uncompressed = "korat".to_string().to_attribute_value();
+        assert!(String::from_compressed_attribute_value(uncompressed).is_err());
+        assert!(String::from_compressed_attribute_value(AttributeValue::default()).is_err());
+    }
+}
@@ -0,0 +1,556 @@
+//! Client side encryption and signing of item attributes.
+//!
+//! Fields marked with `#[korat(encrypt)]` are encrypted with AES-256-GCM
+//! into `B` attributes. The item's key attributes and the attribute name are
+//! bound to each ciphertext as associated data, so a ciphertext copied to
+//! another item or attribute fails to decrypt.
+//!
+//! Encrypted attributes, attributes marked with `#[korat(sign)]` and the key
+//! attributes are covered by an HMAC-SHA256 signature stored in the
+//! `*korat-signature*` attribute, which detects tampering with, removal of
+//! and replay of signed attributes. Key attributes are always stored in
+//! plaintext so that the item can still be addressed.
+//!
+//! ```rust,ignore
+//! let provider = StaticKeyProvider::new(encryption_key, signing_key);
+//! let attribute_map = encryption::encrypt_item(customer, &provider)?;
+//! let customer: Customer = encryption::decrypt_item(attribute_map, &provider)?;
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use errors::{ConversionError, EncryptionError};
+use super::{
+    AttributeType, AttributeValue, AttributeValueConverter, DynamoDBInsertable, ConversionResult
+};
+
+
+/// The attribute holding the signature of an item.
+pub const SIGNATURE_ATTRIBUTE: &str = "*korat-signature*";
+
+/// The format version prefixed to every ciphertext.
+const CIPHERTEXT_VERSION: u8 = 1;
+const NONCE_LENGTH: usize = 12;
+
+pub type EncryptionKey = [u8; 32];
+
+
+/// Supplies the keys used to encrypt and sign items. The key of the item
+/// being processed is given so that keys can be selected or derived per
+/// item.
+pub trait KeyProvider {
+    fn encryption_key(
+        &self, item_key: &HashMap<String, AttributeValue>
+    ) -> Result<EncryptionKey, EncryptionError>;
+
+    fn signing_key(
+        &self, item_key: &HashMap<String, AttributeValue>
+    ) -> Result<EncryptionKey, EncryptionError>;
+}
+
+/// A key provider using the same pair of keys for every item.
+pub struct StaticKeyProvider {
+    encryption_key: EncryptionKey,
+    signing_key: EncryptionKey,
+}
+
+impl StaticKeyProvider {
+    pub fn new(encryption_key: EncryptionKey, signing_key: EncryptionKey) -> StaticKeyProvider {
+        StaticKeyProvider { encryption_key, signing_key }
+    }
+}
+
+impl KeyProvider for StaticKeyProvider {
+    fn encryption_key(
+        &self, _item_key: &HashMap<String, AttributeValue>
+    ) -> Result<EncryptionKey, EncryptionError> {
+        Ok(self.encryption_key)
+    }
+
+    fn signing_key(
+        &self, _item_key: &HashMap<String, AttributeValue>
+    ) -> Result<EncryptionKey, EncryptionError> {
+        Ok(self.signing_key)
+    }
+}
+
+
+/// An item with encrypted or signed attributes. Derived for items with
+/// fields marked with `#[korat(encrypt)]` or `#[korat(sign)]`.
+pub trait EncryptedItem: DynamoDBInsertable {
+    fn key_attribute_names() -> Vec<String>;
+    fn encrypted_attribute_names() -> Vec<String>;
+    fn signed_attribute_names() -> Vec<String>;
+}
+
+/// Converts an item into an attribute map with its encrypted attributes
+/// encrypted and a signature added.
+pub fn encrypt_item<T, P>(
+    item: T, provider: &P
+) -> Result<HashMap<String, AttributeValue>, EncryptionError>
+    where T: EncryptedItem, P: KeyProvider + ?Sized {
+    let item_key = item.get_key();
+    let mut attribute_map: HashMap<String, AttributeValue> = item.into();
+    let associated_data = encode_attributes(&item_key, item_key.keys())?;
+
+    let encryption_key = provider.encryption_key(&item_key)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&encryption_key));
+    for name in T::encrypted_attribute_names() {
+        let plaintext = match attribute_map.remove(&name) {
+            Some(attribute_value) => encode(&attribute_value)?,
+            None => continue,
+        };
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, Payload {
+            msg: &plaintext,
+            aad: &field_associated_data(&associated_data, &name),
+        }).map_err(|_| EncryptionError::EncryptionFailed(name.clone()))?;
+
+        let mut encrypted = vec![CIPHERTEXT_VERSION];
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend(ciphertext);
+        attribute_map.insert(name, encrypted.to_attribute_value());
+    }
+
+    let signature = sign::<T>(&attribute_map, &provider.signing_key(&item_key)?)?;
+    attribute_map.insert(SIGNATURE_ATTRIBUTE.to_string(), signature.to_attribute_value());
+    Ok(attribute_map)
+}
+
+/// Verifies the signature of an attribute map produced by `encrypt_item`,
+/// decrypts its encrypted attributes and converts it into an item.
+pub fn decrypt_item<T, P>(
+    mut attribute_map: HashMap<String, AttributeValue>, provider: &P
+) -> Result<T, EncryptionError>
+    where T: EncryptedItem, P: KeyProvider + ?Sized {
+    let item_key: HashMap<String, AttributeValue> = T::key_attribute_names().into_iter()
+        .filter_map(|name| {
+            let value = attribute_map.get(&name).cloned();
+            value.map(|value| (name, value))
+        })
+        .collect();
+
+    let signature = attribute_map.remove(SIGNATURE_ATTRIBUTE)
+        .ok_or(EncryptionError::MissingSignature)
+        .and_then(|signature| Ok(Vec::<u8>::from_attribute_value(signature)?))?;
+    let mac = signature_mac::<T>(&attribute_map, &provider.signing_key(&item_key)?)?;
+    mac.verify_slice(&signature).map_err(|_| EncryptionError::InvalidSignature)?;
+
+    let associated_data = encode_attributes(&item_key, item_key.keys())?;
+    let encryption_key = provider.encryption_key(&item_key)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&encryption_key));
+    for name in T::encrypted_attribute_names() {
+        let encrypted = match attribute_map.remove(&name) {
+            Some(attribute_value) => Vec::<u8>::from_attribute_value(attribute_value)?,
+            None => continue,
+        };
+        let decryption_failed = || EncryptionError::DecryptionFailed(name.clone());
+        if encrypted.len() < 1 + NONCE_LENGTH || encrypted[0] != CIPHERTEXT_VERSION {
+            return Err(decryption_failed());
+        }
+        let (nonce, ciphertext) = encrypted[1..].split_at(NONCE_LENGTH);
+        let plaintext = cipher.decrypt(Nonce::from_slice(nonce), Payload {
+            msg: ciphertext,
+            aad: &field_associated_data(&associated_data, &name),
+        }).map_err(|_| decryption_failed())?;
+        attribute_map.insert(name, decode(&plaintext)?);
+    }
+
+    Ok(T::try_from(attribute_map)?)
+}
+
+
+fn field_associated_data(item_key: &[u8], name: &str) -> Vec<u8> {
+    let mut associated_data = item_key.to_vec();
+    write_bytes(name.as_bytes(), &mut associated_data);
+    associated_data
+}
+
+fn sign<T: EncryptedItem>(
+    attribute_map: &HashMap<String, AttributeValue>, signing_key: &EncryptionKey
+) -> Result<Vec<u8>, EncryptionError> {
+    let mac = signature_mac::<T>(attribute_map, signing_key)?;
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// Feeds the key, encrypted and signed attributes of an item to a MAC, in
+/// the canonical encoding and in the order of their names.
+fn signature_mac<T: EncryptedItem>(
+    attribute_map: &HashMap<String, AttributeValue>, signing_key: &EncryptionKey
+) -> Result<Hmac<Sha256>, EncryptionError> {
+    let names: BTreeSet<String> = T::key_attribute_names().into_iter()
+        .chain(T::encrypted_attribute_names())
+        .chain(T::signed_attribute_names())
+        .collect();
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(signing_key)
+        .expect("HMAC accepts keys of any length");
+    mac.update(&encode_attributes(attribute_map, names.iter())?);
+    Ok(mac)
+}
+
+
+/// Encodes the named attributes of a map. Absent attributes are encoded
+/// along with their name, so that removing a signed attribute invalidates
+/// the signature.
+fn encode_attributes<'a, I>(
+    attribute_map: &HashMap<String, AttributeValue>, names: I
+) -> ConversionResult<Vec<u8>>
+    where I: Iterator<Item = &'a String> {
+    let mut names: Vec<&String> = names.collect();
+    names.sort();
+    let mut encoded = vec![];
+    for name in names {
+        write_bytes(name.as_bytes(), &mut encoded);
+        match attribute_map.get(name) {
+            Some(attribute_value) => write_attribute_value(attribute_value, &mut encoded)?,
+            None => encoded.push(ABSENT),
+        }
+    }
+    Ok(encoded)
+}
+
+/// Encodes an attribute value in the canonical binary form used for
+/// encryption and signing, in which map entries and set members are sorted.
+fn encode(attribute_value: &AttributeValue) -> ConversionResult<Vec<u8>> {
+    let mut encoded = vec![];
+    write_attribute_value(attribute_value, &mut encoded)?;
+    Ok(encoded)
+}
+
+fn decode(bytes: &[u8]) -> ConversionResult<AttributeValue> {
+    let mut reader = Reader { bytes, position: 0 };
+    let attribute_value = reader.attribute_value()?;
+    if reader.position != bytes.len() {
+        return Err(reader.invalid("trailing bytes"));
+    }
+    Ok(attribute_value)
+}
+
+const ABSENT: u8 = 0;
+
+fn type_tag(attribute_type: AttributeType) -> u8 {
+    match attribute_type {
+        AttributeType::S => 1,
+        AttributeType::N => 2,
+        AttributeType::B => 3,
+        AttributeType::Bool => 4,
+        AttributeType::Null => 5,
+        AttributeType::M => 6,
+        AttributeType::L => 7,
+        AttributeType::SS => 8,
+        AttributeType::NS => 9,
+        AttributeType::BS => 10,
+    }
+}
+
+fn write_bytes(bytes: &[u8], encoded: &mut Vec<u8>) {
+    encoded.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    encoded.extend_from_slice(bytes);
+}
+
+fn write_members<T: AsRef<[u8]>>(members: &[T], encoded: &mut Vec<u8>) {
+    let mut members: Vec<&[u8]> = members.iter().map(AsRef::as_ref).collect();
+    members.sort();
+    encoded.extend_from_slice(&(members.len() as u32).to_be_bytes());
+    for member in members {
+        write_bytes(member, encoded);
+    }
+}
+
+fn write_attribute_value(
+    attribute_value: &AttributeValue, encoded: &mut Vec<u8>
+) -> ConversionResult<()> {
+    let attribute_type = match AttributeType::single(attribute_value) {
+        Ok(attribute_type) => attribute_type,
+        Err(ConversionError::MissingValue) => {
+            encoded.push(ABSENT);
+            return Ok(());
+        },
+        Err(error) => return Err(error),
+    };
+    encoded.push(type_tag(attribute_type));
+    let missing = ConversionError::MissingValue;
+    match attribute_type {
+        AttributeType::S => write_bytes(
+            attribute_value.s.as_ref().ok_or(missing)?.as_bytes(), encoded
+        ),
+        AttributeType::N => write_bytes(
+            attribute_value.n.as_ref().ok_or(missing)?.as_bytes(), encoded
+        ),
+        AttributeType::B => write_bytes(attribute_value.b.as_ref().ok_or(missing)?, encoded),
+        AttributeType::Bool => encoded.push(attribute_value.bool.ok_or(missing)? as u8),
+        AttributeType::Null => encoded.push(attribute_value.null.ok_or(missing)? as u8),
+        AttributeType::M => {
+            let map = attribute_value.m.as_ref().ok_or(missing)?;
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|first, second| first.0.cmp(second.0));
+            encoded.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+            for (name, value) in entries {
+                write_bytes(name.as_bytes(), encoded);
+                write_attribute_value(value, encoded)?;
+            }
+        },
+        AttributeType::L => {
+            let list = attribute_value.l.as_ref().ok_or(missing)?;
+            encoded.extend_from_slice(&(list.len() as u32).to_be_bytes());
+            for value in list {
+                write_attribute_value(value, encoded)?;
+            }
+        },
+        AttributeType::SS => write_members(attribute_value.ss.as_ref().ok_or(missing)?, encoded),
+        AttributeType::NS => write_members(attribute_value.ns.as_ref().ok_or(missing)?, encoded),
+        AttributeType::BS => write_members(attribute_value.bs.as_ref().ok_or(missing)?, encoded),
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn invalid(&self, reason: &str) -> ConversionError {
+        ConversionError::InvalidFormat {
+            expected: "an encoded attribute value",
+            value: format!("{} bytes", self.bytes.len()),
+            reason: format!("{} at byte {}", reason, self.position),
+        }
+    }
+
+    fn take(&mut self, length: usize) -> ConversionResult<&'a [u8]> {
+        if self.bytes.len() - self.position < length {
+            return Err(self.invalid("unexpected end of input"));
+        }
+        let taken = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> ConversionResult<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn length(&mut self) -> ConversionResult<usize> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn bytes(&mut self) -> ConversionResult<Vec<u8>> {
+        let length = self.length()?;
+        self.take(length).map(<[u8]>::to_vec)
+    }
+
+    fn string(&mut self) -> ConversionResult<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes).map_err(|_| self.invalid("invalid UTF-8"))
+    }
+
+    fn repeated<T, F>(&mut self, mut read: F) -> ConversionResult<Vec<T>>
+        where F: FnMut(&mut Self) -> ConversionResult<T> {
+        let count = self.length()?;
+        (0..count).map(|_| read(self)).collect()
+    }
+
+    fn attribute_value(&mut self) -> ConversionResult<AttributeValue> {
+        let mut attribute_value = AttributeValue::default();
+        match self.byte()? {
+            ABSENT => (),
+            1 => attribute_value.s = Some(self.string()?),
+            2 => attribute_value.n = Some(self.string()?),
+            3 => attribute_value.b = Some(self.bytes()?),
+            4 => attribute_value.bool = Some(self.byte()? != 0),
+            5 => attribute_value.null = Some(self.byte()? != 0),
+            6 => attribute_value.m = Some(
+                self.repeated(|reader| Ok((reader.string()?, reader.attribute_value()?)))?
+                    .into_iter()
+                    .collect()
+            ),
+            7 => attribute_value.l = Some(self.repeated(Reader::attribute_value)?),
+            8 => attribute_value.ss = Some(self.repeated(Reader::string)?),
+            9 => attribute_value.ns = Some(self.repeated(Reader::string)?),
+            10 => attribute_value.bs = Some(
+                self.repeated(|reader| reader.bytes())?
+            ),
+            _ => return Err(self.invalid("unknown type tag")),
+        }
+        Ok(attribute_value)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use AttributeValue;
+
+    use {AttributeValueConverter, DynamoDBInsertable, DynamoDBItem};
+    use errors::{ConversionError, EncryptionError};
+    use super::{
+        EncryptedItem, SIGNATURE_ATTRIBUTE, StaticKeyProvider, decode, decrypt_item,
+        encode, encrypt_item, sign
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Customer {
+        id: String,
+        email: String,
+        tier: String,
+        notes: String,
+    }
+
+    impl DynamoDBItem for Customer {
+        fn get_attribute_names() -> Vec<String> {
+            vec!["id", "email", "tier", "notes"].into_iter().map(String::from).collect()
+        }
+    }
+
+    impl DynamoDBInsertable for Customer {
+        fn get_key(&self) -> HashMap<String, AttributeValue> {
+            let mut key = HashMap::new();
+            key.insert("id".to_string(), self.id.clone().to_attribute_value());
+            key
+        }
+    }
+
+    impl EncryptedItem for Customer {
+        fn key_attribute_names() -> Vec<String> {
+            vec!["id".to_string()]
+        }
+
+        fn encrypted_attribute_names() -> Vec<String> {
+            vec!["email".to_string()]
+        }
+
+        fn signed_attribute_names() -> Vec<String> {
+            vec!["tier".to_string()]
+        }
+    }
+
+    impl TryFrom<HashMap<String, AttributeValue>> for Customer {
+        type Error = ConversionError;
+
+        fn try_from(
+            mut attribute_map: HashMap<String, AttributeValue>
+        ) -> Result<Self, Self::Error> {
+            let mut field = |name: &str| String::from_attribute_value(
+                attribute_map.remove(name).ok_or(ConversionError::MissingField)?
+            );
+            Ok(Customer {
+                id: field("id")?,
+                email: field("email")?,
+                tier: field("tier")?,
+                notes: field("notes")?,
+            })
+        }
+    }
+
+    impl From<Customer> for HashMap<String, AttributeValue> {
+        fn from(customer: Customer) -> Self {
+            let mut attribute_map = HashMap::new();
+            attribute_map.insert("id".to_string(), customer.id.to_attribute_value());
+            attribute_map.insert("email".to_string(), customer.email.to_attribute_value());
+            attribute_map.insert("tier".to_string(), customer.tier.to_attribute_value());
+            attribute_map.insert("notes".to_string(), customer.notes.to_attribute_value());
+            attribute_map
+        }
+    }
+
+    fn customer(id: &str) -> Customer {
+        Customer {
+            id: id.to_string(),
+            email: format!("{}@example.com", id),
+            tier: "gold".to_string(),
+            notes: "unprotected".to_string(),
+        }
+    }
+
+    fn provider() -> StaticKeyProvider {
+        StaticKeyProvider::new([1; 32], [2; 32])
+    }
+
+    #[test]
+    fn can_encrypt_and_decrypt_items() {
+        let encrypted = encrypt_item(customer("first"), &provider()).unwrap();
+        assert!(encrypted["email"].b.is_some());
+        assert_eq!("gold", &encrypted["tier"].clone().s.unwrap());
+        assert!(encrypted.contains_key(SIGNATURE_ATTRIBUTE));
+
+        let decrypted: Customer = decrypt_item(encrypted, &provider()).unwrap();
+        assert_eq!(customer("first"), decrypted);
+    }
+
+    #[test]
+    fn rejects_tampered_signed_attributes() {
+        let mut encrypted = encrypt_item(customer("first"), &provider()).unwrap();
+        encrypted.insert("tier".to_string(), "platinum".to_string().to_attribute_value());
+        match decrypt_item::<Customer, _>(encrypted, &provider()) {
+            Err(EncryptionError::InvalidSignature) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut encrypted = encrypt_item(customer("first"), &provider()).unwrap();
+        encrypted.insert("notes".to_string(), "changed".to_string().to_attribute_value());
+        assert!(decrypt_item::<Customer, _>(encrypted, &provider()).is_ok());
+    }
+
+    #[test]
+    fn rejects_ciphertexts_moved_between_items() {
+        let first = encrypt_item(customer("first"), &provider()).unwrap();
+        let mut second = encrypt_item(customer("second"), &provider()).unwrap();
+        second.insert("email".to_string(), first["email"].clone());
+        match decrypt_item::<Customer, _>(second.clone(), &provider()) {
+            Err(EncryptionError::InvalidSignature) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        second.remove(SIGNATURE_ATTRIBUTE);
+        let signature = sign::<Customer>(&second, &[2; 32]).unwrap();
+        second.insert(SIGNATURE_ATTRIBUTE.to_string(), signature.to_attribute_value());
+        match decrypt_item::<Customer, _>(second, &provider()) {
+            Err(EncryptionError::DecryptionFailed(name)) => assert_eq!("email", name),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let wrong_keys = StaticKeyProvider::new([3; 32], [2; 32]);
+        match decrypt_item::<Customer, _>(first, &wrong_keys) {
+            Err(EncryptionError::DecryptionFailed(name)) => assert_eq!("email", name),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn requires_a_signature() {
+        let mut encrypted = encrypt_item(customer("first"), &provider()).unwrap();
+        encrypted.remove(SIGNATURE_ATTRIBUTE);
+        match decrypt_item::<Customer, _>(encrypted, &provider()) {
+            Err(EncryptionError::MissingSignature) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encodes_attribute_values_reversibly() {
+        let attribute_value: AttributeValue = ::value::Value::from(vec![
+            ::value::Value::from("korat"),
+            ::value::Value::N("1.5".parse().unwrap()),
+            ::value::Value::Null,
+            ::value::Value::SS(vec!["b".to_string(), "a".to_string()]),
+        ]).to_attribute_value();
+        let decoded = decode(&encode(&attribute_value).unwrap()).unwrap();
+        let list = decoded.l.unwrap();
+        assert_eq!("korat", &list[0].clone().s.unwrap());
+        assert_eq!("1.5", &list[1].clone().n.unwrap());
+        assert_eq!(Some(true), list[2].null);
+        assert_eq!(vec!["a".to_string(), "b".to_string()], list[3].clone().ss.unwrap());
+
+        assert!(decode(&[6, 0, 0, 0, 1]).is_err());
+    }
+}
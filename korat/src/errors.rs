@@ -1,3 +1,11 @@
+use std::convert::Infallible;
+#[cfg(feature = "export")]
+use std::io;
+#[cfg(feature = "export")]
+use std::path::PathBuf;
+
+use attribute_type::AttributeType;
+
 quick_error! {
     #[derive(Debug)]
     pub enum ConversionError {
@@ -7,5 +15,72 @@ quick_error! {
         }
         InvalidValue  {
         }
+        MalformedValue(types: Vec<AttributeType>) {
+            display("attribute value has more than one member set: {:?}", types)
+        }
+        TypeMismatch { expected: AttributeType, found: AttributeType } {
+            display("expected a {} attribute value but found {}", expected, found)
+        }
+        InvalidFormat { expected: &'static str, value: String, reason: String } {
+            display("expected {} but found {:?}: {}", expected, value, reason)
+        }
+        Custom(message: String) {
+            display("{}", message)
+        }
+    }
+}
+
+/// Lets conversions which can't fail, such as reading a plain attribute map,
+/// be used where a `ConversionError` is expected.
+impl From<Infallible> for ConversionError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+#[cfg(feature = "encryption")]
+quick_error! {
+    #[derive(Debug)]
+    pub enum EncryptionError {
+        KeyProvider(reason: String) {
+            display("failed to provide keys: {}", reason)
+        }
+        MissingSignature {
+            display("the item has no signature")
+        }
+        InvalidSignature {
+            display("the item signature doesn't match its attributes")
+        }
+        EncryptionFailed(attribute: String) {
+            display("failed to encrypt attribute {}", attribute)
+        }
+        DecryptionFailed(attribute: String) {
+            display("failed to decrypt attribute {}", attribute)
+        }
+        Conversion(err: ConversionError) {
+            from()
+            display("failed to convert encrypted item: {}", err)
+        }
+    }
+}
+
+#[cfg(feature = "export")]
+quick_error! {
+    #[derive(Debug)]
+    pub enum ExportError {
+        Io(err: io::Error) {
+            from()
+            display("failed to read export: {}", err)
+        }
+        InvalidManifest(reason: String) {
+            display("invalid export manifest: {}", reason)
+        }
+        InvalidData(path: PathBuf, line: usize, reason: String) {
+            display("invalid item at {}:{}: {}", path.display(), line, reason)
+        }
+        Conversion(err: ConversionError) {
+            from()
+            display("failed to convert exported item: {}", err)
+        }
     }
 }
@@ -0,0 +1,440 @@
+//! A reader for the subset of the Amazon Ion text format written by DynamoDB
+//! exports.
+//!
+//! Every line of an export holds a struct such as
+//! `{Item:{id:"1",count:3,tags:$dynamodb_SS::["a","b"]}}`, optionally
+//! preceded by the `$ion_1_0` version marker. Strings, symbols, integers,
+//! decimals, floats, blobs, booleans, nulls, lists and structs are
+//! supported, along with the `$dynamodb_SS`, `$dynamodb_NS` and
+//! `$dynamodb_BS` annotations marking sets.
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use AttributeValue;
+
+
+type ParseResult<T> = Result<T, String>;
+
+
+/// Reads the item held by a line of an Ion export. Blank lines and lines
+/// holding only the version marker have no item.
+pub fn parse_item(line: &str) -> ParseResult<Option<HashMap<String, AttributeValue>>> {
+    let mut parser = Parser::new(line);
+    parser.skip_whitespace();
+    if parser.consume_keyword("$ion_1_0") {
+        parser.skip_whitespace();
+    }
+    if parser.is_done() {
+        return Ok(None);
+    }
+
+    let mut record = parser.parse_value()?.m
+        .ok_or("expected a struct holding the item")?;
+    parser.skip_whitespace();
+    if !parser.is_done() {
+        return Err(format!("unexpected input after the item at {}", parser.position));
+    }
+    record.remove("Item")
+        .and_then(|item| item.m)
+        .map(Some)
+        .ok_or_else(|| "expected an Item struct".to_string())
+}
+
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Parser {
+        Parser { chars: text.chars().collect(), position: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).cloned()
+    }
+
+    fn is_done(&self) -> bool {
+        self.position >= self.chars.len()
+    }
+
+    fn next(&mut self) -> ParseResult<char> {
+        let next = self.peek().ok_or("unexpected end of input")?;
+        self.position += 1;
+        Ok(next)
+    }
+
+    fn expect(&mut self, expected: char) -> ParseResult<()> {
+        match self.next()? {
+            found if found == expected => Ok(()),
+            found => Err(format!(
+                "expected {:?} but found {:?} at {}", expected, found, self.position - 1
+            )),
+        }
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        prefix.chars().enumerate().all(|(offset, c)| self.peek_at(offset) == Some(c))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes a keyword when it is not the prefix of a longer symbol.
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        let length = keyword.chars().count();
+        let is_boundary = self.peek_at(length).is_none_or(|c| !is_symbol_char(c));
+        if self.starts_with(keyword) && is_boundary {
+            self.position += length;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<AttributeValue> {
+        self.skip_whitespace();
+        let annotations = self.parse_annotations()?;
+        let value = self.parse_unannotated_value()?;
+        match annotations.iter().map(String::as_str).find(|a| a.starts_with("$dynamodb_")) {
+            Some("$dynamodb_SS") => Ok(AttributeValue {
+                ss: Some(set_members(value, |member| member.s)?),
+                ..AttributeValue::default()
+            }),
+            Some("$dynamodb_NS") => Ok(AttributeValue {
+                ns: Some(set_members(value, |member| member.n)?),
+                ..AttributeValue::default()
+            }),
+            Some("$dynamodb_BS") => Ok(AttributeValue {
+                bs: Some(set_members(value, |member| member.b)?),
+                ..AttributeValue::default()
+            }),
+            Some(unknown) => Err(format!("unknown annotation {}", unknown)),
+            None => Ok(value),
+        }
+    }
+
+    fn parse_annotations(&mut self) -> ParseResult<Vec<String>> {
+        let mut annotations = vec![];
+        loop {
+            let start = self.position;
+            let symbol = match self.peek() {
+                Some('\'') if !self.starts_with("'''") => self.parse_quoted('\'')?,
+                Some(c) if is_symbol_start(c) => self.parse_identifier(),
+                _ => return Ok(annotations),
+            };
+            self.skip_whitespace();
+            if self.starts_with("::") {
+                self.position += 2;
+                self.skip_whitespace();
+                annotations.push(symbol);
+            } else {
+                self.position = start;
+                return Ok(annotations);
+            }
+        }
+    }
+
+    fn parse_unannotated_value(&mut self) -> ParseResult<AttributeValue> {
+        match self.peek().ok_or("unexpected end of input")? {
+            '{' if self.peek_at(1) == Some('{') => self.parse_blob(),
+            '{' => self.parse_struct(),
+            '[' => self.parse_list(),
+            '"' => self.parse_quoted('"').map(string),
+            '\'' if self.starts_with("'''") => self.parse_long_string().map(string),
+            '\'' => self.parse_quoted('\'').map(string),
+            '-' | '+' | '0'..='9' => self.parse_number(),
+            c if is_symbol_start(c) => self.parse_keyword_or_symbol(),
+            c => Err(format!("unexpected {:?} at {}", c, self.position)),
+        }
+    }
+
+    fn parse_keyword_or_symbol(&mut self) -> ParseResult<AttributeValue> {
+        let symbol = self.parse_identifier();
+        match symbol.as_str() {
+            "true" | "false" => Ok(AttributeValue {
+                bool: Some(symbol == "true"),
+                ..AttributeValue::default()
+            }),
+            "null" => {
+                // Typed nulls such as null.string are nulls all the same.
+                if self.peek() == Some('.') {
+                    self.position += 1;
+                    self.parse_identifier();
+                }
+                Ok(AttributeValue {
+                    null: Some(true),
+                    ..AttributeValue::default()
+                })
+            },
+            "nan" => Err("nan can't be stored as a number".to_string()),
+            _ => Ok(string(symbol)),
+        }
+    }
+
+    fn parse_identifier(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(is_symbol_char) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn parse_quoted(&mut self, quote: char) -> ParseResult<String> {
+        self.expect(quote)?;
+        let mut string = String::new();
+        loop {
+            match self.next()? {
+                c if c == quote => return Ok(string),
+                '\\' => string.push(self.parse_escape()?),
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Long strings are written as one or more `'''` delimited segments which
+    /// are concatenated.
+    fn parse_long_string(&mut self) -> ParseResult<String> {
+        let mut string = String::new();
+        while self.starts_with("'''") {
+            self.position += 3;
+            while !self.starts_with("'''") {
+                match self.next()? {
+                    '\\' => string.push(self.parse_escape()?),
+                    c => string.push(c),
+                }
+            }
+            self.position += 3;
+            self.skip_whitespace();
+        }
+        Ok(string)
+    }
+
+    fn parse_escape(&mut self) -> ParseResult<char> {
+        let escaped = match self.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            'a' => '\u{07}',
+            'b' => '\u{08}',
+            'f' => '\u{0C}',
+            'v' => '\u{0B}',
+            'x' => return self.parse_code_point(2),
+            'u' => return self.parse_code_point(4),
+            'U' => return self.parse_code_point(8),
+            c @ '"' | c @ '\'' | c @ '\\' | c @ '/' | c @ '?' => c,
+            c => return Err(format!("unknown escape sequence \\{}", c)),
+        };
+        Ok(escaped)
+    }
+
+    fn parse_code_point(&mut self, digits: usize) -> ParseResult<char> {
+        let mut hex = String::new();
+        for _ in 0..digits {
+            hex.push(self.next()?);
+        }
+        u32::from_str_radix(&hex, 16).ok()
+            .and_then(::std::char::from_u32)
+            .ok_or_else(|| format!("invalid escaped code point {}", hex))
+    }
+
+    fn parse_number(&mut self) -> ParseResult<AttributeValue> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || "+-._".contains(c)) {
+            self.position += 1;
+        }
+        let literal: String = self.chars[start..self.position].iter()
+            .filter(|&&c| c != '_')
+            .collect();
+        if literal == "+inf" || literal == "-inf" {
+            return Err(format!("{} can't be stored as a number", literal));
+        }
+
+        // Decimals use d as their exponent marker and may end with a bare
+        // point, neither of which DynamoDB numbers allow.
+        let mut number = literal.replace(['d', 'D'], "E");
+        if let Some(point) = number.find('.') {
+            let mantissa_end = number.find(['E', 'e']).unwrap_or(number.len());
+            if point + 1 == mantissa_end {
+                number.remove(point);
+            }
+        }
+        if number.parse::<f64>().is_err() {
+            return Err(format!("invalid number {}", literal));
+        }
+        Ok(AttributeValue {
+            n: Some(number),
+            ..AttributeValue::default()
+        })
+    }
+
+    fn parse_blob(&mut self) -> ParseResult<AttributeValue> {
+        self.position += 2;
+        let mut encoded = String::new();
+        while !self.starts_with("}}") {
+            let c = self.next()?;
+            if !c.is_whitespace() {
+                encoded.push(c);
+            }
+        }
+        self.position += 2;
+        let bytes = BASE64.decode(&encoded)
+            .map_err(|error| format!("invalid blob {}: {}", encoded, error))?;
+        Ok(AttributeValue {
+            b: Some(bytes),
+            ..AttributeValue::default()
+        })
+    }
+
+    fn parse_list(&mut self) -> ParseResult<AttributeValue> {
+        self.expect('[')?;
+        let mut elements = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.position += 1;
+                break;
+            }
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => (),
+                ']' => break,
+                c => return Err(format!("expected , or ] but found {:?}", c)),
+            }
+        }
+        Ok(AttributeValue {
+            l: Some(elements),
+            ..AttributeValue::default()
+        })
+    }
+
+    fn parse_struct(&mut self) -> ParseResult<AttributeValue> {
+        self.expect('{')?;
+        let mut fields = HashMap::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                self.position += 1;
+                break;
+            }
+            let name = match self.peek() {
+                Some('"') => self.parse_quoted('"')?,
+                Some('\'') if self.starts_with("'''") => self.parse_long_string()?,
+                Some('\'') => self.parse_quoted('\'')?,
+                Some(c) if is_symbol_start(c) => self.parse_identifier(),
+                other => return Err(format!("expected a field name but found {:?}", other)),
+            };
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.insert(name, self.parse_value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => (),
+                '}' => break,
+                c => return Err(format!("expected , or }} but found {:?}", c)),
+            }
+        }
+        Ok(AttributeValue {
+            m: Some(fields),
+            ..AttributeValue::default()
+        })
+    }
+}
+
+fn is_symbol_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+fn string(value: String) -> AttributeValue {
+    AttributeValue {
+        s: Some(value),
+        ..AttributeValue::default()
+    }
+}
+
+/// Reads the members of a list annotated as a set, which should all be of
+/// the set's type.
+fn set_members<T, F>(value: AttributeValue, member: F) -> ParseResult<Vec<T>>
+    where F: Fn(AttributeValue) -> Option<T> {
+    value.l.ok_or("sets should be written as lists")?
+        .into_iter()
+        .map(|element| member(element).ok_or_else(
+            || "set members should all be of the set type".to_string()
+        ))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::parse_item;
+
+    #[test]
+    fn reads_exported_items() {
+        let line = concat!(
+            r#"$ion_1_0 {Item:{id:"103",'count':600,price:20.50,ratio:1.5d-1,"#,
+            r#"total:2000.,big:1_000,avatar:{{AQID}},active:false,deleted:null,"#,
+            r#"address:{"city":"Athens"},history:["aé",1e3],"#,
+            r#"tags:$dynamodb_SS::["a","b"],scores:$dynamodb_NS::[1,2.5],"#,
+            r#"keys:$dynamodb_BS::[{{AQ==}}],note:'''long '''  '''string'''}}"#
+        );
+        let item = parse_item(line).unwrap().unwrap();
+
+        assert_eq!("103", &item["id"].clone().s.unwrap());
+        assert_eq!("600", &item["count"].clone().n.unwrap());
+        assert_eq!("20.50", &item["price"].clone().n.unwrap());
+        assert_eq!("1.5E-1", &item["ratio"].clone().n.unwrap());
+        assert_eq!("2000", &item["total"].clone().n.unwrap());
+        assert_eq!("1000", &item["big"].clone().n.unwrap());
+        let avatar: Vec<u8> = item["avatar"].clone().b.unwrap();
+        assert_eq!(vec![1, 2, 3], avatar);
+        assert_eq!(Some(false), item["active"].bool);
+        assert_eq!(Some(true), item["deleted"].null);
+        assert_eq!("Athens", &item["address"].clone().m.unwrap()["city"].clone().s.unwrap());
+        let history = item["history"].clone().l.unwrap();
+        assert_eq!("a\u{e9}", &history[0].clone().s.unwrap());
+        assert_eq!("1e3", &history[1].clone().n.unwrap());
+        assert_eq!(vec!["a".to_string(), "b".to_string()], item["tags"].clone().ss.unwrap());
+        assert_eq!(vec!["1".to_string(), "2.5".to_string()], item["scores"].clone().ns.unwrap());
+        assert_eq!(1, item["keys"].clone().bs.unwrap().len());
+        assert_eq!("long string", &item["note"].clone().s.unwrap());
+    }
+
+    #[test]
+    fn skips_lines_without_items() {
+        assert!(parse_item("").unwrap().is_none());
+        assert!(parse_item("$ion_1_0").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let invalid = [
+            "{Item:{id:\"1\"}",
+            "{Item:{id:$dynamodb_SS::[1]}}",
+            "{Item:{id:$dynamodb_XS::[1]}}",
+            "{Other:{}}",
+            "{Item:{n:1.2.3}}",
+            "{Item:{}} {Item:{}}",
+        ];
+        for line in invalid.iter() {
+            assert!(parse_item(line).is_err(), "{} should be rejected", line);
+        }
+    }
+}
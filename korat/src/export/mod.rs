@@ -0,0 +1,356 @@
+//! Reads the items of a DynamoDB table export (Export to S3) which has been
+//! downloaded to a local directory.
+//!
+//! An export directory holds a `manifest-summary.json` describing the export,
+//! a `manifest-files.json` listing its data files and a `data` directory with
+//! the data files themselves, gzipped or not, in either the DynamoDB JSON or
+//! the Amazon Ion format. Items are decoded one line at a time so whole files
+//! are never loaded in memory.
+//!
+//! ```rust,ignore
+//! let export = Export::open("exports/AWSDynamoDB/01234-abcd/manifest-summary.json")?;
+//! for order in export.items::<Order>() {
+//!     let order = order?;
+//! }
+//! ```
+
+mod ion;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use flate2::read::GzDecoder;
+use serde_json::Value;
+
+use AttributeValue;
+use errors::{ConversionError, ExportError};
+use json;
+
+
+/// The format of the data files of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    DynamoDBJson,
+    Ion,
+}
+
+/// A table export described by its manifest.
+#[derive(Debug, Clone)]
+pub struct Export {
+    format: ExportFormat,
+    item_count: Option<u64>,
+    data_files: Vec<PathBuf>,
+}
+
+impl Export {
+    /// Reads the manifest summary of an export along with the manifest of its
+    /// data files, which is expected next to it.
+    pub fn open<P: AsRef<Path>>(manifest_summary: P) -> Result<Export, ExportError> {
+        let manifest_summary = manifest_summary.as_ref();
+        let directory = manifest_summary.parent().unwrap_or_else(|| Path::new(""));
+        let summary = read_json(File::open(manifest_summary)?)?;
+
+        let format = match summary.get("exportFormat").and_then(Value::as_str) {
+            Some("DYNAMODB_JSON") => ExportFormat::DynamoDBJson,
+            Some("ION") => ExportFormat::Ion,
+            other => return Err(ExportError::InvalidManifest(
+                format!("unsupported export format {:?}", other)
+            )),
+        };
+        let item_count = summary.get("itemCount").and_then(Value::as_u64);
+        let manifest_files = summary.get("manifestFilesS3Key")
+            .and_then(Value::as_str)
+            .map_or_else(
+                || directory.join("manifest-files.json"),
+                |key| directory.join(file_name(key))
+            );
+
+        let mut data_files = vec![];
+        for line in BufReader::new(File::open(manifest_files)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = read_json(line.as_bytes())?;
+            let key = entry.get("dataFileS3Key").and_then(Value::as_str)
+                .ok_or_else(|| ExportError::InvalidManifest(
+                    format!("data file entry without a dataFileS3Key: {}", line)
+                ))?;
+            data_files.push(directory.join("data").join(file_name(key)));
+        }
+
+        Ok(Export { format, item_count, data_files })
+    }
+
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+
+    /// The number of items in the export, as reported by the manifest.
+    pub fn item_count(&self) -> Option<u64> {
+        self.item_count
+    }
+
+    pub fn data_files(&self) -> &[PathBuf] {
+        &self.data_files
+    }
+
+    /// Iterates over the items of all the data files of the export.
+    pub fn items<T>(&self) -> Items<T>
+        where T: TryFrom<HashMap<String, AttributeValue>>, ConversionError: From<T::Error> {
+        Items {
+            format: self.format,
+            data_files: self.data_files.clone().into_iter(),
+            current: None,
+            item_type: PhantomData,
+        }
+    }
+}
+
+/// Reads the items of the export described by a manifest summary.
+pub fn read_items<T, P>(manifest_summary: P) -> Result<Items<T>, ExportError>
+    where P: AsRef<Path>,
+          T: TryFrom<HashMap<String, AttributeValue>>, ConversionError: From<T::Error> {
+    Export::open(manifest_summary).map(|export| export.items())
+}
+
+
+/// An iterator over the items of an export, decoding them into `T`.
+pub struct Items<T> {
+    format: ExportFormat,
+    data_files: vec::IntoIter<PathBuf>,
+    current: Option<DataFile>,
+    item_type: PhantomData<T>,
+}
+
+struct DataFile {
+    path: PathBuf,
+    lines: Box<dyn Iterator<Item = ::std::io::Result<String>>>,
+    line_number: usize,
+}
+
+impl DataFile {
+    fn open(path: PathBuf) -> Result<DataFile, ExportError> {
+        let file = File::open(&path)?;
+        let is_gzipped = path.extension().is_some_and(|extension| extension == "gz");
+        let reader: Box<dyn Read> = if is_gzipped {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Ok(DataFile {
+            path,
+            lines: Box::new(BufReader::new(reader).lines()),
+            line_number: 0,
+        })
+    }
+}
+
+impl<T> Items<T> {
+    /// Reads the attribute map of the next item, opening the following data
+    /// file when the current one is exhausted.
+    fn next_attribute_map(
+        &mut self
+    ) -> Option<Result<HashMap<String, AttributeValue>, ExportError>> {
+        loop {
+            if self.current.is_none() {
+                let path = self.data_files.next()?;
+                match DataFile::open(path) {
+                    Ok(data_file) => self.current = Some(data_file),
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+
+            let format = self.format;
+            let data_file = self.current.as_mut().unwrap();
+            let line = match data_file.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(error)) => return Some(Err(error.into())),
+                None => {
+                    self.current = None;
+                    continue;
+                },
+            };
+            data_file.line_number += 1;
+
+            let invalid_data = |reason: String| ExportError::InvalidData(
+                data_file.path.clone(), data_file.line_number, reason
+            );
+            let parsed = match format {
+                ExportFormat::DynamoDBJson => parse_json_item(&line),
+                ExportFormat::Ion => ion::parse_item(&line),
+            };
+            match parsed {
+                Ok(Some(attribute_map)) => return Some(Ok(attribute_map)),
+                Ok(None) => continue,
+                Err(reason) => return Some(Err(invalid_data(reason))),
+            }
+        }
+    }
+}
+
+impl<T> Iterator for Items<T>
+    where T: TryFrom<HashMap<String, AttributeValue>>, ConversionError: From<T::Error> {
+    type Item = Result<T, ExportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_attribute_map().map(|attribute_map| {
+            let item = T::try_from(attribute_map?).map_err(ConversionError::from)?;
+            Ok(item)
+        })
+    }
+}
+
+
+/// Reads the item held by a line of a DynamoDB JSON export, which is written
+/// as `{"Item": {...}}`.
+fn parse_json_item(line: &str) -> Result<Option<HashMap<String, AttributeValue>>, String> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let mut record: Value = ::serde_json::from_str(line)
+        .map_err(|error| error.to_string())?;
+    let item = record.get_mut("Item")
+        .map(Value::take)
+        .ok_or("expected an Item object")?;
+    json::from_dynamodb_json(item)
+        .map(Some)
+        .map_err(|error| error.to_string())
+}
+
+fn read_json<R: Read>(reader: R) -> Result<Value, ExportError> {
+    ::serde_json::from_reader(reader)
+        .map_err(|error| ExportError::InvalidManifest(error.to_string()))
+}
+
+/// Data files are listed by their S3 key, which ends with their file name.
+fn file_name(key: &str) -> &str {
+    key.rsplit('/').next().unwrap_or(key)
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use AttributeValue;
+
+    use errors::ExportError;
+    use super::{Export, ExportFormat, read_items};
+
+    /// Writes an export with the given data files to a fresh temporary
+    /// directory and returns the path of its manifest summary.
+    fn write_export(name: &str, format: &str, data_files: &[(&str, &str)]) -> PathBuf {
+        let directory = env::temp_dir()
+            .join(format!("korat-export-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("data")).unwrap();
+
+        let mut manifest_files = String::new();
+        for &(file_name, contents) in data_files {
+            let mut file = File::create(directory.join("data").join(file_name)).unwrap();
+            if file_name.ends_with(".gz") {
+                let mut encoder = GzEncoder::new(file, Compression::default());
+                encoder.write_all(contents.as_bytes()).unwrap();
+                encoder.finish().unwrap();
+            } else {
+                file.write_all(contents.as_bytes()).unwrap();
+            }
+            manifest_files.push_str(&format!(
+                "{{\"itemCount\":1,\"dataFileS3Key\":\"AWSDynamoDB/01234/data/{}\"}}\n",
+                file_name
+            ));
+        }
+        fs::write(directory.join("manifest-files.json"), manifest_files).unwrap();
+
+        let summary = directory.join("manifest-summary.json");
+        fs::write(&summary, format!(
+            "{{\"exportFormat\":\"{}\",\"itemCount\":3,\
+              \"manifestFilesS3Key\":\"AWSDynamoDB/01234/manifest-files.json\"}}",
+            format
+        )).unwrap();
+        summary
+    }
+
+    fn ids(items: Vec<HashMap<String, AttributeValue>>) -> Vec<String> {
+        let mut ids: Vec<String> = items.into_iter()
+            .map(|mut item| item.remove("id").unwrap().s.unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn reads_dynamodb_json_exports() {
+        let summary = write_export("json", "DYNAMODB_JSON", &[
+            ("first.json.gz", concat!(
+                "{\"Item\":{\"id\":{\"S\":\"1\"},\"count\":{\"N\":\"2\"}}}\n",
+                "{\"Item\":{\"id\":{\"S\":\"2\"}}}\n",
+            )),
+            ("second.json", "{\"Item\":{\"id\":{\"S\":\"3\"}}}\n"),
+        ]);
+
+        let export = Export::open(&summary).unwrap();
+        assert_eq!(ExportFormat::DynamoDBJson, export.format());
+        assert_eq!(Some(3), export.item_count());
+        assert_eq!(2, export.data_files().len());
+
+        let items: Vec<HashMap<String, AttributeValue>> = export.items()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec!["1", "2", "3"], ids(items));
+    }
+
+    #[test]
+    fn reads_ion_exports() {
+        let summary = write_export("ion", "ION", &[
+            ("first.ion.gz", concat!(
+                "$ion_1_0 {Item:{id:\"1\",tags:$dynamodb_SS::[\"a\"]}}\n",
+                "$ion_1_0 {Item:{id:\"2\",count:3.}}\n",
+            )),
+        ]);
+
+        let items: Vec<HashMap<String, AttributeValue>> = read_items(&summary).unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec!["1", "2"], ids(items));
+    }
+
+    #[test]
+    fn reports_the_location_of_invalid_data() {
+        let summary = write_export("invalid", "DYNAMODB_JSON", &[
+            ("first.json", "{\"Item\":{\"id\":{\"S\":\"1\"}}}\n{\"Item\":{\"id\":{\"X\":\"2\"}}}\n"),
+        ]);
+
+        let results: Vec<Result<HashMap<String, AttributeValue>, ExportError>> =
+            read_items(&summary).unwrap().collect();
+        assert!(results[0].is_ok());
+        match results[1] {
+            Err(ExportError::InvalidData(ref path, line, _)) => {
+                assert!(path.ends_with("data/first.json"));
+                assert_eq!(2, line);
+            },
+            ref other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        let summary = write_export("format", "CSV", &[]);
+        match Export::open(&summary) {
+            Err(ExportError::InvalidManifest(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
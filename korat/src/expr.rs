@@ -0,0 +1,567 @@
+//! Condition expressions built from typed attribute handles.
+//!
+//! Deriving `DynamoDBItem` generates a `fields` function returning a handle
+//! for every attribute of the item, typed after its field and converting
+//! values with the converter of the field:
+//!
+//! ```rust,ignore
+//! let fields = Order::fields();
+//! let condition = fields.status.eq(Status::Pending)
+//!     .and(fields.total.between(10.0, 100.0))
+//!     .and(!fields.tags.contains("archived".to_string()))
+//!     .build();
+//! let request = PutItemInput {
+//!     expression_attribute_values: condition.expression_attribute_values::<Rusoto>(),
+//!     condition_expression: Some(condition.expression),
+//!     expression_attribute_names: Some(condition.names),
+//!     ..PutItemInput::default()
+//! };
+//! ```
+//!
+//! Every attribute name goes through a `#n0` style placeholder, so reserved
+//! words can be used as names without escaping them by hand, and values
+//! through `:v0` style placeholders. Attributes without a field, such as the
+//! keys of key templates, get handles from `attribute`. Conditions without
+//! values, like `attribute_not_exists(PK)`, have an empty map of values,
+//! which DynamoDB rejects, so `expression_attribute_values` returns `None`
+//! for them.
+//!
+//! Values stored as no attribute at all, like `None`, can't be written in a
+//! condition, so they compare with the existence of the attribute instead:
+//! `eq(None)` matches the items without the attribute and `ne(None)` the
+//! items with it, while ordering comparisons with them match no item.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Not;
+
+use {AttributeValue, AttributeValueConverter, ListElement};
+use backend::{Backend, into_attribute_map};
+use query::PrefixKey;
+
+
+/// Marks the types `contains` applies to: strings, which contain strings,
+/// and sets and lists, which contain their members.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't stored as a string, a set or a list",
+    label = "only strings, sets and lists can contain values"
+)]
+pub trait Contains: AttributeValueConverter {
+    type Element: AttributeValueConverter;
+}
+
+impl Contains for String {
+    type Element = String;
+}
+
+impl<T: ListElement> Contains for Vec<T> {
+    type Element = T;
+}
+
+impl<T: Contains> Contains for Option<T> {
+    type Element = T::Element;
+}
+
+/// A handle on an attribute of type `T`, which builds conditions on it.
+pub struct Field<T> {
+    name: &'static str,
+    to_attribute_value: fn(T) -> AttributeValue,
+}
+
+impl<T> Field<T> {
+    /// Used by the derive, see the `fields` function of derived items.
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, to_attribute_value: fn(T) -> AttributeValue) -> Self {
+        Field { name, to_attribute_value }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Converts a value with the converter of the attribute.
+    pub fn to_attribute_value(&self, value: T) -> AttributeValue {
+        (self.to_attribute_value)(value)
+    }
+
+    pub fn exists(self) -> Condition {
+        attribute_exists(self.name)
+    }
+
+    pub fn not_exists(self) -> Condition {
+        attribute_not_exists(self.name)
+    }
+
+    /// Matches `value`, or the items without the attribute when `value` is
+    /// stored as no attribute.
+    pub fn eq(self, value: T) -> Condition {
+        match self.written_value(value) {
+            Some(value) => self.compare("=", value),
+            None => self.not_exists(),
+        }
+    }
+
+    /// Matches anything but `value`, or the items with the attribute when
+    /// `value` is stored as no attribute.
+    pub fn ne(self, value: T) -> Condition {
+        match self.written_value(value) {
+            Some(value) => self.compare("<>", value),
+            None => self.exists(),
+        }
+    }
+
+    pub fn lt(self, value: T) -> Condition {
+        self.order("<", value)
+    }
+
+    pub fn le(self, value: T) -> Condition {
+        self.order("<=", value)
+    }
+
+    pub fn gt(self, value: T) -> Condition {
+        self.order(">", value)
+    }
+
+    pub fn ge(self, value: T) -> Condition {
+        self.order(">=", value)
+    }
+
+    /// Matches values from `low` to `high`, both included.
+    pub fn between(self, low: T, high: T) -> Condition {
+        match (self.written_value(low), self.written_value(high)) {
+            (Some(low), Some(high)) => {
+                let operand = Operand::Attribute(self.name);
+                Condition(Node::Between(operand, Box::new(low), Box::new(high)))
+            },
+            _ => self.nothing(),
+        }
+    }
+
+    /// Matches any of `values`, of which DynamoDB allows up to 100. DynamoDB
+    /// rejects `IN` without values, so no values give a condition matching
+    /// no item instead.
+    pub fn is_in<I: IntoIterator<Item = T>>(self, values: I) -> Condition {
+        let mut missing = false;
+        let values: Vec<_> = values.into_iter()
+            .filter_map(|value| {
+                let value = self.written_value(value);
+                missing |= value.is_none();
+                value
+            })
+            .collect();
+        match (values.is_empty(), missing) {
+            (true, true) => self.not_exists(),
+            (true, false) => self.nothing(),
+            (false, missing) => {
+                let condition = Condition(Node::In(Operand::Attribute(self.name), values));
+                if missing { condition.or(self.not_exists()) } else { condition }
+            },
+        }
+    }
+
+    /// The size of the attribute: the length of strings and binary values,
+    /// or the number of members of collections.
+    pub fn size(self) -> Size {
+        Size { name: self.name }
+    }
+
+    fn order(self, operator: &'static str, value: T) -> Condition {
+        match self.written_value(value) {
+            Some(value) => self.compare(operator, value),
+            None => self.nothing(),
+        }
+    }
+
+    fn compare(self, operator: &'static str, value: AttributeValue) -> Condition {
+        Condition(Node::Compare(Operand::Attribute(self.name), operator, Box::new(value)))
+    }
+
+    /// Converts `value`, or `None` when it's stored as no attribute at all,
+    /// which conditions can't refer to.
+    fn written_value(&self, value: T) -> Option<AttributeValue> {
+        Some((self.to_attribute_value)(value)).filter(|value| *value != AttributeValue::default())
+    }
+
+    /// A condition no item matches.
+    fn nothing(self) -> Condition {
+        self.exists().and(self.not_exists())
+    }
+}
+
+impl<T: PrefixKey> Field<T> {
+    /// Matches strings and binary values starting with `prefix`.
+    pub fn begins_with(self, prefix: T) -> Condition {
+        let prefix = (self.to_attribute_value)(prefix);
+        Condition(Node::Function("begins_with", self.name, Some(Box::new(prefix))))
+    }
+}
+
+impl<T: Contains> Field<T> {
+    /// Matches strings containing the substring `value`, as well as sets and
+    /// lists containing the member `value`.
+    pub fn contains(self, value: T::Element) -> Condition {
+        let value = Box::new(value.to_attribute_value());
+        Condition(Node::Function("contains", self.name, Some(value)))
+    }
+}
+
+impl<T> Clone for Field<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Field<T> {}
+
+impl<T> fmt::Debug for Field<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Field").field(&self.name).finish()
+    }
+}
+
+/// The size of an attribute, see `Field::size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+    name: &'static str,
+}
+
+impl Size {
+    pub fn eq(self, size: usize) -> Condition {
+        self.compare("=", size)
+    }
+
+    pub fn ne(self, size: usize) -> Condition {
+        self.compare("<>", size)
+    }
+
+    pub fn lt(self, size: usize) -> Condition {
+        self.compare("<", size)
+    }
+
+    pub fn le(self, size: usize) -> Condition {
+        self.compare("<=", size)
+    }
+
+    pub fn gt(self, size: usize) -> Condition {
+        self.compare(">", size)
+    }
+
+    pub fn ge(self, size: usize) -> Condition {
+        self.compare(">=", size)
+    }
+
+    pub fn between(self, low: usize, high: usize) -> Condition {
+        Condition(Node::Between(Operand::Size(self.name), size_value(low), size_value(high)))
+    }
+
+    fn compare(self, operator: &'static str, size: usize) -> Condition {
+        Condition(Node::Compare(Operand::Size(self.name), operator, size_value(size)))
+    }
+}
+
+fn size_value(size: usize) -> Box<AttributeValue> {
+    Box::new(AttributeValue { n: Some(size.to_string()), ..AttributeValue::default() })
+}
+
+/// A handle on an attribute which isn't a field of a derived item, such as
+/// the key of a key template.
+pub fn attribute<T: AttributeValueConverter>(name: &'static str) -> Field<T> {
+    Field::new(name, T::to_attribute_value)
+}
+
+pub fn attribute_exists(name: &'static str) -> Condition {
+    Condition(Node::Function("attribute_exists", name, None))
+}
+
+pub fn attribute_not_exists(name: &'static str) -> Condition {
+    Condition(Node::Function("attribute_not_exists", name, None))
+}
+
+/// A condition on the attributes of an item, combined with `and`, `or` and
+/// `!`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition(Node);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Compare(Operand, &'static str, Box<AttributeValue>),
+    Between(Operand, Box<AttributeValue>, Box<AttributeValue>),
+    In(Operand, Vec<AttributeValue>),
+    Function(&'static str, &'static str, Option<Box<AttributeValue>>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Attribute(&'static str),
+    Size(&'static str),
+}
+
+impl Condition {
+    pub fn and(self, other: Condition) -> Condition {
+        Condition(Node::And(Box::new(self.0), Box::new(other.0)))
+    }
+
+    pub fn or(self, other: Condition) -> Condition {
+        Condition(Node::Or(Box::new(self.0), Box::new(other.0)))
+    }
+
+    /// Writes the condition, adding the attribute names and values it refers
+    /// to. Used to share placeholders with other expressions of a request.
+    pub fn write(&self, attributes: &mut ExpressionAttributes) -> String {
+        self.0.write(attributes)
+    }
+
+    pub fn build(self) -> ConditionExpression {
+        let mut attributes = ExpressionAttributes::default();
+        let expression = self.write(&mut attributes);
+        ConditionExpression {
+            expression,
+            names: attributes.names,
+            values: attributes.values,
+        }
+    }
+}
+
+impl Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Condition {
+        Condition(Node::Not(Box::new(self.0)))
+    }
+}
+
+impl Node {
+    /// How tightly the node binds, from `OR` to `AND`, `NOT` and the other
+    /// conditions.
+    fn precedence(&self) -> u8 {
+        match *self {
+            Node::Or(..) => 0,
+            Node::And(..) => 1,
+            _ => 2,
+        }
+    }
+
+    fn write(&self, attributes: &mut ExpressionAttributes) -> String {
+        match *self {
+            Node::Compare(operand, operator, ref value) => {
+                let operand = operand.write(attributes);
+                format!("{} {} {}", operand, operator, attributes.value(*value.clone()))
+            },
+            Node::Between(operand, ref low, ref high) => {
+                let operand = operand.write(attributes);
+                let low = attributes.value(*low.clone());
+                format!("{} BETWEEN {} AND {}", operand, low, attributes.value(*high.clone()))
+            },
+            Node::In(operand, ref values) => {
+                let operand = operand.write(attributes);
+                let values: Vec<String> = values.iter()
+                    .map(|value| attributes.value(value.clone()))
+                    .collect();
+                format!("{} IN ({})", operand, values.join(", "))
+            },
+            Node::Function(function, name, ref value) => {
+                let name = attributes.name(name);
+                match *value {
+                    Some(ref value) => {
+                        format!("{}({}, {})", function, name, attributes.value(*value.clone()))
+                    },
+                    None => format!("{}({})", function, name),
+                }
+            },
+            Node::And(ref left, ref right) => {
+                let left = left.write_operand(1, attributes);
+                format!("{} AND {}", left, right.write_operand(1, attributes))
+            },
+            Node::Or(ref left, ref right) => {
+                let left = left.write_operand(0, attributes);
+                format!("{} OR {}", left, right.write_operand(0, attributes))
+            },
+            Node::Not(ref condition) => format!("NOT {}", condition.write_operand(2, attributes)),
+        }
+    }
+
+    /// Writes the operand of a logical operator, in parentheses when it binds
+    /// less tightly than the operator.
+    fn write_operand(&self, precedence: u8, attributes: &mut ExpressionAttributes) -> String {
+        let expression = self.write(attributes);
+        if self.precedence() < precedence {
+            format!("({})", expression)
+        } else {
+            expression
+        }
+    }
+}
+
+impl Operand {
+    fn write(self, attributes: &mut ExpressionAttributes) -> String {
+        match self {
+            Operand::Attribute(name) => attributes.name(name),
+            Operand::Size(name) => format!("size({})", attributes.name(name)),
+        }
+    }
+}
+
+/// The `ExpressionAttributeNames` and `ExpressionAttributeValues` of a
+/// request, keyed by their placeholders.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExpressionAttributes {
+    pub names: HashMap<String, String>,
+    pub values: HashMap<String, AttributeValue>,
+}
+
+impl ExpressionAttributes {
+    /// The placeholder of an attribute name, shared by every reference to the
+    /// attribute.
+    pub fn name(&mut self, name: &str) -> String {
+        if let Some((placeholder, _)) = self.names.iter().find(|&(_, known)| known == name) {
+            return placeholder.clone();
+        }
+        let placeholder = free_placeholder("#n", &self.names);
+        self.names.insert(placeholder.clone(), name.to_string());
+        placeholder
+    }
+
+    /// A new placeholder for a value.
+    pub fn value(&mut self, value: AttributeValue) -> String {
+        let placeholder = free_placeholder(":v", &self.values);
+        self.values.insert(placeholder.clone(), value);
+        placeholder
+    }
+}
+
+fn free_placeholder<V>(prefix: &str, placeholders: &HashMap<String, V>) -> String {
+    (placeholders.len()..)
+        .map(|index| format!("{}{}", prefix, index))
+        .find(|placeholder| !placeholders.contains_key(placeholder))
+        .expect("there is a free placeholder")
+}
+
+/// A `ConditionExpression` along with the attribute names and values it
+/// refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionExpression {
+    pub expression: String,
+    pub names: HashMap<String, String>,
+    pub values: HashMap<String, AttributeValue>,
+}
+
+impl ConditionExpression {
+    /// The `ExpressionAttributeValues` of a request, converted for a backend,
+    /// or `None` when the condition has no values.
+    pub fn expression_attribute_values<B: Backend>(
+        &self
+    ) -> Option<HashMap<String, B::AttributeValue>> {
+        optional_values::<B>(&self.values)
+    }
+}
+
+/// Converts the values of an expression for a backend, leaving out empty
+/// maps, which DynamoDB rejects.
+pub(crate) fn optional_values<B: Backend>(
+    values: &HashMap<String, AttributeValue>
+) -> Option<HashMap<String, B::AttributeValue>> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(into_attribute_map::<B>(values.clone()))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use AttributeValueConverter;
+    use super::{Field, attribute, attribute_not_exists};
+
+    fn status() -> Field<String> {
+        attribute("status")
+    }
+
+    #[test]
+    fn shares_name_placeholders_between_references() {
+        let condition = status().eq("paid".to_string())
+            .or(status().not_exists())
+            .build();
+        assert_eq!("#n0 = :v0 OR attribute_not_exists(#n0)", condition.expression);
+        assert_eq!("status", condition.names["#n0"]);
+        assert_eq!("paid".to_string().to_attribute_value(), condition.values[":v0"]);
+    }
+
+    #[test]
+    fn parenthesizes_by_precedence() {
+        let total: Field<u32> = attribute("total");
+        let condition = status().eq("paid".to_string())
+            .or(status().eq("sent".to_string()))
+            .and(!total.between(1, 10))
+            .build();
+        assert_eq!(
+            "(#n0 = :v0 OR #n0 = :v1) AND NOT #n1 BETWEEN :v2 AND :v3", condition.expression
+        );
+
+        let condition = (!attribute_not_exists("PK").and(total.size().gt(3))).build();
+        assert_eq!("NOT (attribute_not_exists(#n0) AND size(#n1) > :v0)", condition.expression);
+        assert_eq!(Some("3".to_string()), condition.values[":v0"].n);
+    }
+
+    #[cfg(feature = "rusoto")]
+    #[test]
+    fn leaves_out_empty_values() {
+        use backend::Rusoto;
+
+        let condition = attribute_not_exists("PK").build();
+        assert_eq!(None, condition.expression_attribute_values::<Rusoto>());
+
+        let condition = status().eq("paid".to_string()).build();
+        let values = condition.expression_attribute_values::<Rusoto>().unwrap();
+        assert_eq!(Some("paid".to_string()), values[":v0"].s);
+    }
+
+    #[test]
+    fn compares_missing_values_with_the_existence_of_the_attribute() {
+        let note: Field<Option<String>> = attribute("note");
+        assert_eq!(note.not_exists(), note.eq(None));
+        assert_eq!(note.exists(), note.ne(None));
+        let nothing = note.exists().and(note.not_exists());
+        assert_eq!(nothing, note.lt(None));
+        assert_eq!(nothing, note.between(None, Some("z".to_string())));
+
+        let condition = note.is_in(vec![Some("a".to_string()), None]).build();
+        assert_eq!("#n0 IN (:v0) OR attribute_not_exists(#n0)", condition.expression);
+        assert_eq!(1, condition.values.len());
+        assert_eq!(note.not_exists(), note.is_in(vec![None]));
+    }
+
+    #[test]
+    fn matches_prefixes_of_strings_and_binary_values() {
+        let avatar: Field<Vec<u8>> = attribute("avatar");
+        let condition = status().begins_with("pa".to_string())
+            .and(avatar.begins_with(vec![1]))
+            .build();
+        assert_eq!("begins_with(#n0, :v0) AND begins_with(#n1, :v1)", condition.expression);
+        assert_eq!(Some(vec![1]), condition.values[":v1"].b);
+    }
+
+    #[test]
+    fn converts_contained_values_as_members() {
+        let scores: Field<Option<BTreeSet<u32>>> = attribute("scores");
+        let condition = scores.contains(7).and(status().contains("ai".to_string())).build();
+        assert_eq!("contains(#n0, :v0) AND contains(#n1, :v1)", condition.expression);
+        assert_eq!(7.to_attribute_value(), condition.values[":v0"]);
+        assert_eq!("ai".to_string().to_attribute_value(), condition.values[":v1"]);
+    }
+
+    #[test]
+    fn matches_nothing_for_empty_in_conditions() {
+        let condition = (!status().is_in(Vec::new())).or(status().exists()).build();
+        assert_eq!(
+            "NOT (attribute_exists(#n0) AND attribute_not_exists(#n0)) OR attribute_exists(#n0)",
+            condition.expression
+        );
+        assert!(condition.values.is_empty());
+    }
+}
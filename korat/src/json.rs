@@ -0,0 +1,312 @@
+//! Import and export of items in the JSON formats used by the AWS tooling.
+//!
+//! The DynamoDB JSON format describes every attribute with its type, as in
+//! `{"name": {"S": "korat"}, "count": {"N": "3"}}`, and round trips every
+//! attribute value exactly. Binary values are base64 encoded.
+//!
+//! The plain (unmarshalled) format stores attributes as the JSON values they
+//! naturally map to, as in `{"name": "korat", "count": 3}`. It is lossy: sets
+//! are read back as lists and binary values, which are exported as base64
+//! strings, are read back as strings.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{Map, Number, Value};
+
+use errors::ConversionError;
+use super::{AttributeType, AttributeValue, AttributeValueConverter, ConversionResult};
+use value::is_valid_number;
+
+
+/// Exports an item or attribute map in the DynamoDB JSON format.
+pub fn to_dynamodb_json<T>(item: T) -> Value
+    where T: Into<HashMap<String, AttributeValue>> {
+    Value::Object(
+        item.into().into_iter()
+            .map(|(name, value)| (name, attribute_value_to_json(value)))
+            .collect()
+    )
+}
+
+/// Imports an item or attribute map from the DynamoDB JSON format.
+pub fn from_dynamodb_json<T>(json: Value) -> ConversionResult<T>
+    where T: TryFrom<HashMap<String, AttributeValue>>, ConversionError: From<T::Error> {
+    let attribute_map = match json {
+        Value::Object(attributes) => attributes.into_iter()
+            .map(|(name, value)| Ok((name, attribute_value_from_json(value)?)))
+            .collect::<ConversionResult<HashMap<String, AttributeValue>>>()?,
+        other => return Err(invalid_json("an object of attributes", &other, "not an object")),
+    };
+    Ok(T::try_from(attribute_map)?)
+}
+
+/// Exports an item or attribute map in the plain JSON format.
+pub fn to_plain_json<T>(item: T) -> Value
+    where T: Into<HashMap<String, AttributeValue>> {
+    Value::Object(
+        item.into().into_iter()
+            .map(|(name, value)| (name, attribute_value_to_plain_json(value)))
+            .collect()
+    )
+}
+
+/// Imports an item or attribute map from the plain JSON format.
+pub fn from_plain_json<T>(json: Value) -> ConversionResult<T>
+    where T: TryFrom<HashMap<String, AttributeValue>>, ConversionError: From<T::Error> {
+    match json {
+        Value::Object(attributes) => Ok(T::try_from(
+            attributes.into_iter()
+                .map(|(name, value)| (name, value.to_attribute_value()))
+                .collect::<HashMap<String, AttributeValue>>()
+        )?),
+        other => Err(invalid_json("an object of attributes", &other, "not an object")),
+    }
+}
+
+/// Describes a single attribute value in the DynamoDB JSON format.
+pub fn attribute_value_to_json(attribute_value: AttributeValue) -> Value {
+    let mut description = Map::new();
+    let members = vec![
+        attribute_value.s.map(|s| ("S", Value::String(s))),
+        attribute_value.n.map(|n| ("N", Value::String(n))),
+        attribute_value.b.map(|b| ("B", Value::String(BASE64.encode(b)))),
+        attribute_value.bool.map(|b| ("BOOL", Value::Bool(b))),
+        attribute_value.null.map(|null| ("NULL", Value::Bool(null))),
+        attribute_value.m.map(|m| ("M", to_dynamodb_json(m))),
+        attribute_value.l.map(|l| ("L", Value::Array(
+            l.into_iter().map(attribute_value_to_json).collect()
+        ))),
+        attribute_value.ss.map(|ss| ("SS", Value::Array(
+            ss.into_iter().map(Value::String).collect()
+        ))),
+        attribute_value.ns.map(|ns| ("NS", Value::Array(
+            ns.into_iter().map(Value::String).collect()
+        ))),
+        attribute_value.bs.map(|bs| ("BS", Value::Array(
+            bs.into_iter().map(|b| Value::String(BASE64.encode(b))).collect()
+        ))),
+    ];
+    for (descriptor, value) in members.into_iter().flatten() {
+        description.insert(descriptor.to_string(), value);
+    }
+    Value::Object(description)
+}
+
+/// Reads a single attribute value described in the DynamoDB JSON format.
+pub fn attribute_value_from_json(json: Value) -> ConversionResult<AttributeValue> {
+    let (descriptor, value) = match json {
+        Value::Object(ref description) if description.len() == 1 => description
+            .iter()
+            .next()
+            .map(|(descriptor, value)| (descriptor.clone(), value.clone()))
+            .unwrap(),
+        ref other => return Err(invalid_json(
+            "an attribute value", other, "expected an object with a single type descriptor"
+        )),
+    };
+
+    let mut attribute_value = AttributeValue::default();
+    match descriptor.as_str() {
+        "S" => attribute_value.s = Some(json_string(value)?),
+        "N" => attribute_value.n = Some(json_number(value)?),
+        "B" => attribute_value.b = Some(json_binary(value)?),
+        "BOOL" => attribute_value.bool = Some(json_bool(value)?),
+        "NULL" => attribute_value.null = Some(json_bool(value)?),
+        "M" => attribute_value.m = Some(from_dynamodb_json(value)?),
+        "L" => attribute_value.l = Some(
+            json_array(value)?.into_iter()
+                .map(attribute_value_from_json)
+                .collect::<ConversionResult<_>>()?
+        ),
+        "SS" => attribute_value.ss = Some(
+            json_array(value)?.into_iter()
+                .map(json_string)
+                .collect::<ConversionResult<_>>()?
+        ),
+        "NS" => attribute_value.ns = Some(
+            json_array(value)?.into_iter()
+                .map(json_number)
+                .collect::<ConversionResult<_>>()?
+        ),
+        "BS" => attribute_value.bs = Some(
+            json_array(value)?.into_iter()
+                .map(json_binary)
+                .collect::<ConversionResult<_>>()?
+        ),
+        unknown => return Err(invalid_json(
+            "an attribute value", &json, &format!("unknown type descriptor {}", unknown)
+        )),
+    }
+    Ok(attribute_value)
+}
+
+/// Converts a single attribute value to its plain JSON form.
+pub fn attribute_value_to_plain_json(attribute_value: AttributeValue) -> Value {
+    let binary = |bytes: Vec<u8>| Value::String(BASE64.encode(bytes));
+    let number = |number: String| number.parse::<Number>()
+        .map(Value::Number)
+        .unwrap_or(Value::String(number));
+
+    match AttributeType::single(&attribute_value) {
+        Ok(AttributeType::S) => Value::String(attribute_value.s.unwrap_or_default()),
+        Ok(AttributeType::N) => number(attribute_value.n.unwrap_or_default()),
+        Ok(AttributeType::B) => binary(attribute_value.b.unwrap_or_default()),
+        Ok(AttributeType::Bool) => Value::Bool(attribute_value.bool.unwrap_or_default()),
+        Ok(AttributeType::Null) | Err(_) => Value::Null,
+        Ok(AttributeType::M) => to_plain_json(attribute_value.m.unwrap_or_default()),
+        Ok(AttributeType::L) => Value::Array(
+            attribute_value.l.unwrap_or_default().into_iter()
+                .map(attribute_value_to_plain_json)
+                .collect()
+        ),
+        Ok(AttributeType::SS) => Value::Array(
+            attribute_value.ss.unwrap_or_default().into_iter()
+                .map(Value::String)
+                .collect()
+        ),
+        Ok(AttributeType::NS) => Value::Array(
+            attribute_value.ns.unwrap_or_default().into_iter().map(number).collect()
+        ),
+        Ok(AttributeType::BS) => Value::Array(
+            attribute_value.bs.unwrap_or_default().into_iter()
+                .map(binary)
+                .collect()
+        ),
+    }
+}
+
+
+fn invalid_json(expected: &'static str, json: &Value, reason: &str) -> ConversionError {
+    ConversionError::InvalidFormat {
+        expected,
+        value: json.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn json_string(json: Value) -> ConversionResult<String> {
+    match json {
+        Value::String(string) => Ok(string),
+        other => Err(invalid_json("a string", &other, "not a string")),
+    }
+}
+
+/// Numbers are strings in DynamoDB JSON, but some tools write them as JSON
+/// numbers. Strings must hold a number in the notation DynamoDB accepts.
+fn json_number(json: Value) -> ConversionResult<String> {
+    match json {
+        Value::String(number) => if is_valid_number(&number) {
+            Ok(number)
+        } else {
+            Err(invalid_json("a number", &Value::String(number), "not a decimal number"))
+        },
+        Value::Number(number) => Ok(number.to_string()),
+        other => Err(invalid_json("a number", &other, "not a number")),
+    }
+}
+
+fn json_bool(json: Value) -> ConversionResult<bool> {
+    match json {
+        Value::Bool(boolean) => Ok(boolean),
+        other => Err(invalid_json("a boolean", &other, "not a boolean")),
+    }
+}
+
+fn json_binary(json: Value) -> ConversionResult<Vec<u8>> {
+    let encoded = json_string(json)?;
+    BASE64.decode(&encoded).map_err(|error| ConversionError::InvalidFormat {
+        expected: "base64 encoded binary",
+        value: encoded,
+        reason: error.to_string(),
+    })
+}
+
+fn json_array(json: Value) -> ConversionResult<Vec<Value>> {
+    match json {
+        Value::Array(elements) => Ok(elements),
+        other => Err(invalid_json("an array", &other, "not an array")),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use AttributeValue;
+
+    use errors::ConversionError;
+    use super::{from_dynamodb_json, from_plain_json, to_dynamodb_json, to_plain_json};
+
+    fn fixture() -> ::serde_json::Value {
+        ::serde_json::from_str(r#"{
+            "name": {"S": "korat"},
+            "count": {"N": "3"},
+            "avatar": {"B": "AQID"},
+            "active": {"BOOL": true},
+            "deleted": {"NULL": true},
+            "address": {"M": {"city": {"S": "Athens"}}},
+            "history": {"L": [{"N": "1"}, {"S": "two"}]},
+            "tags": {"SS": ["a", "b"]},
+            "scores": {"NS": ["1.5", "2"]},
+            "keys": {"BS": ["AQ=="]}
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn can_round_trip_dynamodb_json() {
+        let item: HashMap<String, AttributeValue> = from_dynamodb_json(fixture()).unwrap();
+        let avatar: Vec<u8> = item["avatar"].clone().b.unwrap();
+        assert_eq!(vec![1, 2, 3], avatar);
+        assert_eq!("Athens", &item["address"].clone().m.unwrap()["city"].clone().s.unwrap());
+        assert_eq!(vec!["1.5".to_string(), "2".to_string()], item["scores"].clone().ns.unwrap());
+
+        assert_eq!(fixture(), to_dynamodb_json(item));
+    }
+
+    #[test]
+    fn can_export_plain_json() {
+        let item: HashMap<String, AttributeValue> = from_dynamodb_json(fixture()).unwrap();
+        let expected: ::serde_json::Value = ::serde_json::from_str(r#"{
+            "name": "korat",
+            "count": 3,
+            "avatar": "AQID",
+            "active": true,
+            "deleted": null,
+            "address": {"city": "Athens"},
+            "history": [1, "two"],
+            "tags": ["a", "b"],
+            "scores": [1.5, 2],
+            "keys": ["AQ=="]
+        }"#).unwrap();
+        let plain = to_plain_json(item);
+        assert_eq!(expected, plain);
+
+        let imported: HashMap<String, AttributeValue> = from_plain_json(plain).unwrap();
+        assert_eq!("3", &imported["count"].clone().n.unwrap());
+        assert_eq!(2, imported["tags"].clone().l.unwrap().len());
+    }
+
+    #[test]
+    fn rejects_malformed_descriptions() {
+        let invalid = [
+            r#"{"name": {"X": "korat"}}"#,
+            r#"{"name": {"S": "korat", "N": "1"}}"#,
+            r#"{"name": {"B": "not base64!"}}"#,
+            r#"{"count": {"N": "three"}}"#,
+            r#"{"scores": {"NS": ["1", "1e"]}}"#,
+            r#"{"name": {"SS": "korat"}}"#,
+            r#"["korat"]"#,
+        ];
+        for json in invalid.iter() {
+            let json = ::serde_json::from_str(json).unwrap();
+            match from_dynamodb_json::<HashMap<String, AttributeValue>>(json) {
+                Err(ConversionError::InvalidFormat { .. }) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Stores any serde type as a compact JSON document in an `S` attribute.
+//!
+//! This suits opaque documents which are only ever read back whole. Select it
+//! for a field with `#[korat(json_string)]`; use the `serde` module instead to
+//! store a type as native attribute values.
+
+use serde_crate::Serialize;
+use serde_crate::de::DeserializeOwned;
+
+use super::{AttributeValue, AttributeValueConverter, ConversionResult};
+use common_types::invalid_format;
+use errors::ConversionError;
+
+
+/// Converts a value into an `S` attribute holding its JSON representation.
+///
+/// # Panics
+///
+/// Panics when the value can't be represented as JSON, for example a map
+/// with keys which are not strings. Use `try_to_attribute_value` to handle
+/// these cases, as the derive does.
+pub fn to_attribute_value<T: Serialize>(value: T) -> AttributeValue {
+    try_to_attribute_value(value).unwrap_or_else(
+        |error| panic!("the value can't be stored as a JSON string: {}", error)
+    )
+}
+
+/// Converts a value into an `S` attribute holding its JSON representation,
+/// failing when the value can't be represented as JSON.
+pub fn try_to_attribute_value<T: Serialize>(value: T) -> ConversionResult<AttributeValue> {
+    ::serde_json::to_string(&value)
+        .map(AttributeValueConverter::to_attribute_value)
+        .map_err(|error| ConversionError::Custom(error.to_string()))
+}
+
+pub fn from_attribute_value<T: DeserializeOwned>(
+    attribute_value: AttributeValue
+) -> ConversionResult<T> {
+    let document = String::from_attribute_value(attribute_value)?;
+    ::serde_json::from_str(&document)
+        .map_err(|error| invalid_format("a JSON document", &document, error))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use AttributeValueConverter;
+    use errors::ConversionError;
+    use super::{from_attribute_value, to_attribute_value, try_to_attribute_value};
+
+    #[test]
+    fn stores_values_as_compact_json() {
+        let mut document = BTreeMap::new();
+        document.insert("name".to_string(), vec![1, 2]);
+
+        let converted = to_attribute_value(&document);
+        assert_eq!(r#"{"name":[1,2]}"#, &converted.clone().s.unwrap());
+        assert_eq!(document, from_attribute_value(converted).unwrap());
+    }
+
+    #[test]
+    fn reports_values_which_cant_be_json() {
+        let mut pairs = BTreeMap::new();
+        pairs.insert((1, 2), "pair");
+        match try_to_attribute_value(&pairs) {
+            Err(ConversionError::Custom(message)) => assert!(message.contains("key must be")),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_invalid_documents() {
+        let av = "{".to_string().to_attribute_value();
+        match from_attribute_value::<BTreeMap<String, u32>>(av) {
+            Err(ConversionError::InvalidFormat { expected, value, .. }) => {
+                assert_eq!("a JSON document", expected);
+                assert_eq!("{", value);
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
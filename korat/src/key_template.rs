@@ -0,0 +1,294 @@
+//! Keys synthesized from several fields, as used by single-table designs.
+//!
+//! Instead of marking a field with `#[hash]` or `#[range]`, a struct can
+//! declare its keys as templates over its fields:
+//!
+//! ```rust,ignore
+//! #[derive(DynamoDBItem)]
+//! #[korat(hash = "USER#{user_id}", range = "ORDER#{date}#{order_id}")]
+//! struct Order {
+//!     user_id: String,
+//!     date: String,
+//!     order_id: u32,
+//!     total: f64,
+//! }
+//! ```
+//!
+//! The hash and range keys are stored in the `PK` and `SK` attributes, which
+//! can be renamed with `hash_attribute` and `range_attribute`. The fields
+//! named in a template are its components: they are written into the key
+//! instead of attributes of their own, and parsed back from it when reading
+//! the item. Components are stored as strings or numbers, so they must be
+//! `S` or `N` keys, which the derive checks when compiling.
+//!
+//! A component ends at the first occurrence of the text following it in the
+//! template. So that any value can be written, components followed by text
+//! escape `%` and the first character of that text as `%` and the hex digits
+//! of their UTF-8 bytes, `2024#01` becoming `2024%2301` under
+//! `ORDER#{date}#{order_id}`. The text following a component can't start
+//! with `%`. The last component of a key isn't escaped.
+
+use std::fmt::Write;
+
+use {AttributeType, AttributeValue, AttributeValueConverter, ConversionResult};
+use errors::ConversionError;
+
+
+/// The character starting the escape sequences of components.
+const ESCAPE: char = '%';
+
+
+/// A piece of a key template: either text copied as is, or a component along
+/// with the type it is stored as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplatePart {
+    Literal(&'static str),
+    Component(&'static str, AttributeType),
+}
+
+/// A key template split into its parts. Built by the derive, which checks
+/// that every component names a field and that components are separated by
+/// some text not starting with `%`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyTemplate {
+    template: &'static str,
+    parts: &'static [TemplatePart],
+}
+
+impl KeyTemplate {
+    pub const fn new(template: &'static str, parts: &'static [TemplatePart]) -> Self {
+        KeyTemplate { template, parts }
+    }
+
+    pub fn template(&self) -> &'static str {
+        self.template
+    }
+
+    /// Writes the key from the attribute values of the components, given in
+    /// the order of the template. Fails on components which aren't stored as
+    /// `S` or `N`, and on templates which can't be parsed back, neither of
+    /// which the derive lets through.
+    pub fn format(&self, components: Vec<AttributeValue>) -> ConversionResult<AttributeValue> {
+        let mut components = components.into_iter();
+        let mut key = String::new();
+        let mut parts = self.parts.iter().peekable();
+        while let Some(part) = parts.next() {
+            match *part {
+                TemplatePart::Literal(literal) => key.push_str(literal),
+                TemplatePart::Component(name, _) => {
+                    let component = components.next()
+                        .and_then(|component| component.s.or(component.n))
+                        .ok_or_else(|| self.invalid(name, "isn't stored as S or N"))?;
+                    match parts.peek() {
+                        Some(TemplatePart::Literal(next)) if next.starts_with(ESCAPE) => {
+                            return Err(self.invalid(name, "is followed by an escape"));
+                        },
+                        Some(TemplatePart::Literal(next)) => escape(&component, next, &mut key),
+                        _ => key.push_str(&component),
+                    }
+                },
+            }
+        }
+        Ok(key.to_attribute_value())
+    }
+
+    fn invalid(&self, name: &str, reason: &str) -> ConversionError {
+        ConversionError::InvalidFormat {
+            expected: self.template,
+            value: name.to_string(),
+            reason: format!("the key component {} {}", name, reason),
+        }
+    }
+
+    /// Splits a key into the attribute values of its components, paired with
+    /// their names.
+    pub fn parse(
+        &self, attribute_value: AttributeValue
+    ) -> ConversionResult<Vec<(&'static str, AttributeValue)>> {
+        let key = String::from_attribute_value(attribute_value)?;
+        let mismatch = || ConversionError::InvalidFormat {
+            expected: self.template,
+            value: key.clone(),
+            reason: "the key doesn't match its template".to_string(),
+        };
+
+        let mut components = Vec::new();
+        let mut rest = key.as_str();
+        let mut parts = self.parts.iter().peekable();
+        while let Some(part) = parts.next() {
+            match *part {
+                TemplatePart::Literal(literal) => {
+                    rest = rest.strip_prefix(literal).ok_or_else(mismatch)?;
+                },
+                TemplatePart::Component(name, attribute_type) => {
+                    let end = match parts.peek() {
+                        Some(TemplatePart::Literal(next)) => {
+                            rest.find(next).ok_or_else(mismatch)?
+                        },
+                        _ => rest.len(),
+                    };
+                    let (component, remaining) = rest.split_at(end);
+                    let component = if end == rest.len() {
+                        component.to_string()
+                    } else {
+                        unescape(component).ok_or_else(mismatch)?
+                    };
+                    components.push((name, component_value(component, attribute_type)));
+                    rest = remaining;
+                },
+            }
+        }
+        if rest.is_empty() {
+            Ok(components)
+        } else {
+            Err(mismatch())
+        }
+    }
+}
+
+fn component_value(component: String, attribute_type: AttributeType) -> AttributeValue {
+    let mut attribute_value = AttributeValue::default();
+    if attribute_type == AttributeType::N {
+        attribute_value.n = Some(component);
+    } else {
+        attribute_value.s = Some(component);
+    }
+    attribute_value
+}
+
+/// Writes a component followed by `next`, escaping `ESCAPE` and the first
+/// character of `next` so that `next` can't occur within the component.
+fn escape(component: &str, next: &str, key: &mut String) {
+    let reserved = next.chars().next();
+    for character in component.chars() {
+        if character == ESCAPE || Some(character) == reserved {
+            let mut buffer = [0; 4];
+            for byte in character.encode_utf8(&mut buffer).bytes() {
+                write!(key, "{}{:02X}", ESCAPE, byte).expect("writing to a string can't fail");
+            }
+        } else {
+            key.push(character);
+        }
+    }
+}
+
+/// Reverses `escape`, failing on incomplete escapes.
+fn unescape(component: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut rest = component.as_bytes();
+    while let Some((&byte, remaining)) = rest.split_first() {
+        if byte == ESCAPE as u8 {
+            let digits = remaining.get(..2).filter(|digits| {
+                digits.iter().all(u8::is_ascii_hexdigit)
+            })?;
+            let digits = ::std::str::from_utf8(digits).ok()?;
+            bytes.push(u8::from_str_radix(digits, 16).ok()?);
+            rest = &remaining[2..];
+        } else {
+            bytes.push(byte);
+            rest = remaining;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+
+#[cfg(test)]
+mod test {
+    use {AttributeType, AttributeValueConverter};
+    use errors::ConversionError;
+    use super::{KeyTemplate, TemplatePart};
+
+    const ORDER: KeyTemplate = KeyTemplate::new("ORDER#{date}#{id}", &[
+        TemplatePart::Literal("ORDER#"),
+        TemplatePart::Component("date", AttributeType::S),
+        TemplatePart::Literal("#"),
+        TemplatePart::Component("id", AttributeType::N),
+    ]);
+
+    #[test]
+    fn formats_and_parses_keys() {
+        let key = ORDER.format(vec![
+            "2024-01-02".to_string().to_attribute_value(), 42.to_attribute_value()
+        ]).unwrap();
+        assert_eq!(Some("ORDER#2024-01-02#42".to_string()), key.s);
+
+        let components = ORDER.parse(key).unwrap();
+        assert_eq!(vec![
+            ("date", "2024-01-02".to_string().to_attribute_value()),
+            ("id", 42.to_attribute_value()),
+        ], components);
+    }
+
+    #[test]
+    fn lets_the_last_component_contain_separators() {
+        const FILE: KeyTemplate = KeyTemplate::new("FILE#{owner}#{path}", &[
+            TemplatePart::Literal("FILE#"),
+            TemplatePart::Component("owner", AttributeType::S),
+            TemplatePart::Literal("#"),
+            TemplatePart::Component("path", AttributeType::S),
+        ]);
+        let key = "FILE#korat#docs#readme".to_string().to_attribute_value();
+        let components = FILE.parse(key).unwrap();
+        assert_eq!(Some("korat".to_string()), components[0].1.s);
+        assert_eq!(Some("docs#readme".to_string()), components[1].1.s);
+    }
+
+    #[test]
+    fn escapes_components_containing_their_separator() {
+        const USER: KeyTemplate = KeyTemplate::new("USER#{id}##{name}", &[
+            TemplatePart::Literal("USER#"),
+            TemplatePart::Component("id", AttributeType::S),
+            TemplatePart::Literal("##"),
+            TemplatePart::Component("name", AttributeType::S),
+        ]);
+        for id in &["a##b", "a#", "#", "a#b", "100%", "%23", "é#ü", ""] {
+            let components = vec![
+                id.to_string().to_attribute_value(), "x##y%".to_string().to_attribute_value()
+            ];
+            let key = USER.format(components.clone()).unwrap();
+            let parsed = USER.parse(key).unwrap();
+            let parsed: Vec<_> = parsed.into_iter().map(|(_, value)| value).collect();
+            assert_eq!(components, parsed, "round trip of {:?}", id);
+        }
+
+        let key = USER.format(vec![
+            "a#b%".to_string().to_attribute_value(), "x##y".to_string().to_attribute_value()
+        ]).unwrap();
+        assert_eq!(Some("USER#a%23b%25##x##y".to_string()), key.s);
+
+        let components = vec![(-1).to_attribute_value(), "x".to_string().to_attribute_value()];
+        const RANGE: KeyTemplate = KeyTemplate::new("{low}-{high}", &[
+            TemplatePart::Component("low", AttributeType::N),
+            TemplatePart::Literal("-"),
+            TemplatePart::Component("high", AttributeType::S),
+        ]);
+        let parsed = RANGE.parse(RANGE.format(components).unwrap()).unwrap();
+        assert_eq!(Some("-1".to_string()), parsed[0].1.n);
+    }
+
+    #[test]
+    fn rejects_components_which_cant_be_written() {
+        let components = vec![vec![1u8].to_attribute_value(), 1.to_attribute_value()];
+        match ORDER.format(components) {
+            Err(ConversionError::InvalidFormat { value, .. }) => assert_eq!("date", value),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        for key in &["ORDER#a%2#1", "ORDER#a%zz#1", "ORDER#%FF#1"] {
+            assert!(ORDER.parse(key.to_string().to_attribute_value()).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn fails_on_keys_not_matching_the_template() {
+        for key in &["USER#1", "ORDER#2024"] {
+            match ORDER.parse(key.to_string().to_attribute_value()) {
+                Err(ConversionError::InvalidFormat { expected, .. }) => {
+                    assert_eq!("ORDER#{date}#{id}", expected);
+                },
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+}
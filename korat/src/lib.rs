@@ -1,76 +1,204 @@
-#![feature(try_from, decl_macro)]
-
 #[macro_use]
 extern crate quick_error;
 
+#[cfg(feature = "rusoto")]
 extern crate rusoto_dynamodb;
-
+#[cfg(feature = "aws-sdk")]
+extern crate aws_sdk_dynamodb;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "indexmap")]
+extern crate indexmap;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde as serde_crate;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
+#[cfg(feature = "serde_json")]
+extern crate base64;
+#[cfg(any(feature = "export", feature = "gzip"))]
+extern crate flate2;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+#[cfg(feature = "encryption")]
+extern crate aes_gcm;
+#[cfg(feature = "encryption")]
+extern crate hmac;
+#[cfg(feature = "encryption")]
+extern crate sha2;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "url")]
+extern crate url;
+#[cfg(feature = "uuid")]
+extern crate uuid;
+
+pub mod attribute_type;
+pub mod attribute_value;
+pub mod backend;
+pub mod coercion;
+pub mod common_types;
+pub mod compression;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod errors;
+pub mod expr;
+#[cfg(feature = "export")]
+pub mod export;
+#[cfg(feature = "serde_json")]
+pub mod json;
+#[cfg(feature = "serde_json")]
+pub mod json_string;
+pub mod key_template;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod query;
+pub mod size;
+pub mod table;
+pub mod timestamp;
+pub mod update;
+pub mod wrappers;
+#[macro_use]
+pub mod value;
 
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeSet, HashSet, HashMap};
 use std::convert::TryFrom;
 
-use rusoto_dynamodb::AttributeValue;
-
 use errors::ConversionError;
+pub use attribute_type::AttributeType;
+pub use attribute_value::AttributeValue;
+pub use value::{Number, Value};
 
 
 type ConversionResult<T> = Result<T, ConversionError>;
 
 
+/// Collects the members of a collection which already has a stable iteration
+/// order (sequences, ordered and insertion-ordered sets).
+fn ordered_members<T, C: IntoIterator<Item = T>>(collection: C) -> Vec<T> {
+    collection.into_iter().collect()
+}
+
+/// Collects the members of a hashed set. The iteration order of these is not
+/// deterministic, so the members are sorted to produce stable output for
+/// snapshots and request signing.
+fn sorted_members<T: Ord, C: IntoIterator<Item = T>>(collection: C) -> Vec<T> {
+    let mut members: Vec<T> = collection.into_iter().collect();
+    members.sort();
+    members
+}
+
+
+#[macro_use]
 mod macros {
 
-    use super::{
-        AttributeValue, ConversionError, ConversionResult, AttributeValueConverter
-    };
+    macro_rules! attribute_value {
+        ($field:ident, $value:expr) => {
+            $crate::AttributeValue {
+                $field: Some($value),
+                .. $crate::AttributeValue::default()
+            }
+        }
+    }
+
+    macro_rules! numeric_converter {
+        ($type:ty) => {
+            impl $crate::AttributeValueConverter for $type {
+                fn from_attribute_value(
+                    attribute_value: $crate::AttributeValue
+                ) -> $crate::ConversionResult<Self> {
+                    if $crate::coercion::is_lenient_decoding() {
+                        return Self::coerce_from_attribute_value(attribute_value);
+                    }
+                    $crate::AttributeType::N.expect(attribute_value)?.n
+                        .ok_or($crate::ConversionError::MissingValue)
+                        .and_then(|number_string| {
+                            number_string.parse()
+                                .map_err(|_| $crate::ConversionError::InvalidValue)
+                        })
+                }
 
-    pub macro attribute_value($field:ident, $value:expr) {
-        AttributeValue {
-            $field: Some($value),
-            .. AttributeValue::default()
+                fn coerce_from_attribute_value(
+                    attribute_value: $crate::AttributeValue
+                ) -> $crate::ConversionResult<Self> {
+                    $crate::coercion::number_string(attribute_value)?.parse()
+                        .map_err(|_| $crate::ConversionError::InvalidValue)
+                }
+
+                fn to_attribute_value(self) -> $crate::AttributeValue {
+                    attribute_value!(n, self.to_string())
+                }
+            }
+
+            impl $crate::ScalarKey for $type {
+                const ATTRIBUTE_TYPE: $crate::AttributeType = $crate::AttributeType::N;
+            }
+
+            impl $crate::update::NumberAttribute for $type {}
+
+            impl $crate::update::AddAttribute for $type {}
         }
     }
 
-    pub macro numeric_converter($type:ty) {
-        impl AttributeValueConverter for $type {
-            fn from_attribute_value(
-                attribute_value: AttributeValue
-            ) -> ConversionResult<Self> {
-                attribute_value.n
-                    .ok_or(ConversionError::MissingValue)
-                    .and_then(|number_string| {
-                        number_string.parse()
-                            .map_err(|_| ConversionError::InvalidValue)
-                    })
+    macro_rules! numeric_set_converter {
+        ($type:ty => $collection:ty, $members:path) => {
+            impl $crate::AttributeValueConverter for $collection {
+                fn from_attribute_value(
+                    attribute_value: $crate::AttributeValue
+                ) -> $crate::ConversionResult<Self> {
+                    $crate::AttributeType::NS.expect(attribute_value)?.ns
+                        .ok_or($crate::ConversionError::MissingValue)?
+                        .into_iter()
+                        .map(|number_string| number_string.parse::<$type>()
+                            .map_err(|_| $crate::ConversionError::InvalidValue))
+                        .collect()
+                }
+
+                fn to_attribute_value(self) -> $crate::AttributeValue {
+                    attribute_value!(
+                        ns, $members(self).into_iter()
+                            .map(|item: $type| item.to_string()).collect()
+                    )
+                }
             }
-            
-            fn to_attribute_value(self) -> AttributeValue {
-                attribute_value!(n, self.to_string())
+
+            impl $crate::update::SetAttribute for $collection {}
+
+            impl $crate::update::AddAttribute for $collection {}
+
+            impl $crate::expr::Contains for $collection {
+                type Element = $type;
             }
         }
     }
 
-    pub macro numeric_set_converter($type:ty => $collection:ty) {
-        impl AttributeValueConverter for $collection {
-            fn from_attribute_value(
-                attribute_value: AttributeValue
-            ) -> ConversionResult<Self> {
-                let mut number_string_vec = attribute_value.ns
-                    .ok_or(ConversionError::MissingValue)?;
-                let mut results: Vec<ConversionResult<$type>>= number_string_vec
-                    .drain(..).map(
-                        |number_string| number_string.parse()
-                            .map_err(|_| ConversionError::InvalidValue))
-                    .collect();
-                let aggregated_result = results.drain(..).collect();
-                aggregated_result
-            } 
-            
-            fn to_attribute_value(self) -> AttributeValue {
-                attribute_value!(
-                    ns, self.iter().cloned()
-                        .map(|item| item.to_string()).collect()
-                )
+    macro_rules! set_converter {
+        ($field:ident: $kind:ident, $type:ty => $collection:ty, $members:path) => {
+            impl $crate::AttributeValueConverter for $collection {
+                fn from_attribute_value(
+                    attribute_value: $crate::AttributeValue
+                ) -> $crate::ConversionResult<Self> {
+                    $crate::AttributeType::$kind.expect(attribute_value)?.$field
+                        .ok_or($crate::ConversionError::MissingValue)
+                        .map(|values| values.into_iter().map(<$type>::from).collect())
+                }
+
+                fn to_attribute_value(self) -> $crate::AttributeValue {
+                    attribute_value!(
+                        $field, $members(self).into_iter().map(Into::into).collect()
+                    )
+                }
+            }
+
+            impl $crate::update::SetAttribute for $collection {}
+
+            impl $crate::update::AddAttribute for $collection {}
+
+            impl $crate::expr::Contains for $collection {
+                type Element = $type;
             }
         }
     }
@@ -84,6 +212,19 @@ pub trait DynamoDBItem
     : TryFrom<HashMap<String, AttributeValue>, Error = ConversionError>
     + Into<HashMap<String, AttributeValue>> {
     fn get_attribute_names() -> Vec<String>;
+
+    /// Converts the item into its attribute map, failing when one of its
+    /// fields can't be stored, like serde fields which can't be serialized,
+    /// for which the `Into` conversion panics. The derive implements it.
+    fn try_into_attribute_map(self) -> ConversionResult<HashMap<String, AttributeValue>> {
+        Ok(self.into())
+    }
+
+    /// The size of the item as counted by DynamoDB against the maximum item
+    /// size and for capacity consumption. See the `size` module.
+    fn estimated_size(&self) -> usize where Self: Clone {
+        size::item_size(&self.clone().into())
+    }
 }
 
 
@@ -91,85 +232,230 @@ pub trait DynamoDBItem
 /// for structs which are meant to be stored in dynamodb.
 pub trait DynamoDBInsertable: DynamoDBItem {
     fn get_key(&self) -> HashMap<String, AttributeValue>;
+
+    /// The key of the item in one of the secondary indexes of its table, as
+    /// declared with the `gsi_hash`, `gsi_range` and `lsi_range` attributes of
+    /// the derive. `None` when the index is unknown, or when the item leaves
+    /// out an attribute of the index key and so isn't part of the index.
+    fn get_index_key(&self, _index: &str) -> Option<HashMap<String, AttributeValue>> {
+        None
+    }
 }
 
 pub trait AttributeValueConverter: Sized {
     fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self>;
     fn to_attribute_value(self) -> AttributeValue;
-}
 
-impl AttributeValueConverter for String {
-    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        attribute_value.s.ok_or(ConversionError::MissingValue)
+    /// Converts into an attribute value, failing instead of panicking when
+    /// the value can't be stored, like the items with serde fields which
+    /// can't be serialized. Types whose conversion can't fail don't need to
+    /// implement it.
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        Ok(self.to_attribute_value())
     }
 
-    fn to_attribute_value(self) -> AttributeValue {
-        macros::attribute_value!(s, self)
+    /// Converts from an attribute value accepting the compatible alternate
+    /// representations described in the `coercion` module. Types without
+    /// alternate representations convert strictly.
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        Self::from_attribute_value(attribute_value)
     }
 }
 
-impl AttributeValueConverter for HashSet<String> {
+impl AttributeValueConverter for String {
     fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        attribute_value
-            .ss
+        if coercion::is_lenient_decoding() {
+            return Self::coerce_from_attribute_value(attribute_value);
+        }
+        AttributeType::S.expect(attribute_value)?.s
             .ok_or(ConversionError::MissingValue)
-            .and_then(|mut vec| Ok(vec.drain(..).collect()))
     }
 
-    fn to_attribute_value(mut self) -> AttributeValue {
-        macros::attribute_value!(ss, self.drain().collect())
-    }
-}
-
-impl AttributeValueConverter for HashSet<Vec<u8>> {
-    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        attribute_value
-            .bs
-            .ok_or(ConversionError::MissingValue)
-            .and_then(|mut vec| Ok(vec.drain(..).collect()))
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        coercion::string(attribute_value)
     }
 
-    fn to_attribute_value(mut self) -> AttributeValue {
-        macros::attribute_value!(bs, self.drain().collect())
+    fn to_attribute_value(self) -> AttributeValue {
+        attribute_value!(s, self)
     }
 }
 
 impl AttributeValueConverter for Vec<u8> {
     fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        attribute_value.b.ok_or(ConversionError::MissingValue)
+        AttributeType::B.expect(attribute_value)?.b.ok_or(ConversionError::MissingValue)
     }
 
     fn to_attribute_value(self) -> AttributeValue {
-        macros::attribute_value!(b, self)
+        attribute_value!(b, self)
     }
 }
 
+impl ScalarKey for String {
+    const ATTRIBUTE_TYPE: AttributeType = AttributeType::S;
+}
+
+impl ScalarKey for Vec<u8> {
+    const ATTRIBUTE_TYPE: AttributeType = AttributeType::B;
+}
+
 impl AttributeValueConverter for bool {
     fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        attribute_value.bool.ok_or(ConversionError::MissingValue)
+        if coercion::is_lenient_decoding() {
+            return Self::coerce_from_attribute_value(attribute_value);
+        }
+        AttributeType::Bool.expect(attribute_value)?.bool
+            .ok_or(ConversionError::MissingValue)
+    }
+
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        coercion::boolean(attribute_value)
     }
 
     fn to_attribute_value(self) -> AttributeValue {
-        macros::attribute_value!(bool, self)
+        attribute_value!(bool, self)
     }
 }
 
-impl<T: DynamoDBItem> AttributeValueConverter for T {
-    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        attribute_value
-            .m
-            .ok_or(ConversionError::MissingValue)
-            .and_then(|attribute_map| T::try_from(attribute_map))
+/// Converts an item from the `M` attribute it is nested in. The derived
+/// `AttributeValueConverter` implementations for items delegate to it.
+pub fn item_from_attribute_value<T: DynamoDBItem>(
+    attribute_value: AttributeValue
+) -> ConversionResult<T> {
+    AttributeType::M.expect(attribute_value)?
+        .m
+        .ok_or(ConversionError::MissingValue)
+        .and_then(|attribute_map| T::try_from(attribute_map))
+}
+
+/// Converts an item into an `M` attribute so that it can be nested in
+/// another item.
+pub fn item_to_attribute_value<T: DynamoDBItem>(item: T) -> AttributeValue {
+    attribute_value!(m, item.into())
+}
+
+/// The fallible form of `item_to_attribute_value`, see
+/// `DynamoDBItem::try_into_attribute_map`.
+pub fn item_try_to_attribute_value<T: DynamoDBItem>(item: T) -> ConversionResult<AttributeValue> {
+    item.try_into_attribute_map().map(|attribute_map| attribute_value!(m, attribute_map))
+}
+
+/// Marks the types which are stored as the elements of an `L` attribute when
+/// collected in a `Vec`. It is derived for items and implemented by the
+/// pointer types wrapping them.
+pub trait ListElement: AttributeValueConverter {}
+
+/// Implements `AttributeValueConverter` and `ListElement` for an item which
+/// implements `DynamoDBItem` by hand, so that it can be nested in other items
+/// and put in lists like derived items:
+///
+/// ```ignore
+/// korat::item_converter!(Address);
+/// ```
+#[macro_export]
+macro_rules! item_converter {
+    ($name:ty) => {
+        impl $crate::AttributeValueConverter for $name {
+            fn from_attribute_value(
+                attribute_value: $crate::AttributeValue
+            ) -> Result<Self, $crate::errors::ConversionError> {
+                $crate::item_from_attribute_value(attribute_value)
+            }
+
+            fn to_attribute_value(self) -> $crate::AttributeValue {
+                $crate::item_to_attribute_value(self)
+            }
+
+            fn try_to_attribute_value(
+                self
+            ) -> Result<$crate::AttributeValue, $crate::errors::ConversionError> {
+                $crate::item_try_to_attribute_value(self)
+            }
+        }
+
+        impl $crate::ListElement for $name {}
     }
+}
 
-    fn to_attribute_value(self) -> AttributeValue {
-        macros::attribute_value!(m, self.into())
+/// Marks the types which are stored as a scalar `S`, `N` or `B` attribute,
+/// the only types DynamoDB accepts for hash and range keys. The derive checks
+/// the types of key fields against it.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used as a hash or range key",
+    label = "keys must be stored as an S, N or B attribute",
+    note = "options, sets, lists, maps, booleans and nested items can't be keys"
+)]
+pub trait ScalarKey: AttributeValueConverter {
+    /// The type of the attribute the key is stored as, one of `S`, `N` or
+    /// `B`, as declared in the attribute definitions of a table.
+    const ATTRIBUTE_TYPE: AttributeType;
+}
+
+/// Fails to compile unless `T` can be used as a key. Used by the derive.
+#[doc(hidden)]
+pub const fn assert_scalar_key<T: ScalarKey>() {}
+
+/// Marks the types which can be stored as the keys of a secondary index.
+/// Items leaving out an index key attribute aren't part of the index, so on
+/// top of the `ScalarKey` types, options of them can be used for sparse
+/// indexes.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used as a secondary index key",
+    label = "index keys must be stored as an S, N or B attribute, or be left out",
+    note = "sets, lists, maps, booleans and nested items can't be index keys"
+)]
+pub trait IndexKey: AttributeValueConverter {
+    /// The type of the attribute the key is stored as when present.
+    const ATTRIBUTE_TYPE: AttributeType;
+}
+
+impl<T: ScalarKey> IndexKey for T {
+    const ATTRIBUTE_TYPE: AttributeType = <T as ScalarKey>::ATTRIBUTE_TYPE;
+}
+
+impl<T: ScalarKey> IndexKey for Option<T> {
+    const ATTRIBUTE_TYPE: AttributeType = T::ATTRIBUTE_TYPE;
+}
+
+/// Fails to compile unless `T` can be used as an index key. Used by the
+/// derive.
+#[doc(hidden)]
+pub const fn assert_index_key<T: IndexKey>() {}
+
+/// Fails to compile unless `T` can be a component of a key template, which
+/// is written into a string key. Used by the derive.
+#[doc(hidden)]
+pub const fn assert_key_component<T: ScalarKey>() {
+    if let AttributeType::B = T::ATTRIBUTE_TYPE {
+        panic!("binary keys can't be components of key templates");
+    }
+}
+
+/// Hands back an index key unless one of its attributes is absent, in which
+/// case the item isn't part of the index. Used by the derive.
+#[doc(hidden)]
+pub fn index_key(
+    key: HashMap<String, AttributeValue>
+) -> Option<HashMap<String, AttributeValue>> {
+    let is_present = |attribute_value: &AttributeValue| {
+        AttributeType::single(attribute_value).is_ok_and(|found| found != AttributeType::Null)
+    };
+    if key.values().all(is_present) {
+        Some(key)
+    } else {
+        None
     }
 }
 
-impl<T: DynamoDBItem> AttributeValueConverter for Vec<T> {
+impl<T: ListElement> AttributeValueConverter for Vec<T> {
     fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        let mut convertable_vec = attribute_value.l.ok_or(ConversionError::MissingValue)?;
+        let mut convertable_vec = AttributeType::L.expect(attribute_value)?
+            .l.ok_or(ConversionError::MissingValue)?;
         let results = convertable_vec
             .drain(..)
             .map(|convertable| {
@@ -180,23 +466,42 @@ impl<T: DynamoDBItem> AttributeValueConverter for Vec<T> {
     }
 
     fn to_attribute_value(mut self) -> AttributeValue {
-        macros::attribute_value!(
+        attribute_value!(
             l,
             self.drain(..)
-                .map(|item| macros::attribute_value!(m, item.into()))
+                .map(AttributeValueConverter::to_attribute_value)
                 .collect()
         )
     }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        let elements = self.into_iter()
+            .map(AttributeValueConverter::try_to_attribute_value)
+            .collect::<ConversionResult<_>>()?;
+        Ok(attribute_value!(l, elements))
+    }
+}
+
+/// Maps the errors which stand for an absent value to `None`.
+fn optional<T>(result: ConversionResult<T>) -> ConversionResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ConversionError::MissingValue) |
+        Err(ConversionError::MissingField) |
+        Err(ConversionError::TypeMismatch { found: AttributeType::Null, .. }) => Ok(None),
+        Err(err) => Err(err),
+    }
 }
 
 impl<T: AttributeValueConverter> AttributeValueConverter for Option<T> {
     fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
-        match AttributeValueConverter::from_attribute_value(attribute_value) {
-            Ok(value) => Ok(Some(value)),
-            Err(ConversionError::MissingValue) |
-            Err(ConversionError::MissingField) => Ok(None),
-            Err(err) => Err(err),
-        }
+        optional(T::from_attribute_value(attribute_value))
+    }
+
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        optional(T::coerce_from_attribute_value(attribute_value))
     }
 
     fn to_attribute_value(self) -> AttributeValue {
@@ -205,37 +510,68 @@ impl<T: AttributeValueConverter> AttributeValueConverter for Option<T> {
             None => AttributeValue::default(),
         }
     }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        match self {
+            Some(value) => value.try_to_attribute_value(),
+            None => Ok(AttributeValue::default()),
+        }
+    }
 }
 
-macros::numeric_converter!(u16);
-macros::numeric_converter!(u32);
-macros::numeric_converter!(i32);
-macros::numeric_converter!(i64);
-macros::numeric_converter!(f32);
-macros::numeric_converter!(f64);
-
-macros::numeric_set_converter!(u16 => HashSet<u16>);
-macros::numeric_set_converter!(u16 => Vec<u16>);
-macros::numeric_set_converter!(u32 => HashSet<u32>);
-macros::numeric_set_converter!(u32 => Vec<u32>);
-macros::numeric_set_converter!(i32 => HashSet<i32>);
-macros::numeric_set_converter!(i32 => Vec<i32>);
-macros::numeric_set_converter!(i64 => HashSet<i64>);
-macros::numeric_set_converter!(i64 => Vec<i64>);
-macros::numeric_set_converter!(f32 => Vec<f32>);
-macros::numeric_set_converter!(f64 => Vec<f64>);
+numeric_converter!(u16);
+numeric_converter!(u32);
+numeric_converter!(i32);
+numeric_converter!(i64);
+numeric_converter!(f32);
+numeric_converter!(f64);
+
+numeric_set_converter!(u16 => HashSet<u16>, sorted_members);
+numeric_set_converter!(u16 => BTreeSet<u16>, ordered_members);
+numeric_set_converter!(u16 => Vec<u16>, ordered_members);
+numeric_set_converter!(u32 => HashSet<u32>, sorted_members);
+numeric_set_converter!(u32 => BTreeSet<u32>, ordered_members);
+numeric_set_converter!(u32 => Vec<u32>, ordered_members);
+numeric_set_converter!(i32 => HashSet<i32>, sorted_members);
+numeric_set_converter!(i32 => BTreeSet<i32>, ordered_members);
+numeric_set_converter!(i32 => Vec<i32>, ordered_members);
+numeric_set_converter!(i64 => HashSet<i64>, sorted_members);
+numeric_set_converter!(i64 => BTreeSet<i64>, ordered_members);
+numeric_set_converter!(i64 => Vec<i64>, ordered_members);
+numeric_set_converter!(f32 => Vec<f32>, ordered_members);
+numeric_set_converter!(f64 => Vec<f64>, ordered_members);
+
+set_converter!(ss: SS, String => HashSet<String>, sorted_members);
+set_converter!(ss: SS, String => BTreeSet<String>, ordered_members);
+set_converter!(bs: BS, Vec<u8> => HashSet<Vec<u8>>, sorted_members);
+set_converter!(bs: BS, Vec<u8> => BTreeSet<Vec<u8>>, ordered_members);
+
+#[cfg(feature = "indexmap")]
+mod indexmap_converters {
+    use indexmap::IndexSet;
+
+    use super::ordered_members;
+
+    numeric_set_converter!(u16 => IndexSet<u16>, ordered_members);
+    numeric_set_converter!(u32 => IndexSet<u32>, ordered_members);
+    numeric_set_converter!(i32 => IndexSet<i32>, ordered_members);
+    numeric_set_converter!(i64 => IndexSet<i64>, ordered_members);
+
+    set_converter!(ss: SS, String => IndexSet<String>, ordered_members);
+    set_converter!(bs: BS, Vec<u8> => IndexSet<Vec<u8>>, ordered_members);
+}
 
 
 #[cfg(test)]
 mod test {
     use std::default::Default;
     use std::convert::TryFrom;
-    use std::collections::{HashSet, HashMap};
+    use std::collections::{BTreeSet, HashSet, HashMap};
 
-    use rusoto_dynamodb::AttributeValue;
+    use AttributeValue;
 
     use errors::ConversionError;
-    use super::{AttributeValueConverter, DynamoDBItem};
+    use super::{AttributeType, AttributeValueConverter, DynamoDBItem};
 
 
     macro_rules! test_for_numeric_types {
@@ -307,10 +643,10 @@ mod test {
     }
 
     test_for_numeric_types![
-        u16_tests(u16, 123, [super::HashSet<u16>, Vec<u16>]),
-        u32_tests(u32, 1234, [super::HashSet<u32>, Vec<u32>]),
-        i32_tests(i32, 1234, [super::HashSet<i32>, Vec<i32>]),
-        i64_tests(i64, 1234, [super::HashSet<i64>, Vec<i64>]),
+        u16_tests(u16, 123, [super::HashSet<u16>, super::BTreeSet<u16>, Vec<u16>]),
+        u32_tests(u32, 1234, [super::HashSet<u32>, super::BTreeSet<u32>, Vec<u32>]),
+        i32_tests(i32, 1234, [super::HashSet<i32>, super::BTreeSet<i32>, Vec<i32>]),
+        i64_tests(i64, 1234, [super::HashSet<i64>, super::BTreeSet<i64>, Vec<i64>]),
         f32_tests(f32, 123.4, [Vec<f32>]),
         f64_tests(f64, 123.4, [Vec<f64>])
     ];
@@ -357,6 +693,62 @@ mod test {
         }
     }
 
+    #[test]
+    fn can_convert_from_attribute_value_ordered_string_set() {
+        let input = vec!["two".to_string(), "one".to_string()];
+        let expected_set: BTreeSet<String> = input.iter().cloned().collect();
+        let av = AttributeValue {
+            ss: Some(input),
+            ..AttributeValue::default()
+        };
+
+        let converted: BTreeSet<String> = AttributeValueConverter::from_attribute_value(av).unwrap();
+        assert_eq!(expected_set, converted);
+    }
+
+    #[test]
+    fn ordered_string_set_is_converted_in_order() {
+        let value: BTreeSet<String> = ["b", "c", "a"].iter()
+            .map(|s| s.to_string()).collect();
+        let converted = ::AttributeValueConverter::to_attribute_value(value);
+        assert_eq!(vec!["a", "b", "c"], converted.ss.unwrap());
+    }
+
+    #[test]
+    fn ordered_number_set_is_converted_in_numeric_order() {
+        let value: BTreeSet<i32> = vec![10, -1, 2].into_iter().collect();
+        let converted = ::AttributeValueConverter::to_attribute_value(value);
+        assert_eq!(vec!["-1", "2", "10"], converted.ns.unwrap());
+    }
+
+    #[test]
+    fn hashed_sets_are_converted_in_sorted_order() {
+        let strings: HashSet<String> = ["b", "c", "a"].iter()
+            .map(|s| s.to_string()).collect();
+        let converted = ::AttributeValueConverter::to_attribute_value(strings);
+        assert_eq!(vec!["a", "b", "c"], converted.ss.unwrap());
+
+        let numbers: HashSet<i64> = vec![10, -1, 2].into_iter().collect();
+        let converted = ::AttributeValueConverter::to_attribute_value(numbers);
+        assert_eq!(vec!["-1", "2", "10"], converted.ns.unwrap());
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn insertion_ordered_sets_keep_their_order() {
+        use indexmap::IndexSet;
+
+        let value: IndexSet<String> = ["b", "c", "a"].iter()
+            .map(|s| s.to_string()).collect();
+        let converted = ::AttributeValueConverter::to_attribute_value(value.clone());
+        let retrieved = converted.ss.clone().unwrap();
+        assert_eq!(vec!["b", "c", "a"], retrieved);
+
+        let deserialized: IndexSet<String> = AttributeValueConverter
+            ::from_attribute_value(converted).unwrap();
+        assert_eq!(value, deserialized);
+    }
+
     #[test]
     fn can_convert_from_binary() {
         let input = vec![1, 2, 3, 4];
@@ -393,6 +785,22 @@ mod test {
         assert_eq!(expected_set, converted);
     }
 
+    #[test]
+    fn can_convert_from_ordered_binary_set() {
+        let input = vec![
+            "one".to_string().into_bytes(), "two".to_string().into_bytes()
+        ];
+        let expected_set: BTreeSet<Vec<u8>> = input.iter().cloned().collect();
+        let av = AttributeValue {
+            bs: Some(input),
+            ..AttributeValue::default()
+        };
+
+        let converted: BTreeSet<Vec<u8>> = AttributeValueConverter::from_attribute_value(av)
+            .unwrap();
+        assert_eq!(expected_set, converted);
+    }
+
     #[test]
     fn can_convert_binary_set_into_attribute_value() {
         let mut value = HashSet::new();
@@ -412,13 +820,13 @@ mod test {
         };
 
         let converted: bool = AttributeValueConverter::from_attribute_value(av).unwrap();
-        assert_eq!(true, converted);
+        assert!(converted);
     }
 
     #[test]
     fn can_convert_bool_into_attribute_value() {
         let value = true;
-        let converted = ::AttributeValueConverter::to_attribute_value(value.clone());
+        let converted = ::AttributeValueConverter::to_attribute_value(value);
         assert_eq!(value, converted.bool.unwrap());
     }
 
@@ -429,7 +837,7 @@ mod test {
 
     impl DynamoDBItem for Example {
         fn get_attribute_names() -> Vec<String> {
-            vec!["key"].iter().map(|s| s.to_string()).collect()
+            ["key"].iter().map(|s| s.to_string()).collect()
         }
     }
 
@@ -465,6 +873,8 @@ mod test {
         }
     }
 
+    item_converter!(Example);
+
     #[test]
     fn can_convert_from_dynamodb_item() {
 
@@ -566,17 +976,75 @@ mod test {
         assert_eq!(Some(true), converted);
     }
 
+    #[test]
+    fn can_convert_from_option_null() {
+        let av = AttributeValue {
+            null: Some(true),
+            ..AttributeValue::default()
+        };
+
+        let converted: Option<String> = AttributeValueConverter::from_attribute_value(av).unwrap();
+        assert!(converted.is_none());
+    }
+
+    #[test]
+    fn fails_to_convert_option_of_wrong_type() {
+        let av = AttributeValue {
+            n: Some("123".to_string()),
+            ..AttributeValue::default()
+        };
+
+        let converted: Result<Option<String>, _> = AttributeValueConverter
+            ::from_attribute_value(av);
+        match converted {
+            Err(ConversionError::TypeMismatch { expected, found }) => {
+                assert_eq!(AttributeType::S, expected);
+                assert_eq!(AttributeType::N, found);
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fails_on_type_mismatch() {
+        let av = AttributeValue {
+            n: Some("123".to_string()),
+            ..AttributeValue::default()
+        };
+
+        let converted: Result<String, _> = AttributeValueConverter::from_attribute_value(av);
+        match converted {
+            Err(ConversionError::TypeMismatch { .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fails_on_multiple_members() {
+        let av = AttributeValue {
+            s: Some("value".to_string()),
+            n: Some("123".to_string()),
+            ..AttributeValue::default()
+        };
+
+        let converted: Result<String, _> = AttributeValueConverter::from_attribute_value(av);
+        match converted {
+            Err(ConversionError::MalformedValue(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
     #[test]
     fn can_convert_option_none_into_attribute_value() {
         let value: Option<bool> = None;
-        let converted = ::AttributeValueConverter::to_attribute_value(value.clone());
+        let converted = ::AttributeValueConverter::to_attribute_value(value);
         assert!(converted.bool.is_none());
     }
 
     #[test]
     fn can_convert_option_with_value_into_attribute_value() {
         let value: Option<bool> = Some(true);
-        let converted = ::AttributeValueConverter::to_attribute_value(value.clone());
-        assert_eq!(value.unwrap(), converted.bool.unwrap());
+        let converted = ::AttributeValueConverter::to_attribute_value(value);
+        assert_eq!(value, converted.bool);
     }
 }
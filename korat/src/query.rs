@@ -0,0 +1,231 @@
+//! Key conditions for queries, built from the key structs of derived items.
+//!
+//! Queries take the hash key of the items along with an optional condition
+//! on their range key. Every key struct generated by the derive has a
+//! `partial` constructor taking the hash key fields, which returns a
+//! `PartialKey` typed after the range key field:
+//!
+//! ```rust,ignore
+//! let condition = OrderKey::partial("u1".to_string())
+//!     .between(10, 20)
+//!     .key_condition();
+//! let request = QueryInput {
+//!     table_name: "orders".to_string(),
+//!     index_name: condition.index_name.clone(),
+//!     expression_attribute_values: Some(condition.values_for::<Rusoto>()),
+//!     key_condition_expression: Some(condition.expression),
+//!     expression_attribute_names: Some(condition.names),
+//!     ..QueryInput::default()
+//! };
+//! ```
+//!
+//! or `backend::rusoto::query_input("orders", condition)`. The values are
+//! converted with the converters of the key fields, and the attribute names
+//! go through placeholders so that reserved words can be keys. The key
+//! structs of secondary indexes set the index name of the condition.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use AttributeValue;
+use backend::{Backend, into_attribute_map};
+
+
+/// The range key type of the partial keys of indexes without a range key,
+/// which has no values so that no range condition can be set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoRange {}
+
+/// Marks the types which are stored as an `S` or `B` attribute, the only
+/// ones DynamoDB can match the prefixes of, in key conditions as well as in
+/// the conditions of `expr`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't stored as a string or binary value",
+    label = "only string and binary values can be matched with begins_with"
+)]
+pub trait PrefixKey {}
+
+impl PrefixKey for String {}
+impl PrefixKey for Vec<u8> {}
+
+/// The hash key of a query, with an optional condition on the range key of
+/// type `R`.
+pub struct PartialKey<R> {
+    index_name: Option<&'static str>,
+    hash: (&'static str, AttributeValue),
+    range: Option<RangeKey<R>>,
+    condition: Option<(&'static str, Vec<AttributeValue>)>,
+}
+
+/// The range key attribute of a partial key, with the converter of its
+/// field.
+struct RangeKey<R> {
+    name: &'static str,
+    to_attribute_value: fn(R) -> AttributeValue,
+}
+
+/// The name and converter of a range key, as passed by the derive.
+type RangeDeclaration<R> = (&'static str, fn(R) -> AttributeValue);
+
+impl<R> PartialKey<R> {
+    /// Used by the derive, see the `partial` constructors of key structs.
+    #[doc(hidden)]
+    pub fn new(
+        index_name: Option<&'static str>,
+        hash: (&'static str, AttributeValue),
+        range: Option<RangeDeclaration<R>>
+    ) -> Self {
+        PartialKey {
+            index_name,
+            hash,
+            range: range.map(|(name, to_attribute_value)| RangeKey { name, to_attribute_value }),
+            condition: None,
+        }
+    }
+
+    pub fn equal_to(self, value: R) -> Self {
+        self.with_condition("#range = :range", vec![value])
+    }
+
+    pub fn less_than(self, value: R) -> Self {
+        self.with_condition("#range < :range", vec![value])
+    }
+
+    pub fn less_than_or_equal(self, value: R) -> Self {
+        self.with_condition("#range <= :range", vec![value])
+    }
+
+    pub fn greater_than(self, value: R) -> Self {
+        self.with_condition("#range > :range", vec![value])
+    }
+
+    pub fn greater_than_or_equal(self, value: R) -> Self {
+        self.with_condition("#range >= :range", vec![value])
+    }
+
+    /// Matches range keys from `low` to `high`, both included.
+    pub fn between(self, low: R, high: R) -> Self {
+        self.with_condition("#range BETWEEN :range AND :range_end", vec![low, high])
+    }
+
+    /// Replaces the range condition. Every method taking a value of type `R`
+    /// has a range key, since partial keys without one take `NoRange` values.
+    fn with_condition(mut self, expression: &'static str, values: Vec<R>) -> Self {
+        let range = self.range.as_ref().expect("partial keys of type R have a range key");
+        let values = values.into_iter().map(range.to_attribute_value).collect();
+        self.condition = Some((expression, values));
+        self
+    }
+
+    pub fn key_condition(self) -> KeyCondition {
+        let (hash_name, hash_value) = self.hash;
+        let mut expression = "#hash = :hash".to_string();
+        let mut names = HashMap::new();
+        let mut values = HashMap::new();
+        names.insert("#hash".to_string(), hash_name.to_string());
+        values.insert(":hash".to_string(), hash_value);
+
+        if let (Some(range), Some((condition, condition_values))) = (self.range, self.condition) {
+            expression.push_str(" AND ");
+            expression.push_str(condition);
+            names.insert("#range".to_string(), range.name.to_string());
+            for (placeholder, value) in [":range", ":range_end"].iter().zip(condition_values) {
+                values.insert(placeholder.to_string(), value);
+            }
+        }
+        KeyCondition {
+            index_name: self.index_name.map(str::to_string),
+            expression,
+            names,
+            values,
+        }
+    }
+}
+
+impl<R: PrefixKey> PartialKey<R> {
+    /// Matches range keys starting with `prefix`.
+    pub fn begins_with(self, prefix: R) -> Self {
+        self.with_condition("begins_with(#range, :range)", vec![prefix])
+    }
+}
+
+impl<R> fmt::Debug for PartialKey<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PartialKey")
+            .field("index_name", &self.index_name)
+            .field("hash", &self.hash)
+            .field("range", &self.range.as_ref().map(|range| range.name))
+            .field("condition", &self.condition)
+            .finish()
+    }
+}
+
+/// The `KeyConditionExpression` of a query along with the attribute names and
+/// values it refers to, and the index to query if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyCondition {
+    pub index_name: Option<String>,
+    pub expression: String,
+    pub names: HashMap<String, String>,
+    pub values: HashMap<String, AttributeValue>,
+}
+
+impl KeyCondition {
+    /// The attribute values converted for a backend, for example
+    /// `condition.values_for::<AwsSdk>()`.
+    pub fn values_for<B: Backend>(&self) -> HashMap<String, B::AttributeValue> {
+        into_attribute_map::<B>(self.values.clone())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use {AttributeValue, AttributeValueConverter};
+    use super::{NoRange, PartialKey};
+
+    fn partial_key() -> PartialKey<u32> {
+        PartialKey::new(
+            None,
+            ("user", "u1".to_string().to_attribute_value()),
+            Some(("number", u32::to_attribute_value))
+        )
+    }
+
+    #[test]
+    fn builds_hash_only_conditions() {
+        let condition = partial_key().key_condition();
+        assert_eq!("#hash = :hash", condition.expression);
+        assert_eq!("user", condition.names["#hash"]);
+        assert_eq!(1, condition.values.len());
+
+        let key: PartialKey<NoRange> = PartialKey::new(
+            Some("ByEmail"), ("email", "a@b.c".to_string().to_attribute_value()), None
+        );
+        assert_eq!(Some("ByEmail".to_string()), key.key_condition().index_name);
+    }
+
+    #[test]
+    fn builds_range_conditions_with_typed_values() {
+        let condition = partial_key().between(10, 20).key_condition();
+        assert_eq!("#hash = :hash AND #range BETWEEN :range AND :range_end", condition.expression);
+        assert_eq!("number", condition.names["#range"]);
+        assert_eq!(Some("20".to_string()), condition.values[":range_end"].n);
+
+        let condition = partial_key().between(1, 2).less_than(5).key_condition();
+        assert_eq!("#hash = :hash AND #range < :range", condition.expression);
+        assert_eq!(None::<&AttributeValue>, condition.values.get(":range_end"));
+    }
+
+    #[test]
+    fn matches_prefixes_of_string_keys() {
+        let key: PartialKey<String> = PartialKey::new(
+            None,
+            ("user", "u1".to_string().to_attribute_value()),
+            Some(("sort", String::to_attribute_value))
+        );
+        let condition = key.begins_with("ORDER#".to_string()).key_condition();
+        assert_eq!("#hash = :hash AND begins_with(#range, :range)", condition.expression);
+        assert_eq!(Some("ORDER#".to_string()), condition.values[":range"].s);
+    }
+}
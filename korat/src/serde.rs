@@ -0,0 +1,874 @@
+//! Conversion of any type implementing serde's `Serialize` and `Deserialize`
+//! to and from attribute values.
+//!
+//! The data model is mapped on attribute values as follows:
+//!
+//! * booleans are stored as `BOOL`, numbers as `N` and strings and chars
+//!   as `S` attributes; non finite floats, which DynamoDB numbers can't
+//!   represent, are rejected,
+//! * byte arrays are stored as `B` attributes,
+//! * `None` and unit values are stored as `NULL`,
+//! * sequences and tuples are stored as `L` attributes,
+//! * maps and structs are stored as `M` attributes; map keys should
+//!   serialize to strings, or to integers, booleans and chars which are
+//!   written as strings and parsed back like serde_json does,
+//! * unit enum variants are stored as their name in an `S` attribute and the
+//!   other variants as an `M` attribute with a single entry mapping the
+//!   variant name to its contents.
+//!
+//! Select it for a field with `#[korat(serde)]`.
+
+use std::collections::{hash_map, HashMap};
+use std::fmt::Display;
+use std::vec;
+
+use serde_crate::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor
+};
+use serde_crate::de::value::StringDeserializer;
+use serde_crate::ser::{self, Serialize};
+
+use errors::ConversionError;
+use super::{AttributeType, AttributeValue, ConversionResult};
+
+
+/// Converts a value into an attribute value.
+///
+/// # Panics
+///
+/// Panics when the value can't be represented as an attribute value, which
+/// is the case of non finite floats, of maps with keys of other types than
+/// strings, numbers, booleans and chars, and of values whose `Serialize`
+/// implementation fails. Use `try_to_attribute_value` to handle these cases,
+/// as the derive does in `DynamoDBItem::try_into_attribute_map`.
+pub fn to_attribute_value<T: Serialize>(value: T) -> AttributeValue {
+    try_to_attribute_value(value).unwrap_or_else(
+        |error| panic!("the value can't be stored as an attribute value: {}", error)
+    )
+}
+
+/// Converts a value into an attribute value, failing when the value can't be
+/// represented as one.
+pub fn try_to_attribute_value<T: Serialize>(value: T) -> ConversionResult<AttributeValue> {
+    value.serialize(Serializer)
+}
+
+pub fn from_attribute_value<T: DeserializeOwned>(
+    attribute_value: AttributeValue
+) -> ConversionResult<T> {
+    T::deserialize(Deserializer::new(attribute_value))
+}
+
+
+impl ser::Error for ConversionError {
+    fn custom<T: Display>(message: T) -> Self {
+        ConversionError::Custom(message.to_string())
+    }
+}
+
+impl de::Error for ConversionError {
+    fn custom<T: Display>(message: T) -> Self {
+        ConversionError::Custom(message.to_string())
+    }
+}
+
+
+fn string(value: String) -> AttributeValue {
+    AttributeValue {
+        s: Some(value),
+        ..AttributeValue::default()
+    }
+}
+
+fn number<T: ToString>(value: T) -> AttributeValue {
+    AttributeValue {
+        n: Some(value.to_string()),
+        ..AttributeValue::default()
+    }
+}
+
+fn null() -> AttributeValue {
+    AttributeValue {
+        null: Some(true),
+        ..AttributeValue::default()
+    }
+}
+
+fn map(entries: HashMap<String, AttributeValue>) -> AttributeValue {
+    AttributeValue {
+        m: Some(entries),
+        ..AttributeValue::default()
+    }
+}
+
+fn list(elements: Vec<AttributeValue>) -> AttributeValue {
+    AttributeValue {
+        l: Some(elements),
+        ..AttributeValue::default()
+    }
+}
+
+/// Wraps the contents of an enum variant in a single entry map keyed by the
+/// variant name.
+fn in_variant(variant: Option<&'static str>, value: AttributeValue) -> AttributeValue {
+    match variant {
+        Some(variant) => {
+            let mut entries = HashMap::new();
+            entries.insert(variant.to_string(), value);
+            map(entries)
+        },
+        None => value,
+    }
+}
+
+/// DynamoDB numbers can't represent NaN and infinities, like
+/// `value::Number`.
+fn float<T: Display>(value: T, is_finite: bool) -> ConversionResult<AttributeValue> {
+    if is_finite {
+        Ok(number(value))
+    } else {
+        Err(ConversionError::InvalidValue)
+    }
+}
+
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, value: bool) -> ConversionResult<AttributeValue> {
+        Ok(AttributeValue {
+            bool: Some(value),
+            ..AttributeValue::default()
+        })
+    }
+
+    fn serialize_i8(self, value: i8) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_i16(self, value: i16) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_i32(self, value: i32) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_i64(self, value: i64) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_u8(self, value: u8) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_u16(self, value: u16) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_u32(self, value: u32) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_u64(self, value: u64) -> ConversionResult<AttributeValue> {
+        Ok(number(value))
+    }
+
+    fn serialize_f32(self, value: f32) -> ConversionResult<AttributeValue> {
+        float(value, value.is_finite())
+    }
+
+    fn serialize_f64(self, value: f64) -> ConversionResult<AttributeValue> {
+        float(value, value.is_finite())
+    }
+
+    fn serialize_char(self, value: char) -> ConversionResult<AttributeValue> {
+        Ok(string(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> ConversionResult<AttributeValue> {
+        Ok(string(value.to_string()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> ConversionResult<AttributeValue> {
+        Ok(AttributeValue {
+            b: Some(value.to_vec()),
+            ..AttributeValue::default()
+        })
+    }
+
+    fn serialize_none(self) -> ConversionResult<AttributeValue> {
+        Ok(null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(
+        self, value: &T
+    ) -> ConversionResult<AttributeValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ConversionResult<AttributeValue> {
+        Ok(null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> ConversionResult<AttributeValue> {
+        Ok(null())
+    }
+
+    fn serialize_unit_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str
+    ) -> ConversionResult<AttributeValue> {
+        Ok(string(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self, _name: &'static str, value: &T
+    ) -> ConversionResult<AttributeValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self, _name: &'static str, _index: u32, variant: &'static str, value: &T
+    ) -> ConversionResult<AttributeValue> {
+        Ok(in_variant(Some(variant), value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, length: Option<usize>) -> ConversionResult<SeqSerializer> {
+        Ok(SeqSerializer::new(length, None))
+    }
+
+    fn serialize_tuple(self, length: usize) -> ConversionResult<SeqSerializer> {
+        Ok(SeqSerializer::new(Some(length), None))
+    }
+
+    fn serialize_tuple_struct(
+        self, _name: &'static str, length: usize
+    ) -> ConversionResult<SeqSerializer> {
+        Ok(SeqSerializer::new(Some(length), None))
+    }
+
+    fn serialize_tuple_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str, length: usize
+    ) -> ConversionResult<SeqSerializer> {
+        Ok(SeqSerializer::new(Some(length), Some(variant)))
+    }
+
+    fn serialize_map(self, _length: Option<usize>) -> ConversionResult<MapSerializer> {
+        Ok(MapSerializer::new(None))
+    }
+
+    fn serialize_struct(
+        self, _name: &'static str, _length: usize
+    ) -> ConversionResult<MapSerializer> {
+        Ok(MapSerializer::new(None))
+    }
+
+    fn serialize_struct_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str, _length: usize
+    ) -> ConversionResult<MapSerializer> {
+        Ok(MapSerializer::new(Some(variant)))
+    }
+}
+
+
+struct SeqSerializer {
+    elements: Vec<AttributeValue>,
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer {
+    fn new(length: Option<usize>, variant: Option<&'static str>) -> SeqSerializer {
+        SeqSerializer {
+            elements: Vec::with_capacity(length.unwrap_or_default()),
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> ConversionResult<()> {
+        self.elements.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> ConversionResult<AttributeValue> {
+        Ok(in_variant(self.variant, list(self.elements)))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ConversionResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ConversionResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ConversionResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ConversionResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+
+struct MapSerializer {
+    entries: HashMap<String, AttributeValue>,
+    next_key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl MapSerializer {
+    fn new(variant: Option<&'static str>) -> MapSerializer {
+        MapSerializer {
+            entries: HashMap::new(),
+            next_key: None,
+            variant,
+        }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> ConversionResult<()> {
+        self.entries.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> ConversionResult<AttributeValue> {
+        Ok(in_variant(self.variant, map(self.entries)))
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    /// Keys are stored as strings, which numbers and booleans are written to.
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> ConversionResult<()> {
+        let key = key.serialize(Serializer)?;
+        let boolean = key.bool.map(|value| value.to_string());
+        match key.s.or(key.n).or(boolean) {
+            Some(key) => {
+                self.next_key = Some(key);
+                Ok(())
+            },
+            None => Err(ConversionError::Custom(
+                "map keys should be strings, numbers, booleans or chars".to_string()
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> ConversionResult<()> {
+        let key = self.next_key.take().ok_or_else(|| ConversionError::Custom(
+            "map value serialized before its key".to_string()
+        ))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self, key: &'static str, value: &T
+    ) -> ConversionResult<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = AttributeValue;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self, key: &'static str, value: &T
+    ) -> ConversionResult<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> ConversionResult<AttributeValue> {
+        self.finish()
+    }
+}
+
+
+struct Deserializer {
+    attribute_value: AttributeValue,
+}
+
+impl Deserializer {
+    fn new(attribute_value: AttributeValue) -> Deserializer {
+        Deserializer { attribute_value }
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(
+            AttributeType::single(&self.attribute_value),
+            Ok(AttributeType::Null) | Err(ConversionError::MissingValue)
+        )
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(number: String, visitor: V) -> ConversionResult<V::Value> {
+    if let Ok(value) = number.parse::<u64>() {
+        visitor.visit_u64(value)
+    } else if let Ok(value) = number.parse::<i64>() {
+        visitor.visit_i64(value)
+    } else if let Ok(value) = number.parse::<f64>() {
+        visitor.visit_f64(value)
+    } else {
+        Err(ConversionError::InvalidFormat {
+            expected: "a number",
+            value: number,
+            reason: "not a valid number".to_string(),
+        })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = ConversionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ConversionResult<V::Value> {
+        let attribute_value = self.attribute_value;
+        let missing = ConversionError::MissingValue;
+        match AttributeType::single(&attribute_value)? {
+            AttributeType::S => visitor.visit_string(attribute_value.s.ok_or(missing)?),
+            AttributeType::N => visit_number(attribute_value.n.ok_or(missing)?, visitor),
+            AttributeType::B => visitor.visit_byte_buf(
+                attribute_value.b.ok_or(missing)?
+            ),
+            AttributeType::Bool => visitor.visit_bool(attribute_value.bool.ok_or(missing)?),
+            AttributeType::Null => visitor.visit_unit(),
+            AttributeType::M => visitor.visit_map(
+                MapDeserializer::new(attribute_value.m.ok_or(missing)?)
+            ),
+            AttributeType::L => visitor.visit_seq(
+                SeqDeserializer::new(attribute_value.l.ok_or(missing)?)
+            ),
+            AttributeType::SS => visitor.visit_seq(SeqDeserializer::new(
+                attribute_value.ss.ok_or(missing)?.into_iter().map(string).collect()
+            )),
+            AttributeType::NS => visitor.visit_seq(SeqDeserializer::new(
+                attribute_value.ns.ok_or(missing)?.into_iter().map(number).collect()
+            )),
+            AttributeType::BS => visitor.visit_seq(SeqDeserializer::new(
+                attribute_value.bs.ok_or(missing)?.into_iter()
+                    .map(|bytes| AttributeValue {
+                        b: Some(bytes),
+                        ..AttributeValue::default()
+                    })
+                    .collect()
+            )),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ConversionResult<V::Value> {
+        if self.is_null() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self, _name: &'static str, visitor: V
+    ) -> ConversionResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V
+    ) -> ConversionResult<V::Value> {
+        let attribute_value = self.attribute_value;
+        match AttributeType::single(&attribute_value)? {
+            AttributeType::S => visitor.visit_enum(VariantDeserializer {
+                variant: attribute_value.s.unwrap_or_default(),
+                value: None,
+            }),
+            AttributeType::M => {
+                let mut entries = attribute_value.m.unwrap_or_default().into_iter();
+                match (entries.next(), entries.next()) {
+                    (Some((variant, value)), None) => visitor.visit_enum(
+                        VariantDeserializer { variant, value: Some(value) }
+                    ),
+                    _ => Err(ConversionError::Custom(
+                        "enum variants should be maps with a single entry".to_string()
+                    )),
+                }
+            },
+            found => Err(ConversionError::TypeMismatch {
+                expected: AttributeType::M, found
+            }),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+
+/// Deserializes the keys of maps, which are strings. Keys of other scalar
+/// types are parsed back from them.
+struct KeyDeserializer {
+    key: String,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident($type:ty) => $visit:ident),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> ConversionResult<V::Value> {
+            match self.key.parse::<$type>() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => Err(ConversionError::InvalidFormat {
+                    expected: stringify!($type),
+                    value: self.key,
+                    reason: "the map key can't be parsed".to_string(),
+                }),
+            }
+        }
+    )*}
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = ConversionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ConversionResult<V::Value> {
+        visitor.visit_string(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool(bool) => visit_bool,
+        deserialize_i8(i8) => visit_i8,
+        deserialize_i16(i16) => visit_i16,
+        deserialize_i32(i32) => visit_i32,
+        deserialize_i64(i64) => visit_i64,
+        deserialize_i128(i128) => visit_i128,
+        deserialize_u8(u8) => visit_u8,
+        deserialize_u16(u16) => visit_u16,
+        deserialize_u32(u32) => visit_u32,
+        deserialize_u64(u64) => visit_u64,
+        deserialize_u128(u128) => visit_u128,
+        deserialize_f32(f32) => visit_f32,
+        deserialize_f64(f64) => visit_f64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ConversionResult<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self, _name: &'static str, visitor: V
+    ) -> ConversionResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V
+    ) -> ConversionResult<V::Value> {
+        let key: StringDeserializer<ConversionError> = self.key.into_deserializer();
+        de::Deserializer::deserialize_enum(key, name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+
+struct SeqDeserializer {
+    elements: vec::IntoIter<AttributeValue>,
+}
+
+impl SeqDeserializer {
+    fn new(elements: Vec<AttributeValue>) -> SeqDeserializer {
+        SeqDeserializer { elements: elements.into_iter() }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = ConversionError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self, seed: T
+    ) -> ConversionResult<Option<T::Value>> {
+        match self.elements.next() {
+            Some(element) => seed.deserialize(Deserializer::new(element)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+
+struct MapDeserializer {
+    entries: hash_map::IntoIter<String, AttributeValue>,
+    value: Option<AttributeValue>,
+}
+
+impl MapDeserializer {
+    fn new(entries: HashMap<String, AttributeValue>) -> MapDeserializer {
+        MapDeserializer { entries: entries.into_iter(), value: None }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = ConversionError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self, seed: K
+    ) -> ConversionResult<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer { key }).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self, seed: V
+    ) -> ConversionResult<V::Value> {
+        let value = self.value.take().ok_or(ConversionError::MissingValue)?;
+        seed.deserialize(Deserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+
+struct VariantDeserializer {
+    variant: String,
+    value: Option<AttributeValue>,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantDeserializer {
+    type Error = ConversionError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self, seed: V
+    ) -> ConversionResult<(V::Value, Self)> {
+        let variant: StringDeserializer<ConversionError> =
+            self.variant.clone().into_deserializer();
+        let variant = seed.deserialize(variant)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = ConversionError;
+
+    fn unit_variant(self) -> ConversionResult<()> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => de::Deserialize::deserialize(Deserializer::new(value)),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self, seed: T
+    ) -> ConversionResult<T::Value> {
+        let value = self.value.ok_or(ConversionError::MissingValue)?;
+        seed.deserialize(Deserializer::new(value))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self, _length: usize, visitor: V
+    ) -> ConversionResult<V::Value> {
+        let value = self.value.ok_or(ConversionError::MissingValue)?;
+        de::Deserializer::deserialize_seq(Deserializer::new(value), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self, _fields: &'static [&'static str], visitor: V
+    ) -> ConversionResult<V::Value> {
+        let value = self.value.ok_or(ConversionError::MissingValue)?;
+        de::Deserializer::deserialize_map(Deserializer::new(value), visitor)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use AttributeValue;
+
+    use errors::ConversionError;
+    use super::{from_attribute_value, to_attribute_value, try_to_attribute_value};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rectangle { width: u32, height: u32 },
+        Line(i32, i32),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Drawing {
+        name: String,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, bool>,
+        author: Option<String>,
+        origin: (i64, i64),
+    }
+
+    #[test]
+    fn can_round_trip_the_data_model() {
+        let mut tags = BTreeMap::new();
+        tags.insert("draft".to_string(), true);
+        let drawing = Drawing {
+            name: "korat".to_string(),
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Rectangle { width: 2, height: 3 },
+                Shape::Line(-1, 1),
+            ],
+            tags,
+            author: None,
+            origin: (0, -4),
+        };
+
+        let converted = to_attribute_value(&drawing);
+        let attribute_map = converted.clone().m.unwrap();
+        assert_eq!("korat", &attribute_map["name"].clone().s.unwrap());
+        assert_eq!(Some(true), attribute_map["author"].null);
+
+        let shapes = attribute_map["shapes"].clone().l.unwrap();
+        assert_eq!("Point", &shapes[0].clone().s.unwrap());
+        assert_eq!("1.5", &shapes[1].clone().m.unwrap()["Circle"].clone().n.unwrap());
+
+        assert_eq!(drawing, from_attribute_value(converted).unwrap());
+    }
+
+    #[test]
+    fn reads_sets_as_sequences() {
+        let av = AttributeValue {
+            ns: Some(vec!["1".to_string(), "2".to_string()]),
+            ..AttributeValue::default()
+        };
+        let numbers: Vec<u8> = from_attribute_value(av).unwrap();
+        assert_eq!(vec![1, 2], numbers);
+    }
+
+    #[test]
+    fn reads_missing_values_as_none() {
+        let value: Option<u32> = from_attribute_value(AttributeValue::default()).unwrap();
+        assert_eq!(None, value);
+    }
+
+    #[test]
+    fn stores_scalar_map_keys_as_strings() {
+        let mut numbered = HashMap::new();
+        numbered.insert(1u32, "one".to_string());
+        numbered.insert(2, "two".to_string());
+        let converted = to_attribute_value(&numbered);
+        assert_eq!(Some("one".to_string()), converted.clone().m.unwrap()["1"].s);
+        assert_eq!(numbered, from_attribute_value(converted).unwrap());
+
+        let mut flags = BTreeMap::new();
+        flags.insert(true, 'y');
+        flags.insert(false, 'n');
+        assert_eq!(flags, from_attribute_value(to_attribute_value(&flags)).unwrap());
+
+        let mut pairs = HashMap::new();
+        pairs.insert((1, 2), "pair");
+        assert!(try_to_attribute_value(&pairs).is_err());
+    }
+
+    #[test]
+    fn rejects_non_finite_floats() {
+        for float in &[f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            match try_to_attribute_value(vec![1.5, *float]) {
+                Err(ConversionError::InvalidValue) => (),
+                other => panic!("unexpected result {:?} for {}", other, float),
+            }
+        }
+        assert!(try_to_attribute_value(f32::NAN).is_err());
+
+        let floats: Vec<f64> = from_attribute_value(to_attribute_value(vec![1.5])).unwrap();
+        assert_eq!(vec![1.5], floats);
+    }
+
+    #[test]
+    fn reports_values_of_the_wrong_type() {
+        let av = AttributeValue {
+            s: Some("korat".to_string()),
+            ..AttributeValue::default()
+        };
+        match from_attribute_value::<u32>(av) {
+            Err(ConversionError::Custom(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
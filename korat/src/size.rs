@@ -0,0 +1,242 @@
+//! Item size calculation and capacity unit consumption.
+//!
+//! Sizes follow the rules DynamoDB applies when checking items against the
+//! 400KB limit and when metering capacity:
+//!
+//! * attribute names and strings count their UTF-8 bytes and binaries their
+//!   length,
+//! * numbers are stored in base 100 with leading and trailing zeros trimmed,
+//!   taking one byte per pair of significant digits plus one byte, and one
+//!   more byte when negative,
+//! * booleans and nulls take one byte,
+//! * lists and maps take three bytes plus one byte per element on top of
+//!   their elements (and the names of map entries),
+//! * sets take the size of their members.
+
+use std::collections::HashMap;
+
+use super::{AttributeType, AttributeValue};
+
+
+/// The maximum size of an item, including its attribute names.
+pub const MAX_ITEM_SIZE: usize = 400 * 1024;
+
+const READ_UNIT_SIZE: usize = 4 * 1024;
+const WRITE_UNIT_SIZE: usize = 1024;
+const CONTAINER_OVERHEAD: usize = 3;
+const ELEMENT_OVERHEAD: usize = 1;
+
+
+/// The size of an item as counted by DynamoDB.
+pub fn item_size(attribute_map: &HashMap<String, AttributeValue>) -> usize {
+    attribute_map.iter()
+        .filter(|&(_, attribute_value)| !AttributeType::of(attribute_value).is_empty())
+        .map(|(name, attribute_value)| name.len() + attribute_value_size(attribute_value))
+        .sum()
+}
+
+/// Whether an item fits in the maximum item size.
+pub fn fits(attribute_map: &HashMap<String, AttributeValue>) -> bool {
+    item_size(attribute_map) <= MAX_ITEM_SIZE
+}
+
+/// The size of an attribute value, excluding its name. Attribute values with
+/// no member set are not stored and take no space.
+pub fn attribute_value_size(attribute_value: &AttributeValue) -> usize {
+    let mut size = 0;
+    if let Some(ref string) = attribute_value.s {
+        size += string.len();
+    }
+    if let Some(ref number) = attribute_value.n {
+        size += number_size(number);
+    }
+    if let Some(ref binary) = attribute_value.b {
+        size += binary.len();
+    }
+    if attribute_value.bool.is_some() {
+        size += 1;
+    }
+    if attribute_value.null.is_some() {
+        size += 1;
+    }
+    if let Some(ref map) = attribute_value.m {
+        size += CONTAINER_OVERHEAD + map.iter()
+            .map(|(name, value)| ELEMENT_OVERHEAD + name.len() + attribute_value_size(value))
+            .sum::<usize>();
+    }
+    if let Some(ref list) = attribute_value.l {
+        size += CONTAINER_OVERHEAD + list.iter()
+            .map(|value| ELEMENT_OVERHEAD + attribute_value_size(value))
+            .sum::<usize>();
+    }
+    if let Some(ref set) = attribute_value.ss {
+        size += set.iter().map(String::len).sum::<usize>();
+    }
+    if let Some(ref set) = attribute_value.ns {
+        size += set.iter().map(|number| number_size(number)).sum::<usize>();
+    }
+    if let Some(ref set) = attribute_value.bs {
+        size += set.iter().map(|binary| binary.len()).sum::<usize>();
+    }
+    size
+}
+
+/// The size of a number in DynamoDB's base 100 representation. Numbers which
+/// can't be parsed are counted as their string length.
+pub fn number_size(number: &str) -> usize {
+    let number = number.trim();
+    let (negative, unsigned) = match number.as_bytes().first() {
+        Some(b'-') => (true, &number[1..]),
+        Some(b'+') => (false, &number[1..]),
+        _ => (false, number),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(position) => match unsigned[position + 1..].parse::<i64>() {
+            Ok(exponent) => (&unsigned[..position], exponent),
+            Err(_) => return number.len(),
+        },
+        None => (unsigned, 0),
+    };
+    let (integer, fraction) = match mantissa.find('.') {
+        Some(position) => (&mantissa[..position], &mantissa[position + 1..]),
+        None => (mantissa, ""),
+    };
+    if !integer.bytes().chain(fraction.bytes()).all(|byte| byte.is_ascii_digit()) {
+        return number.len();
+    }
+
+    // The position of the decimal point, counted in digits from the first
+    // significant digit. Exponents that far out aren't valid numbers.
+    let digits: String = integer.chars().chain(fraction.chars()).collect();
+    let significant = digits.trim_start_matches('0');
+    let point = match (integer.len() as i64).checked_add(exponent)
+        .and_then(|point| point.checked_sub((digits.len() - significant.len()) as i64)) {
+        Some(point) => point,
+        None => return number.len(),
+    };
+    let significant = significant.trim_end_matches('0');
+    if significant.is_empty() {
+        return 1;
+    }
+
+    // Pairs of digits are aligned on the decimal point, so a digit on its
+    // own at either end takes a whole byte.
+    let leading = point.rem_euclid(2) as usize;
+    let pairs = (leading + significant.len()).div_ceil(2);
+    pairs + 1 + negative as usize
+}
+
+
+/// The consistency of a read, which determines its capacity cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadConsistency {
+    Eventual,
+    Strong,
+    Transactional,
+}
+
+/// The read capacity units consumed by reading the given number of bytes.
+/// For `Query` and `Scan` the sizes of all the items read are summed before
+/// rounding, while `BatchGetItem` rounds each item separately.
+pub fn read_capacity_units(size: usize, consistency: ReadConsistency) -> f64 {
+    let units = units(size, READ_UNIT_SIZE) as f64;
+    match consistency {
+        ReadConsistency::Eventual => units / 2.0,
+        ReadConsistency::Strong => units,
+        ReadConsistency::Transactional => units * 2.0,
+    }
+}
+
+/// The write capacity units consumed by writing an item of the given size.
+pub fn write_capacity_units(size: usize) -> u64 {
+    units(size, WRITE_UNIT_SIZE)
+}
+
+/// The write capacity units consumed by writing an item of the given size in
+/// a transaction.
+pub fn transactional_write_capacity_units(size: usize) -> u64 {
+    write_capacity_units(size) * 2
+}
+
+/// Every request consumes at least one unit.
+fn units(size: usize, unit_size: usize) -> u64 {
+    size.div_ceil(unit_size).max(1) as u64
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use AttributeValue;
+
+    use AttributeValueConverter;
+    use super::{
+        MAX_ITEM_SIZE, ReadConsistency, attribute_value_size, fits, item_size, number_size,
+        read_capacity_units, transactional_write_capacity_units, write_capacity_units
+    };
+
+    #[test]
+    fn sizes_numbers_in_base_100() {
+        let sizes = [
+            ("0", 1), ("0.000", 1), ("1", 2), ("10", 2), ("100", 2), ("123", 3),
+            ("-1", 3), ("0.5", 2), ("0.05", 2), ("1.5", 3), ("12.34", 3),
+            ("1e2", 2), ("1.5E1", 2), ("0012300", 3), ("123456789012345678901234567890", 16),
+            ("1e9223372036854775807", 21), ("0.01e-9223372036854775808", 25),
+        ];
+        for &(number, size) in sizes.iter() {
+            assert_eq!(size, number_size(number), "size of {}", number);
+        }
+    }
+
+    #[test]
+    fn sizes_attribute_values() {
+        assert_eq!(5, attribute_value_size(&"korat".to_string().to_attribute_value()));
+        assert_eq!(8, attribute_value_size(&"κόρα".to_string().to_attribute_value()));
+        assert_eq!(3, attribute_value_size(&vec![1u8, 2, 3].to_attribute_value()));
+        assert_eq!(1, attribute_value_size(&true.to_attribute_value()));
+        assert_eq!(0, attribute_value_size(&AttributeValue::default()));
+
+        let list = vec!["ab".to_string(), "c".to_string()].into_iter()
+            .map(|string| string.to_attribute_value())
+            .collect();
+        let list = AttributeValue { l: Some(list), ..AttributeValue::default() };
+        assert_eq!(3 + 1 + 2 + 1 + 1, attribute_value_size(&list));
+
+        let mut map = HashMap::new();
+        map.insert("key".to_string(), 1.to_attribute_value());
+        let map = AttributeValue { m: Some(map), ..AttributeValue::default() };
+        assert_eq!(3 + 1 + 3 + 2, attribute_value_size(&map));
+
+        let set = AttributeValue {
+            ns: Some(vec!["1".to_string(), "123".to_string()]),
+            ..AttributeValue::default()
+        };
+        assert_eq!(2 + 3, attribute_value_size(&set));
+    }
+
+    #[test]
+    fn sizes_items_with_their_attribute_names() {
+        let mut item = HashMap::new();
+        item.insert("id".to_string(), "abc".to_string().to_attribute_value());
+        item.insert("count".to_string(), 42.to_attribute_value());
+        item.insert("missing".to_string(), AttributeValue::default());
+        item.insert("empty".to_string(), String::new().to_attribute_value());
+        assert_eq!(2 + 3 + 5 + 2 + 5, item_size(&item));
+        assert!(fits(&item));
+
+        item.insert("body".to_string(), "x".repeat(MAX_ITEM_SIZE).to_attribute_value());
+        assert!(!fits(&item));
+    }
+
+    #[test]
+    fn computes_capacity_units() {
+        assert_eq!(0.5, read_capacity_units(0, ReadConsistency::Eventual));
+        assert_eq!(1.0, read_capacity_units(4096, ReadConsistency::Strong));
+        assert_eq!(2.0, read_capacity_units(4097, ReadConsistency::Strong));
+        assert_eq!(4.0, read_capacity_units(4097, ReadConsistency::Transactional));
+        assert_eq!(1, write_capacity_units(1));
+        assert_eq!(2, write_capacity_units(1025));
+        assert_eq!(4, transactional_write_capacity_units(2048));
+    }
+}
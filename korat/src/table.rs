@@ -0,0 +1,160 @@
+//! Table schemas generated from the items stored in them.
+//!
+//! Deriving `DynamoDBItem` on a struct with a `#[korat(table = "...")]`
+//! attribute also implements `DynamoDBTable`, which describes the table: its
+//! key schema, the secondary indexes declared on the fields, the billing
+//! mode and the time to live attribute. The types of the key attributes are
+//! inferred from the types of the fields through `ScalarKey`.
+//!
+//! The definition converts to the table creation request of the backends,
+//! see `backend::rusoto::create_table_input`.
+
+use AttributeType;
+use DynamoDBInsertable;
+
+
+/// A key attribute of a table or an index, with the type it is stored as:
+/// `S`, `N` or `B`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyAttribute {
+    pub name: String,
+    pub attribute_type: AttributeType,
+}
+
+impl KeyAttribute {
+    pub fn new<S: Into<String>>(name: S, attribute_type: AttributeType) -> Self {
+        KeyAttribute { name: name.into(), attribute_type }
+    }
+}
+
+/// The hash key and optional range key of a table or an index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySchema {
+    pub hash: KeyAttribute,
+    pub range: Option<KeyAttribute>,
+}
+
+/// Whether a secondary index is global, with a key of its own, or local,
+/// sharing the hash key of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    Global,
+    Local,
+}
+
+/// A secondary index of a table. Indexes project all the attributes of the
+/// items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecondaryIndex {
+    pub name: String,
+    pub kind: IndexKind,
+    pub key_schema: KeySchema,
+}
+
+/// How reads and writes on a table are billed. Global secondary indexes of
+/// provisioned tables get the throughput of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BillingMode {
+    #[default]
+    PayPerRequest,
+    Provisioned {
+        read_capacity_units: i64,
+        write_capacity_units: i64,
+    },
+}
+
+/// Everything needed to create a table for an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDefinition {
+    pub name: String,
+    pub key_schema: KeySchema,
+    pub indexes: Vec<SecondaryIndex>,
+    pub billing_mode: BillingMode,
+    /// The attribute holding the expiry time of the items, in seconds since
+    /// the epoch. DynamoDB enables time to live separately from the creation
+    /// of the table.
+    pub ttl_attribute: Option<String>,
+}
+
+impl TableDefinition {
+    /// The key attributes of the table and its indexes, each listed once as
+    /// expected by the attribute definitions of a table.
+    pub fn attribute_definitions(&self) -> Vec<KeyAttribute> {
+        let key_schemas = Some(&self.key_schema).into_iter()
+            .chain(self.indexes.iter().map(|index| &index.key_schema));
+        let mut attributes: Vec<KeyAttribute> = Vec::new();
+        for key_schema in key_schemas {
+            for attribute in Some(&key_schema.hash).into_iter().chain(&key_schema.range) {
+                if attributes.iter().all(|known| known.name != attribute.name) {
+                    attributes.push(attribute.clone());
+                }
+            }
+        }
+        attributes
+    }
+
+    pub fn global_indexes(&self) -> impl Iterator<Item = &SecondaryIndex> {
+        self.indexes.iter().filter(|index| index.kind == IndexKind::Global)
+    }
+
+    pub fn local_indexes(&self) -> impl Iterator<Item = &SecondaryIndex> {
+        self.indexes.iter().filter(|index| index.kind == IndexKind::Local)
+    }
+}
+
+/// Implemented by the derive for items declaring their table with
+/// `#[korat(table = "...")]`.
+pub trait DynamoDBTable: DynamoDBInsertable {
+    fn table_definition() -> TableDefinition;
+
+    fn table_name() -> String {
+        Self::table_definition().name
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use AttributeType;
+    use super::{
+        BillingMode, IndexKind, KeyAttribute, KeySchema, SecondaryIndex, TableDefinition
+    };
+
+    #[test]
+    fn lists_each_key_attribute_once() {
+        let definition = TableDefinition {
+            name: "orders".to_string(),
+            key_schema: KeySchema {
+                hash: KeyAttribute::new("customer", AttributeType::S),
+                range: Some(KeyAttribute::new("number", AttributeType::N)),
+            },
+            indexes: vec![
+                SecondaryIndex {
+                    name: "ByStatus".to_string(),
+                    kind: IndexKind::Global,
+                    key_schema: KeySchema {
+                        hash: KeyAttribute::new("status", AttributeType::S),
+                        range: Some(KeyAttribute::new("number", AttributeType::N)),
+                    },
+                },
+                SecondaryIndex {
+                    name: "ByTotal".to_string(),
+                    kind: IndexKind::Local,
+                    key_schema: KeySchema {
+                        hash: KeyAttribute::new("customer", AttributeType::S),
+                        range: Some(KeyAttribute::new("total", AttributeType::N)),
+                    },
+                },
+            ],
+            billing_mode: BillingMode::default(),
+            ttl_attribute: None,
+        };
+
+        let names: Vec<_> = definition.attribute_definitions().into_iter()
+            .map(|attribute| attribute.name)
+            .collect();
+        assert_eq!(vec!["customer", "number", "status", "total"], names);
+        assert_eq!(1, definition.global_indexes().count());
+        assert_eq!("ByTotal", definition.local_indexes().next().unwrap().name);
+    }
+}
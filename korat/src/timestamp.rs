@@ -0,0 +1,583 @@
+//! Converters for points in time.
+//!
+//! Timestamps are stored as RFC 3339 strings by default. Fields can select a
+//! different encoding with `#[korat(timestamp = "...")]`:
+//!
+//! * `rfc3339`: an `S` attribute in UTC with a fixed-width fraction, such as
+//!   `2018-01-26T18:30:09.453000000Z`, so that strings sort like the times.
+//!   Years outside of 0000 to 9999 are written with a sign and at least six
+//!   digits, such as `-000001-01-01T00:00:00.000000000Z`, as in ISO 8601
+//! * `epoch_seconds`: an `N` attribute with whole seconds since the epoch, as
+//!   required for TTL attributes
+//! * `epoch_millis`: an `N` attribute with milliseconds since the epoch;
+//!   times whose milliseconds don't fit in an `i64`, some 292 million years
+//!   away from the epoch, are clamped to the nearest one that does
+//!
+//! `SystemTime` is always supported, `chrono` and `time` types are available
+//! with the features of the same name.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use errors::ConversionError;
+use super::{AttributeValue, AttributeValueConverter, ConversionResult, optional};
+
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+
+/// A point in time which can be represented both as an offset from the unix
+/// epoch and as an RFC 3339 string.
+pub trait Timestamp: Sized {
+    /// Returns the seconds since the epoch and the nanoseconds within that
+    /// second.
+    fn to_unix(&self) -> (i64, u32);
+    fn from_unix(seconds: i64, nanos: u32) -> ConversionResult<Self>;
+    fn to_rfc3339(&self) -> String;
+    fn from_rfc3339(string: &str) -> ConversionResult<Self>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampEncoding {
+    Rfc3339,
+    EpochSeconds,
+    EpochMillis,
+}
+
+/// A field which can be stored with any of the timestamp encodings; either a
+/// `Timestamp` or an optional one.
+pub trait TimestampField: Sized {
+    fn to_attribute_value_with(self, encoding: TimestampEncoding) -> AttributeValue;
+    fn from_attribute_value_with(
+        attribute_value: AttributeValue, encoding: TimestampEncoding
+    ) -> ConversionResult<Self>;
+}
+
+impl<T: Timestamp> TimestampField for T {
+    fn to_attribute_value_with(self, encoding: TimestampEncoding) -> AttributeValue {
+        let (seconds, nanos) = self.to_unix();
+        match encoding {
+            TimestampEncoding::Rfc3339 => self.to_rfc3339().to_attribute_value(),
+            TimestampEncoding::EpochSeconds => seconds.to_attribute_value(),
+            TimestampEncoding::EpochMillis => seconds.saturating_mul(1000)
+                .saturating_add(i64::from(nanos / 1_000_000))
+                .to_attribute_value(),
+        }
+    }
+
+    fn from_attribute_value_with(
+        attribute_value: AttributeValue, encoding: TimestampEncoding
+    ) -> ConversionResult<Self> {
+        match encoding {
+            TimestampEncoding::Rfc3339 => T::from_rfc3339(
+                &String::from_attribute_value(attribute_value)?
+            ),
+            TimestampEncoding::EpochSeconds => {
+                let seconds = i64::from_attribute_value(attribute_value)?;
+                T::from_unix(seconds, 0)
+            },
+            TimestampEncoding::EpochMillis => {
+                let millis = i64::from_attribute_value(attribute_value)?;
+                T::from_unix(
+                    millis.div_euclid(1000),
+                    (millis.rem_euclid(1000) * 1_000_000) as u32
+                )
+            },
+        }
+    }
+}
+
+impl<T: Timestamp> TimestampField for Option<T> {
+    fn to_attribute_value_with(self, encoding: TimestampEncoding) -> AttributeValue {
+        match self {
+            Some(value) => value.to_attribute_value_with(encoding),
+            None => AttributeValue::default(),
+        }
+    }
+
+    fn from_attribute_value_with(
+        attribute_value: AttributeValue, encoding: TimestampEncoding
+    ) -> ConversionResult<Self> {
+        optional(T::from_attribute_value_with(attribute_value, encoding))
+    }
+}
+
+macro_rules! encoding_module {
+    ($name:ident, $encoding:ident) => {
+        pub mod $name {
+            use AttributeValue;
+
+            use super::{ConversionResult, TimestampEncoding, TimestampField};
+
+            pub fn to_attribute_value<T: TimestampField>(value: T) -> AttributeValue {
+                value.to_attribute_value_with(TimestampEncoding::$encoding)
+            }
+
+            pub fn from_attribute_value<T: TimestampField>(
+                attribute_value: AttributeValue
+            ) -> ConversionResult<T> {
+                T::from_attribute_value_with(attribute_value, TimestampEncoding::$encoding)
+            }
+        }
+    }
+}
+
+encoding_module!(rfc3339, Rfc3339);
+encoding_module!(epoch_seconds, EpochSeconds);
+encoding_module!(epoch_millis, EpochMillis);
+
+macro_rules! timestamp_converter {
+    ($type:ty) => {
+        impl AttributeValueConverter for $type {
+            fn from_attribute_value(
+                attribute_value: AttributeValue
+            ) -> ConversionResult<Self> {
+                rfc3339::from_attribute_value(attribute_value)
+            }
+
+            fn to_attribute_value(self) -> AttributeValue {
+                rfc3339::to_attribute_value(self)
+            }
+        }
+
+        impl ::ScalarKey for $type {
+            const ATTRIBUTE_TYPE: ::AttributeType = ::AttributeType::S;
+        }
+    }
+}
+
+impl Timestamp for SystemTime {
+    fn to_unix(&self) -> (i64, u32) {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+            Err(error) => {
+                let before = error.duration();
+                let seconds = -(before.as_secs() as i64);
+                match before.subsec_nanos() {
+                    0 => (seconds, 0),
+                    nanos => (seconds - 1, 1_000_000_000 - nanos),
+                }
+            },
+        }
+    }
+
+    fn from_unix(seconds: i64, nanos: u32) -> ConversionResult<Self> {
+        let time = if seconds >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(seconds as u64, nanos))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+                .and_then(|time| time.checked_add(Duration::new(0, nanos)))
+        };
+        time.ok_or(ConversionError::InvalidValue)
+    }
+
+    fn to_rfc3339(&self) -> String {
+        let (seconds, nanos) = self.to_unix();
+        format_rfc3339(seconds, nanos)
+    }
+
+    fn from_rfc3339(string: &str) -> ConversionResult<Self> {
+        let (seconds, nanos) = parse_rfc3339(string)?;
+        SystemTime::from_unix(seconds, nanos)
+    }
+}
+
+timestamp_converter!(SystemTime);
+
+/// Formats a point in time as an RFC 3339 string in UTC, always with nine
+/// fractional digits. Years RFC 3339 can't represent get the expanded form
+/// of ISO 8601.
+fn format_rfc3339(seconds: i64, nanos: u32) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    let second_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let year = if (0..=9999).contains(&year) {
+        format!("{:04}", year)
+    } else {
+        format!("{:+07}", year)
+    };
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year, month, day,
+        second_of_day / 3600, second_of_day % 3600 / 60, second_of_day % 60, nanos
+    )
+}
+
+/// Parses an RFC 3339 date-time into seconds since the epoch and
+/// nanoseconds, applying its offset. Expanded years with a sign and six to
+/// twelve digits, as written by `format_rfc3339`, are accepted as well.
+fn parse_rfc3339(string: &str) -> ConversionResult<(i64, u32)> {
+    let invalid = || ConversionError::InvalidValue;
+    let bytes = string.as_bytes();
+    let number = |from: usize, to: usize| -> ConversionResult<i64> {
+        string.get(from..to)
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or(ConversionError::InvalidValue)
+    };
+    let separator = |index: usize, expected: &[u8]| -> ConversionResult<()> {
+        bytes.get(index)
+            .filter(|byte| expected.contains(byte))
+            .map(|_| ())
+            .ok_or(ConversionError::InvalidValue)
+    };
+
+    let (year, start) = match bytes.first() {
+        Some(sign @ b'+') | Some(sign @ b'-') => {
+            let digits = bytes[1..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+            if !(6..=12).contains(&digits) {
+                return Err(invalid());
+            }
+            let year = number(1, 1 + digits)?;
+            (if *sign == b'-' { -year } else { year }, 1 + digits)
+        },
+        _ => (number(0, 4)?, 4),
+    };
+    separator(start, b"-")?;
+    let month = number(start + 1, start + 3)?;
+    separator(start + 3, b"-")?;
+    let day = number(start + 4, start + 6)?;
+    separator(start + 6, b"Tt ")?;
+    let hour = number(start + 7, start + 9)?;
+    separator(start + 9, b":")?;
+    let minute = number(start + 10, start + 12)?;
+    separator(start + 12, b":")?;
+    let second = number(start + 13, start + 15)?;
+
+    let mut index = start + 15;
+    let mut nanos = 0;
+    if bytes.get(index) == Some(&b'.') {
+        let digits = bytes[index + 1..].iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return Err(invalid());
+        }
+        let fraction = &string[index + 1..index + 1 + digits.min(9)];
+        nanos = fraction.parse::<u32>().map_err(|_| invalid())?
+            * 10u32.pow(9 - fraction.len() as u32);
+        index += 1 + digits;
+    }
+
+    let offset = match bytes.get(index) {
+        Some(b'Z') | Some(b'z') if bytes.len() == index + 1 => 0,
+        Some(sign @ b'+') | Some(sign @ b'-') if bytes.len() == index + 6 => {
+            separator(index + 3, b":")?;
+            let offset = number(index + 1, index + 3)? * 3600
+                + number(index + 4, index + 6)? * 60;
+            if *sign == b'-' { -offset } else { offset }
+        },
+        _ => return Err(invalid()),
+    };
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month)
+        || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+
+    let seconds = days_from_civil(year, month, day).checked_mul(SECONDS_PER_DAY)
+        .and_then(|seconds| seconds.checked_add(hour * 3600 + minute * 60 + second - offset))
+        .ok_or_else(invalid)?;
+    Ok((seconds, nanos))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Counts the days from the epoch to a date in the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100
+        + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_converters {
+    use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+
+    use errors::ConversionError;
+    use super::{
+        AttributeValue, AttributeValueConverter, ConversionResult, SECONDS_PER_DAY,
+        Timestamp, format_rfc3339, parse_rfc3339, rfc3339
+    };
+
+    /// The days from 0001-01-01 to the unix epoch.
+    const EPOCH_DAYS_FROM_CE: i64 = 719_163;
+
+    impl Timestamp for DateTime<Utc> {
+        fn to_unix(&self) -> (i64, u32) {
+            (self.timestamp(), self.timestamp_subsec_nanos())
+        }
+
+        fn from_unix(seconds: i64, nanos: u32) -> ConversionResult<Self> {
+            Utc.timestamp_opt(seconds, nanos).single()
+                .ok_or(ConversionError::InvalidValue)
+        }
+
+        /// Formatted like the other types, which chrono only does for the
+        /// years RFC 3339 can represent.
+        fn to_rfc3339(&self) -> String {
+            let (seconds, nanos) = self.to_unix();
+            format_rfc3339(seconds, nanos)
+        }
+
+        fn from_rfc3339(string: &str) -> ConversionResult<Self> {
+            let (seconds, nanos) = parse_rfc3339(string)?;
+            Self::from_unix(seconds, nanos)
+        }
+    }
+
+    impl Timestamp for NaiveDate {
+        fn to_unix(&self) -> (i64, u32) {
+            ((i64::from(self.num_days_from_ce()) - EPOCH_DAYS_FROM_CE) * SECONDS_PER_DAY, 0)
+        }
+
+        fn from_unix(seconds: i64, _nanos: u32) -> ConversionResult<Self> {
+            let days = seconds.div_euclid(SECONDS_PER_DAY) + EPOCH_DAYS_FROM_CE;
+            if days < i64::from(i32::MIN) || days > i64::from(i32::MAX) {
+                return Err(ConversionError::InvalidValue);
+            }
+            NaiveDate::from_num_days_from_ce_opt(days as i32)
+                .ok_or(ConversionError::InvalidValue)
+        }
+
+        /// Dates are stored in the RFC 3339 `full-date` form, `YYYY-MM-DD`.
+        fn to_rfc3339(&self) -> String {
+            self.to_string()
+        }
+
+        fn from_rfc3339(string: &str) -> ConversionResult<Self> {
+            string.parse().map_err(|_| ConversionError::InvalidValue)
+        }
+    }
+
+    timestamp_converter!(DateTime<Utc>);
+    timestamp_converter!(NaiveDate);
+}
+
+#[cfg(feature = "time")]
+mod time_converters {
+    use time::OffsetDateTime;
+
+    use errors::ConversionError;
+    use super::{
+        AttributeValue, AttributeValueConverter, ConversionResult, Timestamp,
+        format_rfc3339, parse_rfc3339, rfc3339
+    };
+
+    const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+    impl Timestamp for OffsetDateTime {
+        fn to_unix(&self) -> (i64, u32) {
+            (self.unix_timestamp(), self.nanosecond())
+        }
+
+        fn from_unix(seconds: i64, nanos: u32) -> ConversionResult<Self> {
+            OffsetDateTime::from_unix_timestamp_nanos(
+                i128::from(seconds) * i128::from(NANOS_PER_SECOND) + i128::from(nanos)
+            ).map_err(|_| ConversionError::InvalidValue)
+        }
+
+        /// Formatted in UTC like the other types, which also covers the
+        /// years and offsets RFC 3339 can't represent.
+        fn to_rfc3339(&self) -> String {
+            let (seconds, nanos) = self.to_unix();
+            format_rfc3339(seconds, nanos)
+        }
+
+        fn from_rfc3339(string: &str) -> ConversionResult<Self> {
+            let (seconds, nanos) = parse_rfc3339(string)?;
+            Self::from_unix(seconds, nanos)
+        }
+    }
+
+    timestamp_converter!(OffsetDateTime);
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use AttributeValue;
+
+    use super::{
+        Timestamp, epoch_millis, epoch_seconds, format_rfc3339, parse_rfc3339,
+        rfc3339
+    };
+
+    #[test]
+    fn formats_rfc3339() {
+        assert_eq!("1970-01-01T00:00:00.000000000Z", format_rfc3339(0, 0));
+        assert_eq!(
+            "2018-01-26T18:30:09.453000000Z", format_rfc3339(1_516_991_409, 453_000_000)
+        );
+        assert_eq!("1969-12-31T23:59:59.500000000Z", format_rfc3339(-1, 500_000_000));
+        assert_eq!("2000-02-29T00:00:00.000000000Z", format_rfc3339(951_782_400, 0));
+        assert_eq!("-000001-12-31T00:00:00.000000000Z", format_rfc3339(-62_167_305_600, 0));
+        assert_eq!("+010000-01-01T00:00:00.000000000Z", format_rfc3339(253_402_300_800, 0));
+    }
+
+    #[test]
+    fn parses_rfc3339() {
+        assert_eq!((1_516_991_409, 453_000_000), parse_rfc3339("2018-01-26T18:30:09.453Z").unwrap());
+        assert_eq!((1_516_991_409, 0), parse_rfc3339("2018-01-26T20:30:09+02:00").unwrap());
+        assert_eq!((-1, 500_000_000), parse_rfc3339("1969-12-31t23:59:59.5z").unwrap());
+        assert_eq!((-62_167_305_600, 0), parse_rfc3339("-000001-12-31T00:00:00Z").unwrap());
+        assert_eq!((253_402_300_800, 0), parse_rfc3339("+010000-01-01T00:00:00Z").unwrap());
+        for invalid in &["2018-01-26", "2018-02-30T00:00:00Z", "2018-01-26T18:30:09",
+                         "2018-01-26T18:30:09.Z", "2018-01-26T18:30:09+0200",
+                         "-0001-12-31T00:00:00Z", "+1000000000000-01-01T00:00:00Z"] {
+            assert!(parse_rfc3339(invalid).is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn converts_system_times_with_each_encoding() {
+        let time = UNIX_EPOCH + Duration::new(1_516_991_409, 453_000_000);
+
+        let converted = rfc3339::to_attribute_value(time);
+        assert_eq!("2018-01-26T18:30:09.453000000Z", converted.s.clone().unwrap());
+        assert_eq!(time, rfc3339::from_attribute_value::<SystemTime>(converted).unwrap());
+
+        let converted = epoch_seconds::to_attribute_value(time);
+        assert_eq!("1516991409", converted.n.clone().unwrap());
+        assert_eq!(
+            UNIX_EPOCH + Duration::from_secs(1_516_991_409),
+            epoch_seconds::from_attribute_value::<SystemTime>(converted).unwrap()
+        );
+
+        let converted = epoch_millis::to_attribute_value(time);
+        assert_eq!("1516991409453", converted.n.clone().unwrap());
+        assert_eq!(time, epoch_millis::from_attribute_value::<SystemTime>(converted).unwrap());
+    }
+
+    #[test]
+    fn sorts_rfc3339_strings_like_times() {
+        let times = [(0, 0), (0, 500_000_000), (0, 999_000_000), (1, 0), (1, 1)];
+        let formatted: Vec<String> = times.iter()
+            .map(|&(seconds, nanos)| format_rfc3339(seconds, nanos))
+            .collect();
+        let mut sorted = formatted.clone();
+        sorted.sort();
+        assert_eq!(formatted, sorted);
+    }
+
+    #[test]
+    fn clamps_times_beyond_epoch_millis() {
+        let time = UNIX_EPOCH + Duration::from_secs(i64::MAX as u64);
+        let converted = epoch_millis::to_attribute_value(time);
+        assert_eq!(i64::MAX.to_string(), converted.n.clone().unwrap());
+        assert_eq!(
+            UNIX_EPOCH + Duration::from_millis(i64::MAX as u64),
+            epoch_millis::from_attribute_value::<SystemTime>(converted).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trips_years_beyond_rfc3339() {
+        for &seconds in &[-62_167_305_600, 253_402_300_800, -1_000_000_000_000] {
+            let (parsed, nanos) = parse_rfc3339(&format_rfc3339(seconds, 5)).unwrap();
+            assert_eq!((seconds, 5), (parsed, nanos));
+        }
+    }
+
+    #[test]
+    fn converts_times_before_the_epoch() {
+        let time = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!((-2, 500_000_000), time.to_unix());
+
+        let converted = epoch_millis::to_attribute_value(time);
+        assert_eq!("-1500", converted.n.clone().unwrap());
+        assert_eq!(time, epoch_millis::from_attribute_value::<SystemTime>(converted).unwrap());
+    }
+
+    #[test]
+    fn converts_optional_timestamps() {
+        let converted: Option<SystemTime> = epoch_seconds
+            ::from_attribute_value(AttributeValue::default()).unwrap();
+        assert_eq!(None, converted);
+        assert_eq!(None, epoch_seconds::to_attribute_value(converted).n);
+    }
+
+    #[test]
+    fn rejects_other_types() {
+        let av = AttributeValue {
+            s: Some("1516991409".to_string()),
+            ..AttributeValue::default()
+        };
+        let converted: Result<SystemTime, _> = epoch_seconds
+            ::from_attribute_value(av);
+        assert!(converted.is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn converts_chrono_types() {
+        use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+        let date_time = Utc.timestamp_opt(1_516_991_409, 453_000_000).unwrap();
+        let converted = rfc3339::to_attribute_value(date_time);
+        assert_eq!("2018-01-26T18:30:09.453000000Z", converted.s.clone().unwrap());
+        assert_eq!(date_time, rfc3339::from_attribute_value::<DateTime<Utc>>(converted).unwrap());
+
+        let date_time = Utc.with_ymd_and_hms(-1, 1, 26, 18, 30, 9).unwrap();
+        let converted = rfc3339::to_attribute_value(date_time);
+        assert_eq!("-000001-01-26T18:30:09.000000000Z", converted.s.clone().unwrap());
+        assert_eq!(date_time, rfc3339::from_attribute_value::<DateTime<Utc>>(converted).unwrap());
+
+        let date = NaiveDate::from_ymd_opt(1969, 7, 20).unwrap();
+        let converted = ::AttributeValueConverter::to_attribute_value(date);
+        assert_eq!("1969-07-20", converted.s.clone().unwrap());
+        let converted = epoch_seconds::to_attribute_value(date);
+        assert_eq!(date, epoch_seconds::from_attribute_value::<NaiveDate>(converted).unwrap());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn converts_time_types() {
+        use time::{OffsetDateTime, UtcOffset};
+
+        let date_time = OffsetDateTime::from_unix_timestamp_nanos(1_516_991_409_453_000_000)
+            .unwrap();
+        let converted = rfc3339::to_attribute_value(date_time);
+        assert_eq!("2018-01-26T18:30:09.453000000Z", converted.s.clone().unwrap());
+        assert_eq!(date_time, rfc3339::from_attribute_value::<OffsetDateTime>(converted).unwrap());
+
+        let converted = epoch_millis::to_attribute_value(date_time);
+        assert_eq!(date_time, epoch_millis::from_attribute_value::<OffsetDateTime>(converted).unwrap());
+
+        let offset = UtcOffset::from_hms(1, 0, 30).unwrap();
+        let converted = rfc3339::to_attribute_value(date_time.to_offset(offset));
+        assert_eq!("2018-01-26T18:30:09.453000000Z", converted.s.clone().unwrap());
+        let date_time = date_time.replace_year(-1).unwrap();
+        let converted = rfc3339::to_attribute_value(date_time);
+        assert_eq!("-000001-01-26T18:30:09.453000000Z", converted.s.clone().unwrap());
+        assert_eq!(date_time, rfc3339::from_attribute_value::<OffsetDateTime>(converted).unwrap());
+    }
+}
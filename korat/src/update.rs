@@ -0,0 +1,311 @@
+//! Update expressions built from the typed attribute handles of `expr`.
+//!
+//! The values of every action are converted with the converter of the field
+//! they apply to, so they must have the type of the field. The actions on
+//! numbers, sets and lists also require fields of such types, through the
+//! `NumberAttribute`, `SetAttribute`, `AddAttribute` and `ListAttribute`
+//! marker traits:
+//!
+//! ```rust,ignore
+//! let fields = Order::fields();
+//! let update = Update::new()
+//!     .set(fields.status, Status::Shipped)
+//!     .increment(fields.revision, 1)
+//!     .add(fields.tags, tags)
+//!     .remove(fields.coupon)
+//!     .condition(fields.status.eq(Status::Paid))
+//!     .build()
+//!     .expect("the update sets the status");
+//! let request = backend::rusoto::update_item_input("orders", order.get_key(), update);
+//! ```
+//!
+//! DynamoDB rejects update expressions without actions, so `build` returns
+//! `None` for updates which end up with none, for example when they only set
+//! missing attributes to `None`.
+//!
+//! The update expression and its condition share their placeholders, which
+//! are generated like the ones of conditions.
+
+use std::collections::HashMap;
+
+use {AttributeValue, AttributeValueConverter, ListElement};
+use backend::Backend;
+use expr::{Condition, ExpressionAttributes, Field, optional_values};
+
+
+/// Marks the types which are stored as an `N` attribute, the only ones
+/// DynamoDB can increment and decrement.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't stored as a number",
+    label = "only number attributes can be incremented and decremented"
+)]
+pub trait NumberAttribute: AttributeValueConverter {}
+
+/// Marks the types which are stored as an `SS`, `NS` or `BS` attribute, the
+/// only ones DynamoDB can delete members from.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't stored as a set",
+    label = "only the members of set attributes can be deleted"
+)]
+pub trait SetAttribute: AttributeValueConverter {}
+
+/// Marks the types `ADD` applies to: numbers and sets.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't stored as a number or a set",
+    label = "only number and set attributes can be added to"
+)]
+pub trait AddAttribute: AttributeValueConverter {}
+
+/// Marks the types which are stored as an `L` attribute, the only ones
+/// DynamoDB can append to.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't stored as a list",
+    label = "only list attributes can be appended to"
+)]
+pub trait ListAttribute: AttributeValueConverter {}
+
+impl<T: ListElement> ListAttribute for Vec<T> {}
+
+
+/// The actions of an `UpdateExpression`, along with an optional condition.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Update {
+    set: Vec<(&'static str, Assignment, AttributeValue)>,
+    remove: Vec<&'static str>,
+    add: Vec<(&'static str, AttributeValue)>,
+    delete: Vec<(&'static str, AttributeValue)>,
+    condition: Option<Condition>,
+}
+
+/// How a `SET` action computes the new value of its attribute from the value
+/// given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assignment {
+    Value,
+    IfNotExists,
+    ListAppend,
+    Plus,
+    Minus,
+}
+
+impl Update {
+    pub fn new() -> Self {
+        Update::default()
+    }
+
+    /// Sets the attribute, or removes it when the value is stored as no
+    /// attribute at all, like the `None` of optional fields.
+    pub fn set<T>(self, field: Field<T>, value: T) -> Self {
+        self.assign(field, Assignment::Value, value)
+    }
+
+    /// Sets the attribute only when the item doesn't have it yet. Values
+    /// stored as no attribute at all leave the item as it is.
+    pub fn set_if_not_exists<T>(self, field: Field<T>, value: T) -> Self {
+        self.assign(field, Assignment::IfNotExists, value)
+    }
+
+    /// Appends `values` to the list, which must exist on the item.
+    pub fn list_append<T: ListAttribute>(self, field: Field<T>, values: T) -> Self {
+        self.assign(field, Assignment::ListAppend, values)
+    }
+
+    /// Adds `amount` to the number, which must exist on the item unlike with
+    /// `add`.
+    pub fn increment<T: NumberAttribute>(self, field: Field<T>, amount: T) -> Self {
+        self.assign(field, Assignment::Plus, amount)
+    }
+
+    /// Subtracts `amount` from the number, which must exist on the item.
+    pub fn decrement<T: NumberAttribute>(self, field: Field<T>, amount: T) -> Self {
+        self.assign(field, Assignment::Minus, amount)
+    }
+
+    /// Removes the attribute, which only optional fields can do without
+    /// making the item unreadable.
+    pub fn remove<T>(mut self, field: Field<Option<T>>) -> Self {
+        self.remove.push(field.name());
+        self
+    }
+
+    /// Adds `value` to a number, starting from 0 when the item doesn't have
+    /// it, or adds the members of `value` to a set.
+    pub fn add<T: AddAttribute>(mut self, field: Field<T>, value: T) -> Self {
+        self.add.push((field.name(), field.to_attribute_value(value)));
+        self
+    }
+
+    /// Deletes the members of `members` from a set.
+    pub fn delete<T: SetAttribute>(mut self, field: Field<T>, members: T) -> Self {
+        self.delete.push((field.name(), field.to_attribute_value(members)));
+        self
+    }
+
+    /// Applies the update only when the item matches `condition`. Conditions
+    /// given several times must all match.
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(match self.condition {
+            Some(previous) => previous.and(condition),
+            None => condition,
+        });
+        self
+    }
+
+    /// Writes the update expression, or `None` when the update has no
+    /// actions, which DynamoDB doesn't allow.
+    pub fn build(self) -> Option<UpdateExpression> {
+        let mut attributes = ExpressionAttributes::default();
+        let mut clauses = Vec::new();
+        if !self.set.is_empty() {
+            let actions: Vec<String> = self.set.into_iter()
+                .map(|(name, assignment, value)| {
+                    let name = attributes.name(name);
+                    let value = attributes.value(value);
+                    match assignment {
+                        Assignment::Value => format!("{} = {}", name, value),
+                        Assignment::IfNotExists => {
+                            format!("{0} = if_not_exists({0}, {1})", name, value)
+                        },
+                        Assignment::ListAppend => {
+                            format!("{0} = list_append({0}, {1})", name, value)
+                        },
+                        Assignment::Plus => format!("{0} = {0} + {1}", name, value),
+                        Assignment::Minus => format!("{0} = {0} - {1}", name, value),
+                    }
+                })
+                .collect();
+            clauses.push(format!("SET {}", actions.join(", ")));
+        }
+        if !self.remove.is_empty() {
+            let names: Vec<String> = self.remove.into_iter()
+                .map(|name| attributes.name(name))
+                .collect();
+            clauses.push(format!("REMOVE {}", names.join(", ")));
+        }
+        for (clause, actions) in [("ADD", self.add), ("DELETE", self.delete)] {
+            if !actions.is_empty() {
+                let actions: Vec<String> = actions.into_iter()
+                    .map(|(name, value)| {
+                        format!("{} {}", attributes.name(name), attributes.value(value))
+                    })
+                    .collect();
+                clauses.push(format!("{} {}", clause, actions.join(", ")));
+            }
+        }
+        if clauses.is_empty() {
+            return None;
+        }
+
+        let condition = self.condition.map(|condition| condition.write(&mut attributes));
+        Some(UpdateExpression {
+            expression: clauses.join(" "),
+            condition,
+            names: attributes.names,
+            values: attributes.values,
+        })
+    }
+
+    /// DynamoDB rejects empty attribute values, so assigning one removes the
+    /// attribute instead, or does nothing when only setting missing ones.
+    fn assign<T>(mut self, field: Field<T>, assignment: Assignment, value: T) -> Self {
+        let value = field.to_attribute_value(value);
+        if value != AttributeValue::default() {
+            self.set.push((field.name(), assignment, value));
+        } else if assignment == Assignment::Value {
+            self.remove.push(field.name());
+        }
+        self
+    }
+}
+
+/// An `UpdateExpression` and its `ConditionExpression`, along with the
+/// attribute names and values they refer to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateExpression {
+    pub expression: String,
+    pub condition: Option<String>,
+    pub names: HashMap<String, String>,
+    /// Empty for updates which only remove attributes, in which case requests
+    /// should leave out their `ExpressionAttributeValues`, as
+    /// `expression_attribute_values` does.
+    pub values: HashMap<String, AttributeValue>,
+}
+
+impl UpdateExpression {
+    /// The `ExpressionAttributeValues` of a request, converted for a backend,
+    /// or `None` when the update has no values.
+    pub fn expression_attribute_values<B: Backend>(
+        &self
+    ) -> Option<HashMap<String, B::AttributeValue>> {
+        optional_values::<B>(&self.values)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use AttributeValueConverter;
+    use expr::{Field, attribute};
+    use super::Update;
+
+    fn tags() -> Field<BTreeSet<String>> {
+        attribute("tags")
+    }
+
+    #[test]
+    fn groups_actions_by_clause() {
+        let views: Field<u32> = attribute("views");
+        let likes: Field<u32> = attribute("likes");
+        let note: Field<Option<String>> = attribute("note");
+        let update = Update::new()
+            .add(tags(), vec!["new".to_string()].into_iter().collect())
+            .set(views, 1)
+            .remove(note)
+            .increment(likes, 2)
+            .build()
+            .unwrap();
+        assert_eq!(
+            "SET #n0 = :v0, #n1 = #n1 + :v1 REMOVE #n2 ADD #n3 :v2", update.expression
+        );
+        assert_eq!(None, update.condition);
+        assert_eq!("tags", update.names["#n3"]);
+        assert_eq!(2.to_attribute_value(), update.values[":v1"]);
+    }
+
+    #[test]
+    fn shares_placeholders_with_the_condition() {
+        let update = Update::new()
+            .delete(tags(), vec!["old".to_string()].into_iter().collect())
+            .condition(tags().exists())
+            .condition(tags().size().gt(1))
+            .build()
+            .unwrap();
+        assert_eq!("DELETE #n0 :v0", update.expression);
+        assert_eq!(Some("attribute_exists(#n0) AND size(#n0) > :v1".to_string()), update.condition);
+        assert_eq!(1, update.names.len());
+    }
+
+    #[test]
+    fn removes_attributes_set_to_none() {
+        let note: Field<Option<String>> = attribute("note");
+        let update = Update::new()
+            .set(note, None)
+            .set_if_not_exists(attribute::<Option<u32>>("views"), None)
+            .set(attribute("title"), "korat".to_string())
+            .build()
+            .unwrap();
+        assert_eq!("SET #n0 = :v0 REMOVE #n1", update.expression);
+        assert_eq!("note", update.names["#n1"]);
+        assert_eq!(1, update.values.len());
+    }
+
+    #[test]
+    fn builds_nothing_without_actions() {
+        assert_eq!(None, Update::new().condition(tags().exists()).build());
+
+        let views: Field<Option<u32>> = attribute("views");
+        assert_eq!(None, Update::new().set_if_not_exists(views, None).build());
+    }
+}
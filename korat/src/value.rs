@@ -0,0 +1,607 @@
+//! A dynamic representation of DynamoDB attribute values.
+//!
+//! `Value` mirrors every kind of attribute DynamoDB can store and can be used
+//! as a field type for the schemaless parts of an item.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use errors::ConversionError;
+use super::{AttributeType, AttributeValue, AttributeValueConverter, ConversionResult};
+
+
+/// A DynamoDB number. Numbers are kept in their string representation so
+/// that no precision is lost for values which do not fit the native types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Number(String);
+
+impl Number {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.0.parse().ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.0.parse().ok()
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.0.parse().ok()
+    }
+}
+
+impl FromStr for Number {
+    type Err = ConversionError;
+
+    fn from_str(number: &str) -> Result<Self, Self::Err> {
+        if is_valid_number(number) {
+            Ok(Number(number.to_string()))
+        } else {
+            Err(ConversionError::InvalidValue)
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Checks a number against the decimal notation accepted by DynamoDB:
+/// an optional sign, digits with an optional fraction and an optional
+/// exponent.
+pub(crate) fn is_valid_number(number: &str) -> bool {
+    let unsigned = if number.starts_with('-') || number.starts_with('+') {
+        &number[1..]
+    } else {
+        number
+    };
+    let mut chars = unsigned.chars().peekable();
+
+    let mut digits = 0;
+    while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+        chars.next();
+        digits += 1;
+    }
+    if chars.peek() == Some(&'.') {
+        chars.next();
+        while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+            chars.next();
+            digits += 1;
+        }
+    }
+    if digits == 0 {
+        return false;
+    }
+
+    if chars.peek().is_some_and(|c| *c == 'e' || *c == 'E') {
+        chars.next();
+        if chars.peek().is_some_and(|c| *c == '-' || *c == '+') {
+            chars.next();
+        }
+        let mut exponent_digits = 0;
+        while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+            chars.next();
+            exponent_digits += 1;
+        }
+        if exponent_digits == 0 {
+            return false;
+        }
+    }
+
+    chars.next().is_none()
+}
+
+macro_rules! number_from {
+    ($($type:ty),*) => {
+        $(impl From<$type> for Number {
+            fn from(number: $type) -> Self {
+                Number(number.to_string())
+            }
+        }
+
+        impl From<$type> for Value {
+            fn from(number: $type) -> Self {
+                Value::N(Number::from(number))
+            }
+        })*
+    }
+}
+
+number_from!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// Floats convert fallibly, since DynamoDB can't store NaN and infinities.
+macro_rules! number_try_from {
+    ($($type:ty),*) => {
+        $(impl TryFrom<$type> for Number {
+            type Error = ConversionError;
+
+            fn try_from(number: $type) -> Result<Self, Self::Error> {
+                if number.is_finite() {
+                    Ok(Number(number.to_string()))
+                } else {
+                    Err(ConversionError::InvalidValue)
+                }
+            }
+        }
+
+        impl TryFrom<$type> for Value {
+            type Error = ConversionError;
+
+            fn try_from(number: $type) -> Result<Self, Self::Error> {
+                Number::try_from(number).map(Value::N)
+            }
+        })*
+    }
+}
+
+number_try_from!(f32, f64);
+
+
+/// Any value which can be stored in a DynamoDB attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    S(String),
+    N(Number),
+    B(Vec<u8>),
+    Bool(bool),
+    Null,
+    M(HashMap<String, Value>),
+    L(Vec<Value>),
+    SS(Vec<String>),
+    NS(Vec<Number>),
+    BS(Vec<Vec<u8>>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::S(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match *self {
+            Value::N(ref number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            Value::B(ref bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&HashMap<String, Value>> {
+        match *self {
+            Value::M(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match *self {
+            Value::L(ref list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_set(&self) -> Option<&[String]> {
+        match *self {
+            Value::SS(ref set) => Some(set),
+            _ => None,
+        }
+    }
+
+    pub fn as_number_set(&self) -> Option<&[Number]> {
+        match *self {
+            Value::NS(ref set) => Some(set),
+            _ => None,
+        }
+    }
+
+    pub fn as_binary_set(&self) -> Option<&[Vec<u8>]> {
+        match *self {
+            Value::BS(ref set) => Some(set),
+            _ => None,
+        }
+    }
+
+    /// Looks up a member of a map value. Returns `None` for missing members
+    /// and for values which are not maps.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_map().and_then(|map| map.get(key))
+    }
+}
+
+impl AttributeValueConverter for Value {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        let attribute_type = AttributeType::single(&attribute_value)?;
+        let AttributeValue { s, n, b, bool, m, l, ss, ns, bs, .. } = attribute_value;
+        match attribute_type {
+            AttributeType::S => Ok(Value::S(s.unwrap_or_default())),
+            AttributeType::N => n.unwrap_or_default().parse().map(Value::N),
+            AttributeType::B => Ok(Value::B(b.unwrap_or_default())),
+            AttributeType::Bool => Ok(Value::Bool(bool.unwrap_or_default())),
+            AttributeType::Null => Ok(Value::Null),
+            AttributeType::M => m.unwrap_or_default()
+                .into_iter()
+                .map(|(key, value)| Ok((key, Value::from_attribute_value(value)?)))
+                .collect::<ConversionResult<_>>()
+                .map(Value::M),
+            AttributeType::L => l.unwrap_or_default()
+                .into_iter()
+                .map(Value::from_attribute_value)
+                .collect::<ConversionResult<_>>()
+                .map(Value::L),
+            AttributeType::SS => Ok(Value::SS(ss.unwrap_or_default())),
+            AttributeType::NS => ns.unwrap_or_default()
+                .into_iter()
+                .map(|number| number.parse())
+                .collect::<ConversionResult<_>>()
+                .map(Value::NS),
+            AttributeType::BS => Ok(Value::BS(bs.unwrap_or_default())),
+        }
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        let mut attribute_value = AttributeValue::default();
+        match self {
+            Value::S(string) => attribute_value.s = Some(string),
+            Value::N(number) => attribute_value.n = Some(number.0),
+            Value::B(bytes) => attribute_value.b = Some(bytes),
+            Value::Bool(boolean) => attribute_value.bool = Some(boolean),
+            Value::Null => attribute_value.null = Some(true),
+            Value::M(mut map) => attribute_value.m = Some(
+                map.drain()
+                    .map(|(key, value)| (key, value.to_attribute_value()))
+                    .collect()
+            ),
+            Value::L(mut list) => attribute_value.l = Some(
+                list.drain(..).map(Value::to_attribute_value).collect()
+            ),
+            Value::SS(set) => attribute_value.ss = Some(set),
+            Value::NS(mut set) => attribute_value.ns = Some(
+                set.drain(..).map(|number| number.0).collect()
+            ),
+            Value::BS(set) => attribute_value.bs = Some(set),
+        }
+        attribute_value
+    }
+}
+
+impl From<Number> for Value {
+    fn from(number: Number) -> Self {
+        Value::N(number)
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::S(string)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(string: &'a str) -> Self {
+        Value::S(string.to_string())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Value::Bool(boolean)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::B(bytes)
+    }
+}
+
+impl From<HashMap<String, Value>> for Value {
+    fn from(map: HashMap<String, Value>) -> Self {
+        Value::M(map)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(list: Vec<Value>) -> Self {
+        Value::L(list)
+    }
+}
+
+impl From<HashSet<String>> for Value {
+    fn from(set: HashSet<String>) -> Self {
+        Value::SS(set.into_iter().collect())
+    }
+}
+
+impl From<BTreeSet<String>> for Value {
+    fn from(set: BTreeSet<String>) -> Self {
+        Value::SS(set.into_iter().collect())
+    }
+}
+
+impl From<BTreeSet<Number>> for Value {
+    fn from(set: BTreeSet<Number>) -> Self {
+        Value::NS(set.into_iter().collect())
+    }
+}
+
+impl From<BTreeSet<Vec<u8>>> for Value {
+    fn from(set: BTreeSet<Vec<u8>>) -> Self {
+        Value::BS(set.into_iter().collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Value::Null, Into::into)
+    }
+}
+
+
+/// Builds a `Value` from a JSON-like literal.
+///
+/// ```ignore
+/// let value = korat::value!({
+///     "name": "korat",
+///     "tags": ["dynamodb", "rusoto"],
+///     "stars": 12,
+///     "archived": false,
+///     "parent": null
+/// });
+/// ```
+///
+/// Anything which is not a `null`, `true`, `false`, list or map literal is
+/// converted with `Value::from`, so floats have to be converted beforehand.
+#[macro_export]
+macro_rules! value {
+    (@list [$($elements:expr,)*]) => {
+        vec![$($elements,)*]
+    };
+    (@list [$($elements:expr,)*] $($rest:tt)+) => {
+        $crate::value!(@element [$($elements,)*] [] $($rest)+)
+    };
+    (@element [$($elements:expr,)*] [$($element:tt)+] , $($rest:tt)*) => {
+        $crate::value!(@list [$($elements,)* $crate::value!($($element)+),] $($rest)*)
+    };
+    (@element [$($elements:expr,)*] [$($element:tt)+]) => {
+        $crate::value!(@list [$($elements,)* $crate::value!($($element)+),])
+    };
+    (@element $elements:tt [$($element:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::value!(@element $elements [$($element)* $next] $($rest)*)
+    };
+
+    (@map $map:ident) => {};
+    (@map $map:ident $key:tt : $($rest:tt)+) => {
+        $crate::value!(@entry $map $key [] $($rest)+)
+    };
+    (@entry $map:ident $key:tt [$($entry:tt)+] , $($rest:tt)*) => {
+        $map.insert(::std::string::String::from($key), $crate::value!($($entry)+));
+        $crate::value!(@map $map $($rest)*)
+    };
+    (@entry $map:ident $key:tt [$($entry:tt)+]) => {
+        $map.insert(::std::string::String::from($key), $crate::value!($($entry)+));
+    };
+    (@entry $map:ident $key:tt [$($entry:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::value!(@entry $map $key [$($entry)* $next] $($rest)*)
+    };
+
+    (null) => {
+        $crate::Value::Null
+    };
+    (true) => {
+        $crate::Value::Bool(true)
+    };
+    (false) => {
+        $crate::Value::Bool(false)
+    };
+    ([ $($elements:tt)* ]) => {
+        $crate::Value::L($crate::value!(@list [] $($elements)*))
+    };
+    ({ $($entries:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut map = ::std::collections::HashMap::new();
+        $crate::value!(@map map $($entries)*);
+        $crate::Value::M(map)
+    }};
+    ($other:expr) => {
+        $crate::Value::from($other)
+    };
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeSet, HashMap};
+    use std::convert::TryFrom;
+
+    use AttributeValue;
+
+    use AttributeValueConverter;
+    use super::{Number, Value};
+
+    #[test]
+    fn validates_numbers() {
+        for valid in &["0", "-1", "+12", "1.5", ".5", "5.", "1e10", "-1.5E-3"] {
+            assert!(valid.parse::<Number>().is_ok(), "{} should be valid", valid);
+        }
+        for invalid in &["", "-", "--1", "1.2.3", "1e", "abc", "1 ", "inf"] {
+            assert!(invalid.parse::<Number>().is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn rejects_non_finite_floats() {
+        assert_eq!("0.25", Number::try_from(0.25f32).unwrap().as_str());
+        for invalid in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(Number::try_from(*invalid).is_err(), "{} should be invalid", invalid);
+            assert!(Value::try_from(*invalid).is_err(), "{} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn numbers_keep_their_precision() {
+        let number: Number = "123456789012345678901234567890.5".parse().unwrap();
+        let converted = Value::N(number.clone()).to_attribute_value();
+        assert_eq!("123456789012345678901234567890.5", &converted.n.unwrap());
+        assert_eq!(None, number.as_i64());
+    }
+
+    #[test]
+    fn can_convert_from_attribute_values() {
+        let mut map = HashMap::new();
+        map.insert("name".to_string(), AttributeValue {
+            s: Some("korat".to_string()),
+            ..AttributeValue::default()
+        });
+        map.insert("stars".to_string(), AttributeValue {
+            n: Some("12".to_string()),
+            ..AttributeValue::default()
+        });
+        map.insert("parent".to_string(), AttributeValue {
+            null: Some(true),
+            ..AttributeValue::default()
+        });
+        let av = AttributeValue {
+            l: Some(vec![AttributeValue {
+                m: Some(map),
+                ..AttributeValue::default()
+            }]),
+            ..AttributeValue::default()
+        };
+
+        let converted = Value::from_attribute_value(av).unwrap();
+        let item = &converted.as_list().unwrap()[0];
+        assert_eq!(Some("korat"), item.get("name").and_then(Value::as_str));
+        assert_eq!(Some(12), item.get("stars").and_then(Value::as_i64));
+        assert!(item.get("parent").unwrap().is_null());
+    }
+
+    #[test]
+    fn fails_on_missing_value() {
+        assert!(Value::from_attribute_value(AttributeValue::default()).is_err());
+    }
+
+    #[test]
+    fn fails_on_multiple_members() {
+        let av = AttributeValue {
+            s: Some("value".to_string()),
+            bool: Some(true),
+            ..AttributeValue::default()
+        };
+        assert!(Value::from_attribute_value(av).is_err());
+    }
+
+    #[test]
+    fn fails_on_invalid_number() {
+        let av = AttributeValue {
+            n: Some("non-numeric".to_string()),
+            ..AttributeValue::default()
+        };
+        assert!(Value::from_attribute_value(av).is_err());
+    }
+
+    #[test]
+    fn can_convert_all_kinds_back_and_forth() {
+        let mut map = HashMap::new();
+        map.insert("nested".to_string(), Value::from("value"));
+        let values = vec![
+            Value::from("string"),
+            Value::try_from(-12.5).unwrap(),
+            Value::from(vec![1u8, 2, 3]),
+            Value::from(true),
+            Value::Null,
+            Value::from(map),
+            Value::L(vec![Value::from(1), Value::from("two")]),
+            Value::SS(vec!["a".to_string(), "b".to_string()]),
+            Value::NS(vec![Number::from(1), Number::try_from(2.5).unwrap()]),
+            Value::BS(vec![vec![1], vec![2]]),
+        ];
+
+        for value in values {
+            let converted = Value::from_attribute_value(
+                value.clone().to_attribute_value()
+            ).unwrap();
+            assert_eq!(value, converted);
+        }
+    }
+
+    #[test]
+    fn can_build_values_from_literals() {
+        let tags: BTreeSet<String> = vec!["a".to_string()].into_iter().collect();
+        let value = value!({
+            "name": "korat",
+            "stars": 12,
+            "offset": -1,
+            "archived": false,
+            "parent": null,
+            "tags": tags.clone(),
+            "versions": [1, "two", [3], {"four": 4}],
+            "empty": {}
+        });
+
+        let mut four = HashMap::new();
+        four.insert("four".to_string(), Value::from(4));
+        let mut expected = HashMap::new();
+        expected.insert("name".to_string(), Value::from("korat"));
+        expected.insert("stars".to_string(), Value::from(12));
+        expected.insert("offset".to_string(), Value::from(-1));
+        expected.insert("archived".to_string(), Value::Bool(false));
+        expected.insert("parent".to_string(), Value::Null);
+        expected.insert("tags".to_string(), Value::from(tags));
+        expected.insert("versions".to_string(), Value::L(vec![
+            Value::from(1),
+            Value::from("two"),
+            Value::L(vec![Value::from(3)]),
+            Value::M(four),
+        ]));
+        expected.insert("empty".to_string(), Value::M(HashMap::new()));
+
+        assert_eq!(Value::M(expected), value);
+        assert_eq!(Value::L(vec![]), value!([]));
+    }
+
+    #[test]
+    fn can_match_on_values() {
+        match value!("string") {
+            Value::S(ref string) if string == "string" => (),
+            other => panic!("unexpected value {:?}", other),
+        }
+    }
+}
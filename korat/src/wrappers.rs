@@ -0,0 +1,191 @@
+//! Converters for the pointer and cell types wrapping a convertible value.
+//!
+//! The wrappers are transparent: a `Box<T>`, `Rc<T>`, `Arc<T>`, `Cell<T>` or
+//! `RefCell<T>` is stored exactly as its `T` would be, and a `Cow` as its
+//! owned form. Boxing makes recursive items such as a comment holding a
+//! `Vec<Box<Comment>>` of replies possible to derive.
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::{
+    AttributeType, AttributeValue, AttributeValueConverter, ConversionResult, ListElement,
+    ScalarKey
+};
+
+
+/// Implements a converter for a pointer type by delegating to the pointee.
+/// Shared pointers are unwrapped when they hold the only reference and
+/// cloned from otherwise.
+macro_rules! pointer_converter {
+    ($pointer:ident, $into_inner:expr) => {
+        impl<T: AttributeValueConverter + Clone> AttributeValueConverter for $pointer<T> {
+            fn from_attribute_value(
+                attribute_value: AttributeValue
+            ) -> ConversionResult<Self> {
+                T::from_attribute_value(attribute_value).map($pointer::new)
+            }
+
+            fn coerce_from_attribute_value(
+                attribute_value: AttributeValue
+            ) -> ConversionResult<Self> {
+                T::coerce_from_attribute_value(attribute_value).map($pointer::new)
+            }
+
+            fn to_attribute_value(self) -> AttributeValue {
+                let into_inner: fn($pointer<T>) -> T = $into_inner;
+                into_inner(self).to_attribute_value()
+            }
+
+            fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+                let into_inner: fn($pointer<T>) -> T = $into_inner;
+                into_inner(self).try_to_attribute_value()
+            }
+        }
+
+        impl<T: ListElement + Clone> ListElement for $pointer<T> {}
+
+        impl<T: ScalarKey + Clone> ScalarKey for $pointer<T> {
+            const ATTRIBUTE_TYPE: AttributeType = T::ATTRIBUTE_TYPE;
+        }
+    }
+}
+
+pointer_converter!(Rc, |rc| Rc::try_unwrap(rc).unwrap_or_else(|rc| (*rc).clone()));
+pointer_converter!(Arc, |arc| Arc::try_unwrap(arc).unwrap_or_else(|arc| (*arc).clone()));
+
+impl<T: AttributeValueConverter> AttributeValueConverter for Box<T> {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        T::from_attribute_value(attribute_value).map(Box::new)
+    }
+
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        T::coerce_from_attribute_value(attribute_value).map(Box::new)
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        (*self).to_attribute_value()
+    }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        (*self).try_to_attribute_value()
+    }
+}
+
+impl<T: ListElement> ListElement for Box<T> {}
+
+impl<T: ScalarKey> ScalarKey for Box<T> {
+    const ATTRIBUTE_TYPE: AttributeType = T::ATTRIBUTE_TYPE;
+}
+
+impl<T: AttributeValueConverter + Copy> AttributeValueConverter for Cell<T> {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        T::from_attribute_value(attribute_value).map(Cell::new)
+    }
+
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        T::coerce_from_attribute_value(attribute_value).map(Cell::new)
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        self.into_inner().to_attribute_value()
+    }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        self.into_inner().try_to_attribute_value()
+    }
+}
+
+impl<T: AttributeValueConverter> AttributeValueConverter for RefCell<T> {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        T::from_attribute_value(attribute_value).map(RefCell::new)
+    }
+
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        T::coerce_from_attribute_value(attribute_value).map(RefCell::new)
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        self.into_inner().to_attribute_value()
+    }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        self.into_inner().try_to_attribute_value()
+    }
+}
+
+/// Borrowed values are converted through their owned form, so decoding
+/// always produces `Cow::Owned`.
+impl<'a, T> AttributeValueConverter for Cow<'a, T>
+    where T: ToOwned + ?Sized, T::Owned: AttributeValueConverter {
+    fn from_attribute_value(attribute_value: AttributeValue) -> ConversionResult<Self> {
+        T::Owned::from_attribute_value(attribute_value).map(Cow::Owned)
+    }
+
+    fn coerce_from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> ConversionResult<Self> {
+        T::Owned::coerce_from_attribute_value(attribute_value).map(Cow::Owned)
+    }
+
+    fn to_attribute_value(self) -> AttributeValue {
+        self.into_owned().to_attribute_value()
+    }
+
+    fn try_to_attribute_value(self) -> ConversionResult<AttributeValue> {
+        self.into_owned().try_to_attribute_value()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use AttributeValueConverter;
+
+    #[test]
+    fn wrappers_are_stored_as_their_contents() {
+        let converted = Box::new(12i32).to_attribute_value();
+        assert_eq!("12", &converted.clone().n.unwrap());
+        assert_eq!(Box::new(12), Box::<i32>::from_attribute_value(converted).unwrap());
+
+        let converted = Cell::new(true).to_attribute_value();
+        assert!(Cell::<bool>::from_attribute_value(converted).unwrap().get());
+
+        let converted = RefCell::new("korat".to_string()).to_attribute_value();
+        assert_eq!("korat", *RefCell::<String>::from_attribute_value(converted).unwrap().borrow());
+    }
+
+    #[test]
+    fn shared_pointers_are_cloned_when_shared() {
+        let shared = Rc::new("korat".to_string());
+        let converted = shared.clone().to_attribute_value();
+        assert_eq!("korat", &converted.clone().s.unwrap());
+        assert_eq!(shared, Rc::from_attribute_value(converted).unwrap());
+
+        let shared = Arc::new(vec![1u16, 2]);
+        let converted = Arc::clone(&shared).to_attribute_value();
+        assert_eq!(shared, Arc::from_attribute_value(converted).unwrap());
+    }
+
+    #[test]
+    fn cows_are_decoded_as_owned() {
+        let converted = Cow::Borrowed("korat").to_attribute_value();
+        match Cow::<str>::from_attribute_value(converted).unwrap() {
+            Cow::Owned(string) => assert_eq!("korat", string),
+            Cow::Borrowed(_) => panic!("expected an owned value"),
+        }
+    }
+}
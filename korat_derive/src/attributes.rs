@@ -0,0 +1,541 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Attribute, Error, Field, Ident, LitInt, LitStr, Meta, Path, Result, Token};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+
+use error::suggestion;
+
+
+const TIMESTAMP_ENCODINGS: &[&str] = &["rfc3339", "epoch_seconds", "epoch_millis"];
+const COMPRESSIONS: &[&str] = &["gzip", "zstd"];
+const KEY_ATTRIBUTE_TYPES: &[&str] = &["S", "N", "B"];
+const BILLING_MODES: &[&str] = &["pay_per_request", "provisioned"];
+const WORD_OPTIONS: &[&str] = &[
+    "hash", "range", "coerce", "serde", "json_string", "encrypt", "sign", "ttl"
+];
+const VALUE_OPTIONS: &[&str] = &[
+    "timestamp", "compress", "with", "gsi_hash", "gsi_range", "lsi_range", "attribute_type"
+];
+const KEY_OPTIONS: &[&str] = &["hash", "range", "gsi_hash", "gsi_range", "lsi_range"];
+const TABLE_FIELD_OPTIONS: &[&str] = &["ttl", "attribute_type"];
+const TABLE_OPTIONS: &[&str] = &["table", "billing_mode", "read_capacity", "write_capacity"];
+const STRUCT_OPTIONS: &[&str] = &[
+    "hash", "range", "hash_attribute", "range_attribute",
+    "table", "billing_mode", "read_capacity", "write_capacity"
+];
+
+
+/// The options set on a field through `#[korat(...)]` attributes, along with
+/// the `#[hash]` and `#[range]` markers, which may also be written as
+/// `#[korat(hash)]` and `#[korat(range)]`.
+///
+/// The secondary index options may be repeated for a field which is a key of
+/// more than one index. `ttl` and `attribute_type` only matter to the table
+/// definition, see `StructOptions`.
+#[derive(Default)]
+pub struct FieldOptions {
+    pub hash: bool,
+    pub range: bool,
+    pub coerce: bool,
+    pub serde: bool,
+    pub json_string: bool,
+    pub compress: Option<String>,
+    pub timestamp: Option<String>,
+    pub with: Option<Path>,
+    pub encrypt: bool,
+    pub sign: bool,
+    pub gsi_hash: Vec<LitStr>,
+    pub gsi_range: Vec<LitStr>,
+    pub lsi_range: Vec<LitStr>,
+    pub ttl: bool,
+    pub attribute_type: Option<LitStr>,
+}
+
+impl FieldOptions {
+    pub fn from_field(field: &Field) -> Result<FieldOptions> {
+        let mut options = FieldOptions::default();
+        for attr in &field.attrs {
+            if attr.path().is_ident("hash") {
+                attr.meta.require_path_only()?;
+                options.hash = true;
+            } else if attr.path().is_ident("range") {
+                attr.meta.require_path_only()?;
+                options.range = true;
+            } else if attr.path().is_ident("korat") {
+                attr.parse_nested_meta(|meta| options.set(meta))?;
+            }
+        }
+        options.check_combination(field)?;
+        Ok(options)
+    }
+
+    fn set(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        let name = meta.path.get_ident().map(Ident::to_string).unwrap_or_default();
+        if WORD_OPTIONS.contains(&name.as_str()) && !is_word(&meta) {
+            return Err(meta.error(format!(
+                "#[korat({})] takes no value, use #[korat({})]", name, name
+            )));
+        }
+        if VALUE_OPTIONS.contains(&name.as_str()) && !meta.input.peek(Token![=]) {
+            return Err(meta.error(format!(
+                "#[korat({})] requires a value, as in #[korat({} = \"...\")]", name, name
+            )));
+        }
+
+        match name.as_str() {
+            "hash" => self.hash = true,
+            "range" => self.range = true,
+            "coerce" => self.coerce = true,
+            "serde" => self.serde = true,
+            "json_string" => self.json_string = true,
+            "encrypt" => self.encrypt = true,
+            "sign" => self.sign = true,
+            "ttl" => self.ttl = true,
+            "timestamp" => {
+                let encoding = string_value(&meta)?;
+                self.timestamp = Some(
+                    one_of(&encoding, TIMESTAMP_ENCODINGS, "timestamp encoding")?
+                );
+            },
+            "compress" => {
+                let compression = string_value(&meta)?;
+                self.compress = Some(one_of(&compression, COMPRESSIONS, "compression")?);
+            },
+            "with" => {
+                let path = string_value(&meta)?;
+                self.with = Some(path.parse().map_err(|_| Error::new(path.span(), format!(
+                    "the value of `with` should be a module path such as \
+                     \"korat::timestamp::rfc3339\", found \"{}\"", path.value()
+                )))?);
+            },
+            "gsi_hash" => self.gsi_hash.push(index_name(&meta)?),
+            "gsi_range" => self.gsi_range.push(index_name(&meta)?),
+            "lsi_range" => self.lsi_range.push(index_name(&meta)?),
+            "attribute_type" => {
+                let attribute_type = string_value(&meta)?;
+                one_of(&attribute_type, KEY_ATTRIBUTE_TYPES, "key attribute type")?;
+                self.attribute_type = Some(attribute_type);
+            },
+            _ => {
+                let valid: Vec<&str> = WORD_OPTIONS.iter().chain(VALUE_OPTIONS).cloned().collect();
+                return Err(meta.error(format!(
+                    "unknown korat attribute `{}`, expected one of {}{}",
+                    name, valid.join(", "), suggestion(&name, &valid)
+                )));
+            },
+        }
+        Ok(())
+    }
+
+    fn check_combination(&self, field: &Field) -> Result<()> {
+        // Compression is the only option which combines with another, so
+        // that serde types can be compressed.
+        let converters = [
+            self.timestamp.is_some(), self.with.is_some(),
+            self.serde || self.compress.is_some(), self.json_string
+        ];
+        match converters.iter().filter(|&&is_set| is_set).count() {
+            0 => (),
+            1 if !self.coerce => (),
+            1 => return Err(Error::new_spanned(
+                &field.ident, "coerce can only be used with the default converters, remove \
+                               either coerce or the converter option"
+            )),
+            _ => return Err(Error::new_spanned(
+                &field.ident, "a field can only set one of a timestamp encoding, a converter \
+                               module, serde, json_string or compress, which may be combined \
+                               with serde"
+            )),
+        }
+        if self.hash && self.range {
+            return Err(Error::new_spanned(
+                &field.ident, "a field can't be both the hash and the range key"
+            ));
+        }
+        if (self.hash || self.range) && (self.encrypt || self.sign) {
+            return Err(Error::new_spanned(
+                &field.ident, "key fields are always signed and can't be encrypted, remove \
+                               encrypt and sign from the key"
+            ));
+        }
+        if self.is_index_key() && self.encrypt {
+            return Err(Error::new_spanned(
+                &field.ident, "secondary index keys are stored in plaintext, remove either \
+                               encrypt or the index options"
+            ));
+        }
+        if self.attribute_type.is_some() && !(self.hash || self.range || self.is_index_key()) {
+            return Err(Error::new_spanned(
+                &field.ident, "attribute_type sets the type of a key attribute in the table \
+                               definition, it can only be used on key fields"
+            ));
+        }
+        let is_number = self.timestamp.as_ref().is_none_or(|encoding| encoding == "epoch_seconds")
+            && !(self.serde || self.json_string || self.compress.is_some() || self.encrypt);
+        if self.ttl && !is_number {
+            return Err(Error::new_spanned(
+                &field.ident, "the time to live attribute must be stored as a number of \
+                               seconds since the epoch, use #[korat(timestamp = \
+                               \"epoch_seconds\")] or an integer field"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the field is part of the key of a secondary index.
+    pub fn is_index_key(&self) -> bool {
+        !(self.gsi_hash.is_empty() && self.gsi_range.is_empty() && self.lsi_range.is_empty())
+    }
+
+    /// Whether the stored type of the field follows from its type, which is
+    /// the case unless it goes through a converter module, serde or JSON.
+    pub fn stores_field_type(&self) -> bool {
+        self.with.is_none() && !self.serde && !self.json_string
+    }
+
+    /// The path exposing the `to_attribute_value` and `from_attribute_value`
+    /// functions used for the field.
+    pub fn converter(&self) -> TokenStream {
+        if let Some(ref path) = self.with {
+            return quote!(#path);
+        }
+        if let Some(ref compression) = self.compress {
+            let compression = Ident::new(compression, Span::call_site());
+            return if self.serde {
+                quote!(::korat::compression::#compression::serde)
+            } else {
+                quote!(::korat::compression::#compression)
+            };
+        }
+        if self.serde {
+            return quote!(::korat::serde);
+        }
+        if self.json_string {
+            return quote!(::korat::json_string);
+        }
+        match self.timestamp {
+            Some(ref encoding) => {
+                let encoding = Ident::new(encoding, Span::call_site());
+                quote!(::korat::timestamp::#encoding)
+            },
+            None => quote!(::korat::AttributeValueConverter),
+        }
+    }
+
+    pub fn encoder(&self) -> TokenStream {
+        let converter = self.converter();
+        quote!(#converter::to_attribute_value)
+    }
+
+    /// Converts `value` into a `Result`, through `try_to_attribute_value` for
+    /// the converters which can fail, serde, JSON strings and the types of
+    /// fields, so that their errors are reported instead of panicking.
+    pub fn try_encode(&self, value: TokenStream) -> TokenStream {
+        let converter = self.converter();
+        let fallible = self.with.is_none()
+            && (self.serde || self.compress.is_none() && self.timestamp.is_none());
+        if fallible {
+            quote!(#converter::try_to_attribute_value(#value))
+        } else {
+            let conversion_error = quote!(::korat::errors::ConversionError);
+            quote!(Ok::<_, #conversion_error>(#converter::to_attribute_value(#value)))
+        }
+    }
+
+    pub fn decoder(&self) -> TokenStream {
+        let converter = self.converter();
+        if self.coerce {
+            quote!(#converter::coerce_from_attribute_value)
+        } else {
+            quote!(#converter::from_attribute_value)
+        }
+    }
+}
+
+/// Copies the `#[korat(...)]` attributes of a key field for the generated
+/// key struct, leaving out the key markers so that the key struct doesn't
+/// get a key of its own, as well as the options of the table definition.
+pub fn key_struct_attributes(field: &Field) -> Vec<TokenStream> {
+    field.attrs.iter()
+        .filter(|attr| attr.path().is_ident("korat"))
+        .filter_map(|attr| {
+            let items = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .expect("korat attributes are validated when reading the field options");
+            let items: Vec<&Meta> = items.iter()
+                .filter(|item| {
+                    !KEY_OPTIONS.iter().chain(TABLE_FIELD_OPTIONS)
+                        .any(|option| item.path().is_ident(option))
+                })
+                .collect();
+            if items.is_empty() {
+                None
+            } else {
+                Some(quote!(#[korat(#(#items),*)]))
+            }
+        })
+        .collect()
+}
+
+/// The options set on the struct through `#[korat(...)]` attributes, which
+/// declare the keys synthesized from the fields and the table the item is
+/// stored in:
+///
+/// ```rust,ignore
+/// #[korat(hash = "USER#{user_id}", range = "ORDER#{order_id}")]
+/// #[korat(table = "orders", billing_mode = "provisioned", read_capacity = 5, write_capacity = 5)]
+/// ```
+///
+/// The generated key structs are marked with `key_struct`, so that they don't
+/// get key structs of their own.
+#[derive(Default)]
+pub struct StructOptions {
+    pub hash: Option<KeyTemplate>,
+    pub range: Option<KeyTemplate>,
+    pub table: Option<TableOptions>,
+    pub key_struct: bool,
+}
+
+/// Tables are billed per request unless provisioned, in which case both
+/// capacities are required.
+pub struct TableOptions {
+    pub name: LitStr,
+    pub billing_mode: BillingMode,
+}
+
+pub enum BillingMode {
+    PayPerRequest,
+    Provisioned { read_capacity: i64, write_capacity: i64 },
+}
+
+/// A key template such as `"ORDER#{date}#{order_id}"`, stored in the
+/// attribute named by `hash_attribute` or `range_attribute`.
+pub struct KeyTemplate {
+    pub template: LitStr,
+    pub attribute_name: LitStr,
+    pub parts: Vec<TemplatePart>,
+}
+
+pub enum TemplatePart {
+    Literal(String),
+    Component(String),
+}
+
+impl StructOptions {
+    pub fn from_attributes(attrs: &[Attribute]) -> Result<StructOptions> {
+        let mut options = StructOptions::default();
+        let mut templates = (None, None);
+        let mut attribute_names = (None, None);
+        let mut table_name = None;
+        let mut billing_mode = None;
+        let mut capacities = (None, None);
+        let mut first_table_option = None;
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("korat")) {
+            attr.parse_nested_meta(|meta| {
+                let option = meta.path.get_ident().map(Ident::to_string).unwrap_or_default();
+                if option == "key_struct" {
+                    options.key_struct = true;
+                    return Ok(());
+                }
+                if STRUCT_OPTIONS.contains(&option.as_str()) && !meta.input.peek(Token![=]) {
+                    return Err(meta.error(format!(
+                        "#[korat({})] requires a value, as in #[korat({} = ...)]",
+                        option, option
+                    )));
+                }
+                if TABLE_OPTIONS.contains(&option.as_str()) && first_table_option.is_none() {
+                    first_table_option = Some(meta.path.clone());
+                }
+                match option.as_str() {
+                    "hash" => templates.0 = Some(string_value(&meta)?),
+                    "range" => templates.1 = Some(string_value(&meta)?),
+                    "hash_attribute" => attribute_names.0 = Some(string_value(&meta)?),
+                    "range_attribute" => attribute_names.1 = Some(string_value(&meta)?),
+                    "table" => table_name = Some(resource_name(&meta, "table")?),
+                    "billing_mode" => {
+                        let mode = string_value(&meta)?;
+                        one_of(&mode, BILLING_MODES, "billing mode")?;
+                        billing_mode = Some(mode);
+                    },
+                    "read_capacity" => capacities.0 = Some(capacity(&meta)?),
+                    "write_capacity" => capacities.1 = Some(capacity(&meta)?),
+                    _ => return Err(meta.error(format!(
+                        "unknown korat attribute `{}` on a struct, expected one of {}{}",
+                        option, STRUCT_OPTIONS.join(", "), suggestion(&option, STRUCT_OPTIONS)
+                    ))),
+                }
+                Ok(())
+            })?;
+        }
+
+        options.hash = KeyTemplate::from_options(templates.0, attribute_names.0, "hash", "PK")?;
+        options.range = KeyTemplate::from_options(templates.1, attribute_names.1, "range", "SK")?;
+        options.table = match (table_name, first_table_option) {
+            (Some(name), _) => Some(TableOptions::new(name, billing_mode, capacities)?),
+            (None, Some(option)) => return Err(Error::new_spanned(
+                option, "the table options require the table name, as in \
+                         #[korat(table = \"...\")]"
+            )),
+            (None, None) => None,
+        };
+        Ok(options)
+    }
+}
+
+impl TableOptions {
+    fn new(
+        name: LitStr, billing_mode: Option<LitStr>, capacities: (Option<i64>, Option<i64>)
+    ) -> Result<TableOptions> {
+        let is_provisioned = billing_mode.as_ref().is_some_and(|mode| mode.value() == "provisioned");
+        let billing_mode = match capacities {
+            (Some(read_capacity), Some(write_capacity)) if is_provisioned => {
+                BillingMode::Provisioned { read_capacity, write_capacity }
+            },
+            (None, None) if !is_provisioned => BillingMode::PayPerRequest,
+            _ if is_provisioned => return Err(Error::new(
+                name.span(), "provisioned tables require read_capacity and write_capacity"
+            )),
+            _ => return Err(Error::new(
+                name.span(), "read_capacity and write_capacity only apply to provisioned \
+                              tables, add billing_mode = \"provisioned\""
+            )),
+        };
+        Ok(TableOptions { name, billing_mode })
+    }
+}
+
+impl KeyTemplate {
+    fn from_options(
+        template: Option<LitStr>, attribute_name: Option<LitStr>, key: &str, default_name: &str
+    ) -> Result<Option<KeyTemplate>> {
+        let template = match (template, &attribute_name) {
+            (Some(template), _) => template,
+            (None, Some(attribute_name)) => return Err(Error::new(attribute_name.span(), format!(
+                "{0}_attribute names the attribute of a {0} key template, add \
+                 #[korat({0} = \"...\")]", key
+            ))),
+            (None, None) => return Ok(None),
+        };
+        let attribute_name = attribute_name
+            .unwrap_or_else(|| LitStr::new(default_name, template.span()));
+        if template.value().is_empty() {
+            return Err(Error::new(template.span(), "key templates can't be empty"));
+        }
+        let parts = KeyTemplate::parse(&template)?;
+        Ok(Some(KeyTemplate { template, attribute_name, parts }))
+    }
+
+    /// Splits a template into its text and the fields in braces. Components
+    /// must be separated by some text for keys to be parsed back, which can't
+    /// start with the `%` escaping the components.
+    fn parse(template: &LitStr) -> Result<Vec<TemplatePart>> {
+        let error = |message: &str| Err(Error::new(template.span(), format!(
+            "invalid key template \"{}\": {}", template.value(), message
+        )));
+        let value = template.value();
+        let mut parts = Vec::new();
+        let mut rest = value.as_str();
+        while !rest.is_empty() {
+            let start = rest.find(['{', '}']).unwrap_or(rest.len());
+            if start > 0 {
+                if rest.starts_with('%') && !parts.is_empty() {
+                    return error("the text following a component can't start with `%`, \
+                                  which escapes the components");
+                }
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            rest = &rest[start..];
+            if rest.starts_with('}') {
+                return error("unmatched `}`");
+            }
+            if rest.is_empty() {
+                break;
+            }
+            let end = match rest.find('}') {
+                Some(end) => end,
+                None => return error("unmatched `{`"),
+            };
+            let component = &rest[1..end];
+            if Ident::parse_any.parse_str(component).is_err() {
+                return error(&format!("`{{{}}}` should name a field", component));
+            }
+            if let Some(TemplatePart::Component(_)) = parts.last() {
+                return error("components must be separated by some text, as in {a}#{b}");
+            }
+            let is_repeated = parts.iter().any(|part| {
+                matches!(part, TemplatePart::Component(other) if other == component)
+            });
+            if is_repeated {
+                return error(&format!("`{}` is used more than once", component));
+            }
+            parts.push(TemplatePart::Component(component.to_string()));
+            rest = &rest[end + 1..];
+        }
+        Ok(parts)
+    }
+
+    pub fn components(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match *part {
+            TemplatePart::Component(ref component) => Some(component.as_str()),
+            TemplatePart::Literal(_) => None,
+        })
+    }
+}
+
+fn is_word(meta: &ParseNestedMeta) -> bool {
+    meta.input.is_empty() || meta.input.peek(Token![,])
+}
+
+fn string_value(meta: &ParseNestedMeta) -> Result<LitStr> {
+    let name = meta.path.get_ident().map(Ident::to_string).unwrap_or_default();
+    let value = meta.value()?;
+    value.parse::<LitStr>().map_err(|error| Error::new(error.span(), format!(
+        "the value of korat attribute {} should be a string, as in {} = \"...\"", name, name
+    )))
+}
+
+fn index_name(meta: &ParseNestedMeta) -> Result<LitStr> {
+    resource_name(meta, "index")
+}
+
+/// Reads the name of a table or a secondary index, which DynamoDB restricts
+/// to 3 to 255 letters, digits, underscores, dashes and dots.
+fn resource_name(meta: &ParseNestedMeta, resource: &str) -> Result<LitStr> {
+    let name = string_value(meta)?;
+    let value = name.value();
+    let is_valid = (3..=255).contains(&value.len()) && value.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if is_valid {
+        Ok(name)
+    } else {
+        Err(Error::new(name.span(), format!(
+            "invalid {0} name \"{1}\", {0} names are 3 to 255 letters, digits, \
+             underscores, dashes and dots", resource, value
+        )))
+    }
+}
+
+fn capacity(meta: &ParseNestedMeta) -> Result<i64> {
+    let name = meta.path.get_ident().map(Ident::to_string).unwrap_or_default();
+    let units = meta.value()?.parse::<LitInt>().and_then(|units| units.base10_parse::<i64>());
+    match units {
+        Ok(units) if units > 0 => Ok(units),
+        _ => Err(meta.error(format!(
+            "the value of {} should be a positive number of capacity units, as in {} = 5",
+            name, name
+        ))),
+    }
+}
+
+fn one_of(value: &LitStr, valid: &[&str], description: &str) -> Result<String> {
+    let string = value.value();
+    if valid.contains(&string.as_str()) {
+        Ok(string)
+    } else {
+        Err(Error::new(value.span(), format!(
+            "unknown {} \"{}\", expected one of {}{}",
+            description, string, valid.join(", "), suggestion(&string, valid)
+        )))
+    }
+}
@@ -1,78 +1,800 @@
-use quote::Tokens;
-use syn::{Ident, Field, DeriveInput, Visibility};
-use syn::Body::Struct;
-use syn::VariantData::Struct as StructData;
+use proc_macro2::{Span, TokenStream};
+use syn::{
+    Attribute, Data, DeriveInput, Error, Field, Fields, Ident, LitStr, Result, Visibility
+};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
 
+use attributes::{
+    BillingMode, FieldOptions, KeyTemplate, StructOptions, TableOptions, TemplatePart,
+    key_struct_attributes
+};
+use error::{collect, suggestion, to_compile_error};
 
-pub fn expand(ast: &DeriveInput) -> Tokens {
+
+pub fn expand(ast: &DeriveInput) -> TokenStream {
     let name = &ast.ident;
     let vis = &ast.vis;
-    match ast.body {
-        Struct(StructData(ref fields)) => make_dynamodb_item(vis, name, fields),
-        _ => panic!("DynamoDB Items can only be generated for structs")
+    let expanded = match ast.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let fields: Vec<Field> = fields.named.iter().cloned().collect();
+                make_dynamodb_item(vis, name, &ast.attrs, &fields)
+            },
+            _ => Err(Error::new_spanned(&data.fields, format!(
+                "DynamoDBItem can only be derived for structs with named fields, as in \
+                 `struct {} {{ id: String }}`", name
+            ))),
+        },
+        Data::Enum(ref data) => Err(Error::new(data.enum_token.span, format!(
+            "DynamoDBItem can only be derived for structs; to store the enum {} in a \
+             field, mark the field with #[korat(serde)]", name
+        ))),
+        Data::Union(ref data) => Err(Error::new(
+            data.union_token.span, "DynamoDBItem can only be derived for structs"
+        )),
+    };
+    expanded.unwrap_or_else(to_compile_error)
+}
+
+/// A field of the item along with its parsed `#[korat(...)]` options.
+struct ItemField {
+    field: Field,
+    name: Ident,
+    /// The name of the attribute the field is stored in, which is the field
+    /// name without the `r#` prefix of raw identifiers.
+    attribute_name: String,
+    options: FieldOptions,
+}
+
+impl ItemField {
+    fn from_field(field: &Field) -> Result<ItemField> {
+        let name = field.ident.clone()
+            .ok_or_else(|| Error::new(field.span(), "DynamoDBItem fields should have identifiers"))?;
+        Ok(ItemField {
+            field: field.clone(),
+            attribute_name: name.unraw().to_string(),
+            name,
+            options: FieldOptions::from_field(field)?,
+        })
+    }
+}
+
+/// The hash and range keys of an item, along with the secondary indexes
+/// declared on its fields.
+struct Keys<'a> {
+    hash: Option<Key<'a>>,
+    range: Option<Key<'a>>,
+    indexes: Vec<Index<'a>>,
+}
+
+impl<'a> Keys<'a> {
+    fn from_fields(fields: &'a [ItemField], options: &'a StructOptions) -> Result<Keys<'a>> {
+        let (hash, range) = match (
+            get_key_field(fields, "hash", |options| options.hash),
+            get_key_field(fields, "range", |options| options.range)
+        ) {
+            (Ok(hash), Ok(range)) => (hash, range),
+            (Err(mut error), Err(other)) => {
+                error.combine(other);
+                return Err(error);
+            },
+            (Err(error), _) | (_, Err(error)) => return Err(error),
+        };
+        let hash = Key::from_declarations(hash, options.hash.as_ref(), fields, "hash")?;
+        let range = Key::from_declarations(range, options.range.as_ref(), fields, "range")?;
+        match (&hash, &range) {
+            (None, Some(Key::Field(range))) => return Err(Error::new_spanned(
+                &range.name, "a range key requires a hash key, mark another field with #[hash]"
+            )),
+            (None, Some(Key::Template(template, _))) => return Err(Error::new(
+                template.template.span(), "a range key requires a hash key, declare it with \
+                                           #[korat(hash = \"...\")]"
+            )),
+            _ => (),
+        }
+        let keys = Keys { indexes: vec![], hash, range };
+        keys.check_attribute_names(fields)?;
+        let indexes = Index::from_fields(fields, keys.hash.as_ref(), keys.range.as_ref())?;
+        Ok(Keys { indexes, ..keys })
+    }
+
+    /// Fails when the attribute of a key template is also the attribute of a
+    /// field or of the other template.
+    fn check_attribute_names(&self, fields: &[ItemField]) -> Result<()> {
+        let templates: Vec<&KeyTemplate> = self.keys().into_iter()
+            .filter_map(|key| match *key {
+                Key::Template(template, _) => Some(template),
+                Key::Field(_) => None,
+            })
+            .collect();
+        for (position, template) in templates.iter().enumerate() {
+            let attribute_name = template.attribute_name.value();
+            let is_taken = fields.iter()
+                .filter(|field| !self.is_component(field))
+                .any(|field| field.attribute_name == attribute_name)
+                || templates[..position].iter()
+                    .any(|other| other.attribute_name.value() == attribute_name);
+            if is_taken {
+                return Err(Error::new(template.attribute_name.span(), format!(
+                    "the key template \"{}\" is stored in the attribute `{}`, which is already \
+                     used, name another one with hash_attribute or range_attribute",
+                    template.template.value(), attribute_name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn keys(&self) -> Vec<&Key<'a>> {
+        self.hash.iter().chain(&self.range).collect()
+    }
+
+    /// The fields making up the keys, each listed once.
+    fn fields(&self) -> Vec<&'a ItemField> {
+        let mut fields: Vec<&ItemField> = vec![];
+        for field in self.keys().into_iter().flat_map(Key::fields) {
+            if !fields.iter().any(|other| other.name == field.name) {
+                fields.push(field);
+            }
+        }
+        fields
+    }
+
+    /// Whether the field is a component of a key template, and so stored
+    /// within the key rather than in an attribute of its own.
+    fn is_component(&self, field: &ItemField) -> bool {
+        self.keys().into_iter().any(|key| match *key {
+            Key::Template(_, ref components) => {
+                components.iter().any(|component| component.name == field.name)
+            },
+            Key::Field(_) => false,
+        })
+    }
+}
+
+/// The hash or range key of an item: a field marked with `#[hash]` or
+/// `#[range]`, or a template over fields declared on the struct.
+enum Key<'a> {
+    Field(&'a ItemField),
+    /// The template along with its components, in the template order.
+    Template(&'a KeyTemplate, Vec<&'a ItemField>),
+}
+
+impl<'a> Key<'a> {
+    fn from_declarations(
+        field: Option<&'a ItemField>, template: Option<&'a KeyTemplate>,
+        fields: &'a [ItemField], key: &str
+    ) -> Result<Option<Key<'a>>> {
+        let template = match (field, template) {
+            (Some(field), Some(template)) => {
+                let mut error = Error::new(template.template.span(), format!(
+                    "the {} key is declared both as a template and on a field, keep one of them",
+                    key
+                ));
+                error.combine(Error::new_spanned(&field.name, format!(
+                    "`{}` is marked as the {} key", field.attribute_name, key
+                )));
+                return Err(error);
+            },
+            (Some(field), None) => return Ok(Some(Key::Field(field))),
+            (None, Some(template)) => template,
+            (None, None) => return Ok(None),
+        };
+
+        let components = collect(template.components().map(|component| {
+            let attribute_name = component.trim_start_matches("r#");
+            let field = fields.iter().find(|field| field.attribute_name == attribute_name);
+            match field {
+                Some(field) => check_component(field, template).map(|_| field),
+                None => {
+                    let names: Vec<&str> = fields.iter()
+                        .map(|field| field.attribute_name.as_str())
+                        .collect();
+                    Err(Error::new(template.template.span(), format!(
+                        "`{}` in the key template \"{}\" isn't a field{}",
+                        component, template.template.value(), suggestion(attribute_name, &names)
+                    )))
+                },
+            }
+        }))?;
+        Ok(Some(Key::Template(template, components)))
     }
+
+    fn attribute_name(&self) -> String {
+        match *self {
+            Key::Field(field) => field.attribute_name.clone(),
+            Key::Template(template, _) => template.attribute_name.value(),
+        }
+    }
+
+    fn fields(&self) -> Vec<&'a ItemField> {
+        match *self {
+            Key::Field(field) => vec![field],
+            Key::Template(_, ref components) => components.clone(),
+        }
+    }
+
+    /// The attribute value of the key, built from the values of its fields
+    /// given by `value`. Writing template keys can't fail, since components
+    /// are asserted to be `S` or `N` keys and are escaped as needed.
+    fn to_attribute_value<F>(&self, value: F) -> TokenStream
+        where F: Fn(&ItemField) -> TokenStream {
+        let encode = |field: &ItemField| {
+            let to_attribute_value = field.options.encoder();
+            let value = value(field);
+            quote!(#to_attribute_value(#value))
+        };
+        match *self {
+            Key::Field(field) => encode(field),
+            Key::Template(template, ref components) => {
+                let key_template = get_key_template(template, components);
+                let components = components.iter().map(|&component| encode(component));
+                quote!({
+                    const TEMPLATE: ::korat::key_template::KeyTemplate = #key_template;
+                    TEMPLATE.format(vec![#(#components),*])
+                        .expect("key components are checked to be S or N keys when compiling")
+                })
+            },
+        }
+    }
+}
+
+/// Reads a field of `item` for a key, which leaves the item untouched.
+fn cloned_field(item: TokenStream) -> impl Fn(&ItemField) -> TokenStream {
+    move |field| {
+        let field_name = &field.name;
+        quote!(#item.#field_name.clone())
+    }
+}
+
+/// Fails on the options which don't fit a field stored within a key.
+fn check_component(field: &ItemField, template: &KeyTemplate) -> Result<()> {
+    let options = &field.options;
+    if options.hash || options.range || options.is_index_key() || options.ttl
+        || options.encrypt || options.sign {
+        return Err(Error::new_spanned(&field.name, format!(
+            "`{}` is stored within the key \"{}\", so it can't also be a key, an index key, \
+             the time to live attribute, encrypted or signed",
+            field.attribute_name, template.template.value()
+        )));
+    }
+    if !options.stores_field_type() || options.compress.is_some() {
+        return Err(Error::new_spanned(&field.name, format!(
+            "`{}` is stored within the key \"{}\" as a string or a number, remove its \
+             converter option; only timestamp encodings apply to key components",
+            field.attribute_name, template.template.value()
+        )));
+    }
+    Ok(())
+}
+
+/// A secondary index declared with the `gsi_hash`, `gsi_range` and
+/// `lsi_range` options. The hash key of a local secondary index is the hash
+/// key of the table.
+struct Index<'a> {
+    /// The first declaration of the index name, for error spans.
+    name: &'a LitStr,
+    kind: IndexKind,
+    hash: &'a ItemField,
+    range: Option<&'a ItemField>,
+}
+
+#[derive(Clone, Copy)]
+enum IndexKind {
+    Global,
+    Local,
+}
+
+/// A field declaring itself as a key of a secondary index.
+type IndexDeclaration<'a> = (&'a ItemField, &'a LitStr);
+
+impl<'a> Index<'a> {
+    fn from_fields(
+        fields: &'a [ItemField], hash: Option<&Key<'a>>, range: Option<&Key<'a>>
+    ) -> Result<Vec<Index<'a>>> {
+        let mut names: Vec<&LitStr> = vec![];
+        for field in fields {
+            let options = &field.options;
+            for name in options.gsi_hash.iter().chain(&options.gsi_range).chain(&options.lsi_range) {
+                if !names.iter().any(|other| other.value() == name.value()) {
+                    names.push(name);
+                }
+            }
+        }
+        if let (None, Some(name)) = (hash, names.first()) {
+            return Err(Error::new(name.span(), format!(
+                "the secondary index `{}` requires the table to have a hash key, mark it \
+                 with #[hash]", name.value()
+            )));
+        }
+
+        let indexes = collect(names.iter().map(|name| {
+            Index::from_declarations(&name.value(), fields, hash, range)
+        }))?;
+        let mut errors = indexes.iter().enumerate().filter_map(|(position, index)| {
+            indexes[..position].iter()
+                .find(|other| other.struct_suffix() == index.struct_suffix())
+                .map(|other| Error::new(index.name.span(), format!(
+                    "the key structs of the indexes `{}` and `{}` would both be named \
+                     after {}, rename one of them", other.name.value(), index.name.value(),
+                    index.struct_suffix()
+                )))
+        });
+        match errors.next() {
+            Some(error) => Err(errors.fold(error, |mut error, other| {
+                error.combine(other);
+                error
+            })),
+            None => Ok(indexes),
+        }
+    }
+
+    fn from_declarations(
+        name: &str, fields: &'a [ItemField], hash: Option<&Key<'a>>, range: Option<&Key<'a>>
+    ) -> Result<Index<'a>> {
+        let declared = |declarations: fn(&FieldOptions) -> &Vec<LitStr>| {
+            fields.iter()
+                .flat_map(|field| declarations(&field.options).iter()
+                    .filter(|declared| declared.value() == name)
+                    .map(move |declared| (field, declared)))
+                .collect::<Vec<IndexDeclaration<'a>>>()
+        };
+        let gsi_hash = declared(|options| &options.gsi_hash);
+        let gsi_range = declared(|options| &options.gsi_range);
+        let lsi_range = declared(|options| &options.lsi_range);
+
+        let is_global = !gsi_hash.is_empty() || !gsi_range.is_empty();
+        if is_global && !lsi_range.is_empty() {
+            let declarations = gsi_hash.iter().chain(&gsi_range).chain(&lsi_range);
+            return Err(error_at_declarations(declarations, &format!(
+                "`{}` is declared both as a global and a local secondary index", name
+            )));
+        }
+        let index = match (
+            single_declaration(lsi_range, name, "lsi_range")?,
+            single_declaration(gsi_hash, name, "gsi_hash")?,
+            single_declaration(gsi_range, name, "gsi_range")?
+        ) {
+            (Some((field, declared)), _, _) => match (hash, range) {
+                (Some(&Key::Field(hash)), Some(_)) => Index {
+                    name: declared, kind: IndexKind::Local, hash, range: Some(field)
+                },
+                (Some(&Key::Template(..)), _) => return Err(Error::new(declared.span(), format!(
+                    "the local secondary index `{}` shares the hash key of the table, which \
+                     can't be a key template", name
+                ))),
+                _ => return Err(Error::new(declared.span(), format!(
+                    "the local secondary index `{}` requires the table to have both a hash \
+                     and a range key, mark them with #[hash] and #[range]", name
+                ))),
+            },
+            (None, Some((field, declared)), range) => Index {
+                name: declared,
+                kind: IndexKind::Global,
+                hash: field,
+                range: range.map(|(field, _)| field),
+            },
+            (None, None, Some((_, declared))) => return Err(Error::new(declared.span(), format!(
+                "the global secondary index `{0}` has a range key but no hash key, mark its \
+                 hash key with #[korat(gsi_hash = \"{0}\")]", name
+            ))),
+            (None, None, None) => unreachable!("index names are taken from their declarations"),
+        };
+
+        match index.range {
+            Some(range) if range.name == index.hash.name => Err(Error::new_spanned(
+                &range.name, format!(
+                    "`{}` can't be both the hash and the range key of the index `{}`",
+                    range.attribute_name, name
+                )
+            )),
+            _ => Ok(index),
+        }
+    }
+
+    fn fields(&self) -> Vec<&'a ItemField> {
+        Some(self.hash).into_iter().chain(self.range).collect()
+    }
+
+    /// The index name as it appears in the name of its key struct, with the
+    /// words separated by underscores, dashes or dots capitalized, so that
+    /// the key struct of the `email-index` index of `User` is
+    /// `UserEmailIndexKey`.
+    fn struct_suffix(&self) -> String {
+        self.name.value()
+            .split(['_', '-', '.'])
+            .map(|word| {
+                let mut chars = word.chars();
+                chars.next()
+                    .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+/// Fails when an index option is set on more than one field for an index.
+fn single_declaration<'a>(
+    declarations: Vec<IndexDeclaration<'a>>, name: &str, option: &str
+) -> Result<Option<IndexDeclaration<'a>>> {
+    if declarations.len() > 1 {
+        return Err(error_at_declarations(&declarations, &format!(
+            "only one field can be the {} of the index `{}`", option, name
+        )));
+    }
+    Ok(declarations.into_iter().next())
+}
+
+fn error_at_declarations<'a, 'b: 'a, I>(declarations: I, message: &str) -> Error
+    where I: IntoIterator<Item = &'a IndexDeclaration<'b>> {
+    declarations.into_iter()
+        .map(|&(_, declared)| Error::new(declared.span(), message))
+        .reduce(|mut error, other| {
+            error.combine(other);
+            error
+        })
+        .expect("errors are reported for at least one declaration")
 }
 
 fn make_dynamodb_item(
-    vis: &Visibility, name: &Ident, fields: &[Field]
-) -> Tokens {
+    vis: &Visibility, name: &Ident, attrs: &[Attribute], fields: &[Field]
+) -> Result<TokenStream> {
+    let fields = collect(fields.iter().map(ItemField::from_field))?;
+    let options = StructOptions::from_attributes(attrs)?;
+    let keys = Keys::from_fields(&fields, &options)?;
 
-    let dynamodb_traits = get_dynamodb_traits(vis, name, fields);
-    let to_attribute_map = get_from_attribute_map_trait(name, fields);
-    let from_attribute_map = get_to_attribute_map_trait(name, fields);
+    let dynamodb_traits = get_dynamodb_traits(vis, name, &fields, &keys, options.key_struct);
+    let to_attribute_map = get_from_attribute_map_trait(name, &fields, &keys);
+    let from_attribute_map = get_to_attribute_map_trait(name);
+    let attribute_value_converter = get_attribute_value_converter_trait(name);
+    let encrypted_item = get_encrypted_item_trait(name, &fields, &keys)?;
+    let dynamodb_table = get_dynamodb_table_trait(name, options.table.as_ref(), &fields, &keys)?;
+    let field_handles = if options.key_struct {
+        quote!()
+    } else {
+        get_field_handles(vis, name, &fields, &keys)
+    };
 
-    quote! {
+    // The conversions for the rusoto and AWS SDK attribute maps depend on the
+    // features korat is built with, so korat expands them.
+    Ok(quote! {
         #from_attribute_map
         #to_attribute_map
         #dynamodb_traits
+        #attribute_value_converter
+        #encrypted_item
+        #dynamodb_table
+        #field_handles
+        ::korat::item_backend_conversions!(#name);
+    })
+}
+
+fn get_attribute_value_converter_trait(name: &Ident) -> TokenStream {
+    let attribute_value = quote!(::korat::AttributeValue);
+    let conversion_error = quote!(::korat::errors::ConversionError);
+
+    quote! {
+        impl ::korat::AttributeValueConverter for #name {
+            fn from_attribute_value(
+                attribute_value: #attribute_value
+            ) -> Result<Self, #conversion_error> {
+                ::korat::item_from_attribute_value(attribute_value)
+            }
+
+            fn to_attribute_value(self) -> #attribute_value {
+                ::korat::item_to_attribute_value(self)
+            }
+
+            fn try_to_attribute_value(self) -> Result<#attribute_value, #conversion_error> {
+                ::korat::item_try_to_attribute_value(self)
+            }
+        }
+
+        impl ::korat::ListElement for #name {}
     }
 }
 
-fn get_to_attribute_map_trait(name: &Ident, fields: &[Field]) -> Tokens {
+fn get_encrypted_item_trait(
+    name: &Ident, fields: &[ItemField], keys: &Keys
+) -> Result<TokenStream> {
+    let field_names = |matches: &dyn Fn(&FieldOptions) -> bool| -> Vec<&str> {
+        fields.iter()
+            .filter(|field| matches(&field.options))
+            .map(|field| field.attribute_name.as_str())
+            .collect()
+    };
+    let encrypted = field_names(&|options| options.encrypt);
+    let signed = field_names(&|options| options.sign && !options.encrypt);
+    if encrypted.is_empty() && signed.is_empty() {
+        return Ok(quote!());
+    }
+
+    if keys.hash.is_none() {
+        return Err(Error::new_spanned(name, format!(
+            "{} has encrypted or signed fields, which are bound to the item key; mark \
+             its key field with #[hash]", name
+        )));
+    }
+    let key_names: Vec<String> = keys.keys().into_iter().map(Key::attribute_name).collect();
+
+    Ok(quote! {
+        impl ::korat::encryption::EncryptedItem for #name {
+            fn key_attribute_names() -> Vec<String> {
+                vec![#(String::from(#key_names)),*]
+            }
+
+            fn encrypted_attribute_names() -> Vec<String> {
+                vec![#(String::from(#encrypted)),*]
+            }
+
+            fn signed_attribute_names() -> Vec<String> {
+                vec![#(String::from(#signed)),*]
+            }
+        }
+    })
+}
+
+/// Generates the `fields` function of the item, returning a struct with an
+/// `expr::Field` handle for each attribute stored from a field. The
+/// components of key templates aren't attributes of their own, so they have
+/// no handle.
+/// Encrypted fields get no handle, since their attributes hold ciphertexts
+/// which conditions and updates can't compare or write.
+fn get_field_handles(
+    vis: &Visibility, name: &Ident, fields: &[ItemField], keys: &Keys
+) -> TokenStream {
+    let struct_name = Ident::new(&format!("{}Fields", name.unraw()), Span::call_site());
+    let fields: Vec<&ItemField> = fields.iter()
+        .filter(|field| !keys.is_component(field) && !field.options.encrypt)
+        .collect();
+    let handle_fields = fields.iter().map(|field| {
+        let field_vis = &field.field.vis;
+        let field_name = &field.name;
+        let ty = &field.field.ty;
+        quote!(#field_vis #field_name: ::korat::expr::Field<#ty>)
+    });
+    let handles = fields.iter().map(|field| {
+        let field_name = &field.name;
+        let attribute_name = &field.attribute_name;
+        let to_attribute_value = field.options.encoder();
+        quote!(#field_name: ::korat::expr::Field::new(#attribute_name, #to_attribute_value))
+    });
+
+    quote! {
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #struct_name {
+            #(#handle_fields),*
+        }
+
+        impl #name {
+            #vis fn fields() -> #struct_name {
+                #struct_name {
+                    #(#handles),*
+                }
+            }
+        }
+    }
+}
+
+fn get_dynamodb_table_trait(
+    name: &Ident, table: Option<&TableOptions>, fields: &[ItemField], keys: &Keys
+) -> Result<TokenStream> {
+    let ttl_fields: Vec<&ItemField> = fields.iter().filter(|field| field.options.ttl).collect();
+    let table = match (table, ttl_fields.first()) {
+        (Some(table), _) => table,
+        (None, Some(field)) => return Err(Error::new_spanned(
+            &field.name, format!(
+                "#[korat(ttl)] sets the time to live attribute of the table of {}, declare \
+                 the table with #[korat(table = \"...\")] on the struct", name
+            )
+        )),
+        (None, None) => return Ok(quote!()),
+    };
+    if let Some(other) = ttl_fields.get(1) {
+        return Err(Error::new_spanned(
+            &other.name, "a table has a single time to live attribute, remove #[korat(ttl)] \
+                          from all but one field"
+        ));
+    }
+    let hash = keys.hash.as_ref().ok_or_else(|| Error::new(table.name.span(), format!(
+        "the table `{}` requires a hash key, mark it with #[hash]", table.name.value()
+    )))?;
+
+    let key_schema = get_key_schema(hash, keys.range.as_ref())?;
+    let indexes = collect(keys.indexes.iter().map(|index| {
+        let index_name = index.name.value();
+        let kind = match index.kind {
+            IndexKind::Global => quote!(::korat::table::IndexKind::Global),
+            IndexKind::Local => quote!(::korat::table::IndexKind::Local),
+        };
+        let range = index.range.map(Key::Field);
+        let key_schema = get_key_schema(&Key::Field(index.hash), range.as_ref())?;
+        Ok(quote! {
+            ::korat::table::SecondaryIndex {
+                name: String::from(#index_name),
+                kind: #kind,
+                key_schema: #key_schema,
+            }
+        })
+    }))?;
+    let billing_mode = match table.billing_mode {
+        BillingMode::PayPerRequest => quote!(::korat::table::BillingMode::PayPerRequest),
+        BillingMode::Provisioned { read_capacity, write_capacity } => quote! {
+            ::korat::table::BillingMode::Provisioned {
+                read_capacity_units: #read_capacity,
+                write_capacity_units: #write_capacity,
+            }
+        },
+    };
+    let ttl_attribute = match ttl_fields.first() {
+        Some(field) => {
+            let attribute_name = &field.attribute_name;
+            quote!(Some(String::from(#attribute_name)))
+        },
+        None => quote!(None),
+    };
+    let table_name = &table.name;
+
+    Ok(quote! {
+        impl ::korat::table::DynamoDBTable for #name {
+            fn table_definition() -> ::korat::table::TableDefinition {
+                ::korat::table::TableDefinition {
+                    name: String::from(#table_name),
+                    key_schema: #key_schema,
+                    indexes: vec![#(#indexes),*],
+                    billing_mode: #billing_mode,
+                    ttl_attribute: #ttl_attribute,
+                }
+            }
+        }
+    })
+}
+
+fn get_key_schema(hash: &Key, range: Option<&Key>) -> Result<TokenStream> {
+    let hash = get_key_attribute(hash)?;
+    let range = match range {
+        Some(range) => {
+            let range = get_key_attribute(range)?;
+            quote!(Some(#range))
+        },
+        None => quote!(None),
+    };
+    Ok(quote! {
+        ::korat::table::KeySchema {
+            hash: #hash,
+            range: #range,
+        }
+    })
+}
+
+/// Describes a key attribute for the table definition. Key templates are
+/// stored as strings.
+fn get_key_attribute(key: &Key) -> Result<TokenStream> {
+    let attribute_type = match *key {
+        Key::Field(field) => get_attribute_type(field)?,
+        Key::Template(..) => quote!(::korat::AttributeType::S),
+    };
+    let attribute_name = key.attribute_name();
+
+    Ok(quote! {
+        ::korat::table::KeyAttribute {
+            name: String::from(#attribute_name),
+            attribute_type: #attribute_type,
+        }
+    })
+}
+
+/// The type a key field is stored as, which follows from its converter
+/// options, or else from its type, except for converter modules and serde
+/// which need an explicit `attribute_type`.
+fn get_attribute_type(field: &ItemField) -> Result<TokenStream> {
+    let options = &field.options;
+    let attribute_type = |attribute_type: &str| {
+        let attribute_type = Ident::new(attribute_type, Span::call_site());
+        quote!(::korat::AttributeType::#attribute_type)
+    };
+    Ok(if let Some(ref declared) = options.attribute_type {
+        attribute_type(&declared.value())
+    } else if options.compress.is_some() {
+        attribute_type("B")
+    } else if options.json_string {
+        attribute_type("S")
+    } else if let Some(ref encoding) = options.timestamp {
+        attribute_type(if encoding == "rfc3339" { "S" } else { "N" })
+    } else if options.stores_field_type() {
+        let ty = &field.field.ty;
+        quote!(<#ty as ::korat::IndexKey>::ATTRIBUTE_TYPE)
+    } else {
+        return Err(Error::new_spanned(&field.name, format!(
+            "the type of the key attribute `{}` can't be told from its converter, set it \
+             with #[korat(attribute_type = \"S\")], \"N\" or \"B\"", field.attribute_name
+        )));
+    })
+}
+
+/// Builds the `KeyTemplate` constant of a template, with the components
+/// typed after their fields.
+fn get_key_template(template: &KeyTemplate, components: &[&ItemField]) -> TokenStream {
+    let mut components = components.iter();
+    let parts = template.parts.iter().map(|part| match *part {
+        TemplatePart::Literal(ref literal) => {
+            quote!(::korat::key_template::TemplatePart::Literal(#literal))
+        },
+        TemplatePart::Component(_) => {
+            let field = components.next().expect("components are resolved in template order");
+            let attribute_name = &field.attribute_name;
+            let attribute_type = get_attribute_type(field)
+                .expect("key components store their field type or a timestamp");
+            quote!(::korat::key_template::TemplatePart::Component(#attribute_name, #attribute_type))
+        },
+    });
+    let template = &template.template;
+    quote!(::korat::key_template::KeyTemplate::new(#template, &[#(#parts),*]))
+}
+
+/// The conversion into attribute maps goes through `try_into_attribute_map`,
+/// panicking on the fields which can't be stored.
+fn get_to_attribute_map_trait(name: &Ident) -> TokenStream {
     let attribute_map = quote!(
-        ::std::collections::HashMap<String, ::rusoto_dynamodb::AttributeValue>
+        ::std::collections::HashMap<String, ::korat::AttributeValue>
     );
     let from = quote!(::std::convert::From);
-    let to_attribute_map = get_to_attribute_map_function(name, fields);
 
     quote! {
         impl #from<#name> for #attribute_map {
-            #to_attribute_map
+            fn from(item: #name) -> Self {
+                ::korat::DynamoDBItem::try_into_attribute_map(item).unwrap_or_else(
+                    |error| panic!("the item can't be stored as an attribute map: {}", error)
+                )
+            }
         }
     }
 }
 
-fn get_to_attribute_map_function(name: &Ident, fields: &[Field]) -> Tokens {
-    let to_attribute_value = quote!(
-        ::korat::AttributeValueConverter::to_attribute_value
-    );
-
-    let field_conversions = fields.iter().map(|field| {
-        let field_name = &field.ident;
+/// The components of key templates are only written into their keys, which
+/// are inserted before the fields are moved out of the item.
+fn get_to_attribute_map_function(fields: &[ItemField], keys: &Keys) -> TokenStream {
+    let template_conversions = keys.keys().into_iter()
+        .filter(|key| matches!(key, Key::Template(..)))
+        .map(|key| {
+            let attribute_name = key.attribute_name();
+            let to_attribute_value = key.to_attribute_value(cloned_field(quote!(item)));
+            quote! {
+                values.insert(#attribute_name.to_string(), #to_attribute_value);
+            }
+        });
+    let field_conversions = fields.iter().filter(|field| !keys.is_component(field)).map(|field| {
+        let field_name = &field.name;
+        let attribute_name = &field.attribute_name;
+        let to_attribute_value = field.options.try_encode(quote!(item.#field_name));
         quote! {
-            values.insert(
-                stringify!(#field_name).to_string(),
-                #to_attribute_value(item.#field_name)
-            );
+            values.insert(#attribute_name.to_string(), #to_attribute_value?);
         }
     });
+    let attribute_map = quote!(
+        ::std::collections::HashMap<String, ::korat::AttributeValue>
+    );
+    let conversion_error = quote!(::korat::errors::ConversionError);
 
     quote! {
-        fn from(item: #name) -> Self {
-            let mut values = Self::new();
+        fn try_into_attribute_map(self) -> Result<#attribute_map, #conversion_error> {
+            let item = self;
+            let mut values = <#attribute_map>::new();
+            #(#template_conversions)*
             #(#field_conversions)*
-            values
+            Ok(values)
         }
     }
 }
 
-fn get_from_attribute_map_trait(name: &Ident, fields: &[Field]) -> Tokens {
+fn get_from_attribute_map_trait(name: &Ident, fields: &[ItemField], keys: &Keys) -> TokenStream {
     let attribute_map = quote!(
-        ::std::collections::HashMap<String, ::rusoto_dynamodb::AttributeValue>
+        ::std::collections::HashMap<String, ::korat::AttributeValue>
     );
-    let conversion_error = quote!(::korat::errors::ConversionError); 
+    let conversion_error = quote!(::korat::errors::ConversionError);
     let try_from = quote!(::std::convert::TryFrom);
-    let from_attribute_map = get_from_attribute_map_function(fields);
+    let from_attribute_map = get_from_attribute_map_function(fields, keys);
 
     quote! {
         impl #try_from<#attribute_map> for #name {
@@ -82,20 +804,50 @@ fn get_from_attribute_map_trait(name: &Ident, fields: &[Field]) -> Tokens {
     }
 }
 
-fn get_from_attribute_map_function(fields: &[Field]) -> Tokens {
+/// Key templates are parsed first, into the attribute values of their
+/// components which are then converted like the other fields.
+fn get_from_attribute_map_function(fields: &[ItemField], keys: &Keys) -> TokenStream {
     let attribute_map = quote!(
-        ::std::collections::HashMap<String, ::rusoto_dynamodb::AttributeValue>
-    );
-    let from_attribute_value = quote!(
-        ::korat::AttributeValueConverter::from_attribute_value
+        ::std::collections::HashMap<String, ::korat::AttributeValue>
     );
-    let conversion_error = quote!(::korat::errors::ConversionError); 
+    let conversion_error = quote!(::korat::errors::ConversionError);
 
+    let template_parsers: Vec<TokenStream> = keys.keys().into_iter()
+        .filter_map(|key| match *key {
+            Key::Template(template, ref components) => Some((key, template, components)),
+            Key::Field(_) => None,
+        })
+        .map(|(key, template, components)| {
+            let attribute_name = key.attribute_name();
+            let key_template = get_key_template(template, components);
+            quote! {
+                const TEMPLATE: ::korat::key_template::KeyTemplate = #key_template;
+                components.extend(TEMPLATE.parse(
+                    item.remove(#attribute_name).ok_or(#conversion_error::MissingField)?
+                )?);
+            }
+        })
+        .collect();
+    let components = if template_parsers.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            let mut components = ::std::collections::HashMap::new();
+            #({ #template_parsers })*
+        }
+    };
     let field_conversions = fields.iter().map(|field| {
-        let field_name = &field.ident;
+        let field_name = &field.name;
+        let attribute_name = &field.attribute_name;
+        let from_attribute_value = field.options.decoder();
+        let attributes = if keys.is_component(field) {
+            quote!(components)
+        } else {
+            quote!(item)
+        };
         quote! {
             #field_name: #from_attribute_value(
-                item.remove(stringify!(#field_name))
+                #attributes.remove(#attribute_name)
                     .ok_or(#conversion_error::MissingField)?
             )?
         }
@@ -103,6 +855,7 @@ fn get_from_attribute_map_function(fields: &[Field]) -> Tokens {
 
     quote! {
         fn try_from(mut item: #attribute_map) -> Result<Self, Self::Error> {
+            #components
             Ok(Self {
                 #(#field_conversions),*
             })
@@ -111,10 +864,14 @@ fn get_from_attribute_map_function(fields: &[Field]) -> Tokens {
 }
 
 fn get_dynamodb_traits(
-    vis: &Visibility, name: &Ident, fields: &[Field]
-) -> Tokens {
-    let dynamodb_item_trait = get_dynamodb_item_trait(name, fields);
-    let dynamodb_insertables = get_dynamodb_insertables(vis, name, fields);
+    vis: &Visibility, name: &Ident, fields: &[ItemField], keys: &Keys, is_key_struct: bool
+) -> TokenStream {
+    let dynamodb_item_trait = get_dynamodb_item_trait(name, fields, keys);
+    let dynamodb_insertables = if is_key_struct {
+        quote!()
+    } else {
+        get_dynamodb_insertables(vis, name, keys)
+    };
 
     quote! {
         #dynamodb_item_trait
@@ -122,111 +879,286 @@ fn get_dynamodb_traits(
     }
 }
 
-fn get_dynamodb_item_trait(name: &Ident, fields: &[Field]) -> Tokens {
+fn get_dynamodb_item_trait(name: &Ident, fields: &[ItemField], keys: &Keys) -> TokenStream {
     let dynamodb_item = quote!(::korat::DynamoDBItem);
-    let field_names: Vec<String> = fields.iter().cloned()
-        .map(|f| f.ident.expect("DynamoDBItem fields should have identifiers")
-             .to_string())
-        .collect();
+    let template_names = keys.keys().into_iter()
+        .filter(|key| matches!(key, Key::Template(..)))
+        .map(Key::attribute_name);
+    let field_names = fields.iter()
+        .filter(|field| !keys.is_component(field))
+        .map(|field| field.attribute_name.clone());
+    let attribute_names = template_names.chain(field_names);
+    let to_attribute_map = get_to_attribute_map_function(fields, keys);
 
     quote!{
         impl #dynamodb_item for #name {
             fn get_attribute_names() -> Vec<String> {
-                vec![#(String::from(#field_names)),*]
+                vec![#(String::from(#attribute_names)),*]
             }
+
+            #to_attribute_map
         }
     }
 }
 
 fn get_dynamodb_insertables(
-    vis: &Visibility, name: &Ident, fields: &[Field]
-) -> Tokens {
-    let dynamodb_insertable_trait = get_dynamodb_insertable_trait(name, fields);
-    let dynamodb_key_struct = get_dynamodb_key_struct(vis, name, fields);
+    vis: &Visibility, name: &Ident, keys: &Keys
+) -> TokenStream {
+    let dynamodb_insertable_trait = get_dynamodb_insertable_trait(name, keys);
+    let key_type_assertions = get_key_type_assertions(keys);
+    let dynamodb_key_struct = get_dynamodb_key_struct(vis, name, keys);
+    let index_key_structs = keys.indexes.iter().map(|index| {
+        let struct_name = format!("{}{}Key", name.unraw(), index.struct_suffix());
+        let fields = index.fields().into_iter().map(|field| (field, quote!())).collect();
+        let range = index.range.map(Key::Field);
+        let partial = get_partial_key_function(
+            vis, Some(index.name), &Key::Field(index.hash), range.as_ref()
+        );
+        get_key_struct(vis, &struct_name, quote!(), fields, partial)
+    });
 
     quote! {
         #dynamodb_insertable_trait
+        #(#key_type_assertions)*
         #dynamodb_key_struct
+        #(#index_key_structs)*
     }
 }
 
-fn get_dynamodb_insertable_trait(name: &Ident, fields: &[Field]) -> Tokens {
+fn get_dynamodb_insertable_trait(name: &Ident, keys: &Keys) -> TokenStream {
     let dynamodb_insertable = quote!(::korat::DynamoDBInsertable);
     let attribute_map = quote!(
-        ::std::collections::HashMap<String, ::rusoto_dynamodb::AttributeValue>
+        ::std::collections::HashMap<String, ::korat::AttributeValue>
     );
-    let hash_key_name = get_field_name_with_attribute(&fields, "hash");
-    let range_key_name = get_field_name_with_attribute(&fields, "range");
-
-    let hash_key_inserter = get_key_inserter(&hash_key_name);
-    let range_key_inserter = get_key_inserter(&range_key_name);
+    let key_inserters = keys.keys().into_iter().map(get_key_inserter);
+    let get_index_key = get_index_key_function(keys);
 
-    hash_key_name.map(|_| quote!{
+    keys.hash.as_ref().map(|_| quote!{
         impl #dynamodb_insertable for #name {
             fn get_key(&self) -> #attribute_map {
                 let mut keys = ::std::collections::HashMap::new();
-                #hash_key_inserter
-                #range_key_inserter
+                #(#key_inserters)*
                 keys
             }
+
+            #get_index_key
         }
-    }).unwrap_or(quote!{})
+    }).unwrap_or_default()
 }
 
-fn get_field_name_with_attribute(
-    fields: &[Field], attribute_name: &str
-) -> Option<Ident> {
-    get_field_with_attribute(fields, attribute_name)
-        .map(|field| field.ident.expect(
-            &format!("{} should have an identifier", attribute_name)
-        ))
+fn get_index_key_function(keys: &Keys) -> TokenStream {
+    if keys.indexes.is_empty() {
+        return quote!();
+    }
+    let attribute_map = quote!(
+        ::std::collections::HashMap<String, ::korat::AttributeValue>
+    );
+    let index_keys = keys.indexes.iter().map(|index| {
+        let name = index.name.value();
+        let key_inserters = index.fields().into_iter()
+            .map(|field| get_key_inserter(&Key::Field(field)));
+        quote! {
+            #name => {
+                #(#key_inserters)*
+            }
+        }
+    });
+
+    quote! {
+        fn get_index_key(&self, index: &str) -> Option<#attribute_map> {
+            let mut keys = ::std::collections::HashMap::new();
+            match index {
+                #(#index_keys),*
+                _ => return None,
+            }
+            ::korat::index_key(keys)
+        }
+    }
 }
 
-fn get_field_with_attribute(
-    fields: &[Field], attribute_name: &str
-) -> Option<Field> {
-    let mut fields = fields.iter().cloned().filter(
-        |field| field.attrs.iter().any(|attr| attr.name() == attribute_name)
-    );
+fn get_key_field<'a, F>(
+    fields: &'a [ItemField], key: &str, is_key: F
+) -> Result<Option<&'a ItemField>>
+    where F: Fn(&FieldOptions) -> bool {
+    let mut fields = fields.iter().filter(|field| is_key(&field.options));
 
     let field = fields.next();
-    if let Some(_) = fields.next() {
-        panic!("Can't set more than one {} key", attribute_name);
+    let others: Vec<&ItemField> = fields.collect();
+    match (field, others.is_empty()) {
+        (Some(field), false) => {
+            let mut error = Error::new_spanned(&field.name, format!(
+                "can't set more than one {} key; remove the attribute from all but one field",
+                key
+            ));
+            for other in others {
+                error.combine(Error::new_spanned(&other.name, format!(
+                    "`{}` is also marked as the {} key", other.attribute_name, key
+                )));
+            }
+            Err(error)
+        },
+        _ => Ok(field),
     }
-    field
 }
 
-fn get_key_inserter(field_name: &Option<Ident>) -> Tokens {    
-    let to_attribute_value = quote!(
-        ::korat::AttributeValueConverter::to_attribute_value
-    );
-    field_name.as_ref().map(|field_name| quote!{
-        keys.insert(
-            stringify!(#field_name).to_string(),
-            #to_attribute_value(self.#field_name.clone())
-        );
-    }).unwrap_or(quote!())
+/// Asserts that the key fields are stored as scalar attributes, which may be
+/// optional for the keys of secondary indexes, and that the components of
+/// key templates are stored as strings or numbers. Fields going through
+/// converter modules or serde are skipped, as their stored type can't be told
+/// from the field type.
+fn get_key_type_assertions(keys: &Keys) -> Vec<TokenStream> {
+    let table_keys = keys.keys().into_iter().flat_map(|key| {
+        let assertion = match *key {
+            Key::Field(_) => quote!(::korat::assert_scalar_key),
+            Key::Template(..) => quote!(::korat::assert_key_component),
+        };
+        key.fields().into_iter().map(move |field| (field, assertion.clone()))
+    });
+    let table_fields = keys.fields();
+    let index_keys = keys.indexes.iter()
+        .flat_map(Index::fields)
+        .filter(|field| !table_fields.iter().any(|key| key.name == field.name))
+        .map(|field| (field, quote!(::korat::assert_index_key)));
+    table_keys
+        .chain(index_keys)
+        .filter(|(field, _)| field.options.stores_field_type())
+        .map(|(field, assertion)| {
+            let ty = &field.field.ty;
+            quote_spanned! {ty.span()=>
+                const _: () = #assertion::<#ty>();
+            }
+        })
+        .collect()
 }
 
+fn get_key_inserter(key: &Key) -> TokenStream {
+    let attribute_name = key.attribute_name();
+    let to_attribute_value = key.to_attribute_value(cloned_field(quote!(self)));
+    quote!{
+        keys.insert(#attribute_name.to_string(), #to_attribute_value);
+    }
+}
+
+/// The key struct of an item declares the same keys as the item, and is
+/// marked as a key struct so that it doesn't get a key struct of its own.
 fn get_dynamodb_key_struct(
-    vis: &Visibility, name: &Ident, fields: &[Field]
-) -> Tokens {
-    let name = Ident::from(format!("{}Key", name));
-
-    let hash_key = get_field_with_attribute(&fields, "hash");
-    let range_key = get_field_with_attribute(&fields, "range")
-        .map(|mut range_key| {
-            range_key.attrs = vec![];
-            quote! {#range_key}
-        }).unwrap_or(quote!());
-
-    hash_key.map(|mut hash_key| {
-        hash_key.attrs = vec![];
-        quote!{
-            #[derive(DynamoDBItem, Debug, Clone, PartialEq)]
-            #vis struct #name {
-                #hash_key,
-                #range_key
-            }
-        }}).unwrap_or(quote!())
+    vis: &Visibility, name: &Ident, keys: &Keys
+) -> TokenStream {
+    let hash = match keys.hash {
+        Some(ref hash) => hash,
+        None => return quote!(),
+    };
+    let templates = [("hash", &keys.hash), ("range", &keys.range)].iter()
+        .filter_map(|&(key, declaration)| match *declaration {
+            Some(Key::Template(template, _)) => {
+                let option = Ident::new(key, Span::call_site());
+                let attribute_option = Ident::new(&format!("{}_attribute", key), Span::call_site());
+                let (template, attribute_name) = (&template.template, &template.attribute_name);
+                Some(quote!(#[korat(#option = #template, #attribute_option = #attribute_name)]))
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let struct_attributes = quote! {
+        #[korat(key_struct)]
+        #(#templates)*
+    };
+    let fields = keys.fields().into_iter()
+        .map(|field| {
+            let marker = match (&keys.hash, &keys.range) {
+                (Some(Key::Field(hash)), _) if hash.name == field.name => quote!(#[hash]),
+                (_, Some(Key::Field(range))) if range.name == field.name => quote!(#[range]),
+                _ => quote!(),
+            };
+            (field, marker)
+        })
+        .collect();
+    let partial = get_partial_key_function(vis, None, hash, keys.range.as_ref());
+    get_key_struct(vis, &format!("{}Key", name.unraw()), struct_attributes, fields, partial)
+}
+
+/// Generates the `partial` constructor of a key struct, which takes the hash
+/// key fields and returns a `PartialKey` typed after the range key. The
+/// range key of a template is the key string.
+fn get_partial_key_function(
+    vis: &Visibility, index_name: Option<&LitStr>, hash: &Key, range: Option<&Key>
+) -> TokenStream {
+    let hash_fields = hash.fields();
+    let field_names = hash_fields.iter().map(|field| &field.name);
+    let types = hash_fields.iter().map(|field| &field.field.ty);
+    let hash_name = hash.attribute_name();
+    let hash_value = hash.to_attribute_value(|field| {
+        let field_name = &field.name;
+        quote!(#field_name)
+    });
+    let (range_type, range) = match range {
+        Some(&Key::Field(field)) => {
+            let ty = &field.field.ty;
+            let range_name = &field.attribute_name;
+            let to_attribute_value = field.options.encoder();
+            (quote!(#ty), quote!(Some((#range_name, #to_attribute_value))))
+        },
+        Some(key @ &Key::Template(..)) => {
+            let range_name = key.attribute_name();
+            (quote!(String), quote!(Some((
+                #range_name,
+                <String as ::korat::AttributeValueConverter>::to_attribute_value
+            ))))
+        },
+        None => (quote!(::korat::query::NoRange), quote!(None)),
+    };
+    let index_name = match index_name {
+        Some(index_name) => quote!(Some(#index_name)),
+        None => quote!(None),
+    };
+
+    quote! {
+        #vis fn partial(
+            #(#field_names: #types),*
+        ) -> ::korat::query::PartialKey<#range_type> {
+            ::korat::query::PartialKey::new(
+                #index_name, (#hash_name, #hash_value), #range
+            )
+        }
+    }
+}
+
+/// Generates a key struct holding the given fields, along with a constructor
+/// taking them in order and the `partial` constructor.
+fn get_key_struct(
+    vis: &Visibility, name: &str, struct_attributes: TokenStream,
+    fields: Vec<(&ItemField, TokenStream)>, partial: TokenStream
+) -> TokenStream {
+    let name = Ident::new(name, Span::call_site());
+
+    let key_fields = fields.iter().map(|&(field, ref marker)| {
+        let attributes = key_struct_attributes(&field.field);
+        let field_vis = &field.field.vis;
+        let field_name = &field.name;
+        let ty = &field.field.ty;
+        quote! {
+            #marker
+            #(#attributes)*
+            #field_vis #field_name: #ty
+        }
+    });
+    let field_names: Vec<&Ident> = fields.iter().map(|&(field, _)| &field.name).collect();
+    let types = fields.iter().map(|&(field, _)| &field.field.ty);
+
+    quote!{
+        #[derive(DynamoDBItem, Debug, Clone, PartialEq)]
+        #struct_attributes
+        #vis struct #name {
+            #(#key_fields),*
+        }
+
+        impl #name {
+            #[allow(clippy::too_many_arguments, clippy::new_without_default)]
+            #vis fn new(#(#field_names: #types),*) -> Self {
+                #name { #(#field_names),* }
+            }
+
+            #partial
+        }
+    }
 }
@@ -0,0 +1,92 @@
+//! Helpers for reporting errors in the input of the derive. Errors are
+//! `syn::Error`s pointing at the offending tokens, reported as
+//! `compile_error!` invocations rather than panics so that all of them show
+//! up at once as regular compiler errors.
+
+use proc_macro2::TokenStream;
+use syn::{Error, Result};
+
+
+/// Reports each of the messages of an error as a `compile_error!` at its
+/// span. Unlike `Error::to_compile_error`, the generated invocations don't
+/// refer to `::core`, which doesn't resolve in 2015 edition crates.
+pub fn to_compile_error(error: Error) -> TokenStream {
+    let errors = error.into_iter().map(|error| {
+        let message = error.to_string();
+        quote_spanned!(error.span()=> compile_error!(#message);)
+    });
+    quote!(#(#errors)*)
+}
+
+/// Collects the results of a sequence, combining all the errors rather than
+/// stopping at the first one.
+pub fn collect<T, I: IntoIterator<Item = Result<T>>>(results: I) -> Result<Vec<T>> {
+    let mut values = vec![];
+    let mut error: Option<Error> = None;
+    for result in results {
+        match (result, error.as_mut()) {
+            (Ok(value), _) => values.push(value),
+            (Err(other), Some(error)) => error.combine(other),
+            (Err(other), None) => error = Some(other),
+        }
+    }
+    match error {
+        Some(error) => Err(error),
+        None => Ok(values),
+    }
+}
+
+/// Suggests the closest of the valid names for a misspelled one.
+pub fn suggestion(name: &str, valid: &[&str]) -> String {
+    let closest = valid.iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .min();
+    match closest {
+        Some((distance, candidate)) if distance <= 2 => {
+            format!("; did you mean `{}`?", candidate)
+        },
+        _ => String::new(),
+    }
+}
+
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous: Vec<usize> = (0..second.len() + 1).collect();
+    for (i, first_char) in first.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &second_char) in second.iter().enumerate() {
+            let substitution = previous[j] + if first_char == second_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[second.len()]
+}
+
+
+#[cfg(test)]
+mod test {
+    use proc_macro2::Span;
+    use syn::Error;
+
+    use super::{collect, suggestion};
+
+    #[test]
+    fn suggests_close_names() {
+        let valid = ["compress", "coerce", "serde"];
+        assert_eq!("; did you mean `compress`?", suggestion("compres", &valid));
+        assert_eq!("; did you mean `serde`?", suggestion("Serde", &valid));
+        assert_eq!("", suggestion("encrypt", &valid));
+    }
+
+    #[test]
+    fn collects_all_errors() {
+        let error = |message| Err(Error::new(Span::call_site(), message));
+        let results = vec![Ok(1), error("first"), Ok(2), error("second")];
+        let messages: Vec<String> = collect(results).unwrap_err().into_iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(vec!["first", "second"], messages);
+        assert_eq!(vec![1, 2], collect(vec![Ok(1), Ok(2)]).unwrap());
+    }
+}
@@ -1,21 +1,28 @@
-//! Korat provides rusoto implementations for using an structs as dynamodb items
+//! Korat provides implementations for using structs as dynamodb items, with the
+//! rusoto and AWS SDK clients
 
 #[macro_use] extern crate quote;
 
 extern crate proc_macro;
+extern crate proc_macro2;
 extern crate syn;
 
+mod attributes;
 mod dynamodb_item;
+mod error;
 
 use proc_macro::TokenStream;
+use syn::DeriveInput;
 
 use dynamodb_item::expand;
+use error::to_compile_error;
 
 
-#[proc_macro_derive(DynamoDBItem, attributes(hash, range))]
+#[proc_macro_derive(DynamoDBItem, attributes(hash, range, korat))]
 pub fn dynamodb_item(input: TokenStream) -> TokenStream {
-    let s = input.to_string();
-    let ast = syn::parse_macro_input(&s).unwrap();
-    let gen = expand(&ast);
-    gen.parse().unwrap()
+    let expanded = match syn::parse::<DeriveInput>(input) {
+        Ok(ast) => expand(&ast),
+        Err(error) => to_compile_error(error),
+    };
+    expanded.into()
 }
@@ -0,0 +1,68 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+extern crate aws_sdk_dynamodb;
+extern crate rusoto_dynamodb;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Track {
+    #[hash] album: String,
+    #[range] number: u32,
+    title: String,
+    cover: Vec<u8>,
+    featuring: Option<String>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use aws_sdk_dynamodb::types::AttributeValue as SdkAttributeValue;
+    use korat::DynamoDBInsertable;
+    use korat::backend::{AwsSdk, Rusoto, into_attribute_map};
+    use rusoto_dynamodb::AttributeValue as RusotoAttributeValue;
+
+    use super::{Track, TrackKey};
+
+    fn track() -> Track {
+        Track {
+            album: "blue".to_string(),
+            number: 3,
+            title: "korat".to_string(),
+            cover: vec![1, 2, 3],
+            featuring: None,
+        }
+    }
+
+    #[test]
+    fn converts_items_for_rusoto() {
+        let item = track();
+        let attributes: HashMap<String, RusotoAttributeValue> = item.clone().into();
+        assert_eq!(Some("3".to_string()), attributes["number"].n);
+        assert_eq!(&[1u8, 2, 3][..], &attributes["cover"].b.as_ref().unwrap()[..]);
+        assert_eq!(item, Track::try_from(attributes).unwrap());
+    }
+
+    #[test]
+    fn converts_items_for_the_aws_sdk() {
+        let item = track();
+        let attributes: HashMap<String, SdkAttributeValue> = item.clone().into();
+        assert_eq!(SdkAttributeValue::N("3".to_string()), attributes["number"]);
+        assert_eq!(SdkAttributeValue::Null(true), attributes["featuring"]);
+        assert_eq!(item, Track::try_from(attributes).unwrap());
+    }
+
+    #[test]
+    fn converts_keys_for_both_backends() {
+        let item = track();
+        let key = TrackKey { album: "blue".to_string(), number: 3 };
+
+        let rusoto_key: HashMap<String, RusotoAttributeValue> = key.clone().into();
+        assert_eq!(rusoto_key, into_attribute_map::<Rusoto>(item.get_key()));
+
+        let sdk_key: HashMap<String, SdkAttributeValue> = key.into();
+        assert_eq!(sdk_key, into_attribute_map::<AwsSdk>(item.get_key()));
+    }
+}
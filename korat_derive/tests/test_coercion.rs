@@ -0,0 +1,69 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct LegacyItem {
+    #[korat(coerce)] count: i32,
+    #[korat(coerce)] enabled: bool,
+    #[korat(coerce)] label: Option<String>,
+    strict_count: i32
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+    use std::default::Default;
+
+    use korat::AttributeValue;
+
+    use korat::coercion;
+
+    use super::LegacyItem;
+
+    macro_rules! insert {
+        ($attrs:ident, $name:expr, $field:ident, $value:expr) => {
+            $attrs.insert(String::from($name), AttributeValue {
+                $field: Some($value),
+                .. AttributeValue::default()
+            });
+        }
+    }
+
+    fn legacy_attributes() -> HashMap<String, AttributeValue> {
+        let mut attributes = HashMap::new();
+        insert!(attributes, "count", s, String::from("12"));
+        insert!(attributes, "enabled", s, String::from("true"));
+        insert!(attributes, "label", n, String::from("3"));
+        attributes
+    }
+
+    // Both scenarios live in a single test as the lenient decoding switch is
+    // global to the process.
+    #[test]
+    fn coerces_fields_and_globally_when_enabled() {
+        let mut attributes = legacy_attributes();
+        insert!(attributes, "strict_count", n, String::from("4"));
+        let item = LegacyItem::try_from(attributes).unwrap();
+        assert_eq!(LegacyItem {
+            count: 12,
+            enabled: true,
+            label: Some(String::from("3")),
+            strict_count: 4
+        }, item);
+
+        let mut attributes = legacy_attributes();
+        insert!(attributes, "strict_count", s, String::from("4"));
+        assert!(LegacyItem::try_from(attributes).is_err());
+
+        coercion::set_lenient_decoding(true);
+        let mut attributes = legacy_attributes();
+        insert!(attributes, "strict_count", s, String::from("4"));
+        let item = LegacyItem::try_from(attributes);
+        coercion::set_lenient_decoding(false);
+        assert_eq!(4, item.unwrap().strict_count);
+    }
+}
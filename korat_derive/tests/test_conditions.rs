@@ -0,0 +1,83 @@
+#[macro_use] extern crate korat_derive;
+#[macro_use] extern crate serde_derive;
+extern crate korat;
+extern crate rusoto_dynamodb;
+
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+enum Status {
+    Pending,
+    Paid,
+}
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Order {
+    #[hash] id: String,
+    #[korat(serde)] status: Status,
+    total: f64,
+    tags: HashSet<String>,
+    #[korat(timestamp = "epoch_seconds")] placed: SystemTime,
+}
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[korat(hash = "USER#{user_id}")]
+struct Session {
+    user_id: String,
+    device: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use korat::AttributeValueConverter;
+    use korat::backend::Rusoto;
+    use korat::expr::attribute_not_exists;
+
+    use super::{Order, Session, Status};
+
+    #[test]
+    fn converts_values_with_the_field_converters() {
+        let fields = Order::fields();
+        let condition = fields.status.eq(Status::Paid)
+            .and(fields.placed.lt(UNIX_EPOCH + Duration::from_secs(60)))
+            .build();
+        assert_eq!("#n0 = :v0 AND #n1 < :v1", condition.expression);
+        assert_eq!("status", condition.names["#n0"]);
+        assert_eq!(Some("Paid".to_string()), condition.values[":v0"].s);
+        assert_eq!(Some("60".to_string()), condition.values[":v1"].n);
+
+        let values = condition.expression_attribute_values::<Rusoto>().unwrap();
+        assert_eq!(Some("60".to_string()), values[":v1"].n);
+    }
+
+    #[test]
+    fn builds_functions_and_sets_of_values() {
+        let fields = Order::fields();
+        let condition = fields.id.exists()
+            .and(fields.status.is_in(vec![Status::Pending, Status::Paid]))
+            .and(!fields.tags.contains("archived".to_string()))
+            .and(fields.tags.size().le(5).or(fields.total.between(10.0, 20.0)))
+            .build();
+        assert_eq!(
+            "attribute_exists(#n0) AND #n1 IN (:v0, :v1) AND NOT contains(#n2, :v2) \
+             AND (size(#n2) <= :v3 OR #n3 BETWEEN :v4 AND :v5)",
+            condition.expression
+        );
+        assert_eq!("archived".to_string().to_attribute_value(), condition.values[":v2"]);
+        assert_eq!(4, condition.names.len());
+    }
+
+    #[test]
+    fn leaves_key_components_to_their_key() {
+        let fields = Session::fields();
+        let condition = attribute_not_exists("PK").and(fields.device.ne("tv".to_string())).build();
+        assert_eq!("attribute_not_exists(#n0) AND #n1 <> :v0", condition.expression);
+        assert_eq!("PK", condition.names["#n0"]);
+        assert_eq!("device", condition.names["#n1"]);
+    }
+}
@@ -1,12 +1,10 @@
-#![feature(try_from)]
-
 #[macro_use] extern crate korat_derive;
-extern crate korat;
-
-extern crate rusoto_dynamodb;
+#[macro_use] extern crate korat;
 
 
 use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 
 #[derive(DynamoDBItem, PartialEq, Debug, Clone)]
@@ -37,6 +35,79 @@ struct ItemWithHashAndRange {
 }
 
 
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithDynamicValue {
+    #[hash] hash: String,
+    metadata: korat::Value
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithTimestamps {
+    #[hash] #[korat(timestamp = "epoch_millis")] created: SystemTime,
+    updated: SystemTime,
+    #[korat(timestamp = "epoch_seconds")] expires: Option<SystemTime>
+}
+
+
+mod reversed {
+    use korat::AttributeValue;
+
+    use korat::AttributeValueConverter;
+    use korat::errors::ConversionError;
+
+    pub fn to_attribute_value(value: String) -> AttributeValue {
+        value.chars().rev().collect::<String>().to_attribute_value()
+    }
+
+    pub fn from_attribute_value(
+        attribute_value: AttributeValue
+    ) -> Result<String, ConversionError> {
+        String::from_attribute_value(attribute_value)
+            .map(|value| value.chars().rev().collect())
+    }
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithCustomConverter {
+    #[korat(with = "reversed")] name: String
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[allow(clippy::vec_box)]
+struct Comment {
+    text: String,
+    replies: Vec<Box<Comment>>
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithSharedConfig {
+    #[hash] hash: String,
+    config: Arc<SingleFieldItem>,
+    parent: Option<Box<Comment>>
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithBinaryKeys {
+    #[hash] partition: Box<u32>,
+    #[range] #[korat(compress = "gzip")] sort: Vec<u8>,
+    #[korat(with = "reversed")] name: String
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithRawIdentifiers {
+    #[korat(hash)] r#type: String,
+    #[korat(range, coerce)] r#match: u32,
+    /// Documented fields and other attributes are left alone.
+    #[allow(dead_code)] r#ref: Option<String>
+}
+
+
 mod internal {
     #[derive(DynamoDBItem, PartialEq, Debug, Clone)]
     pub struct InternalItem {
@@ -47,20 +118,75 @@ mod internal {
 }
 
 
+mod manual {
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use korat::{AttributeValue, AttributeValueConverter, DynamoDBItem};
+    use korat::errors::ConversionError;
+
+    /// An item implementing `DynamoDBItem` by hand, which opts in to being nested.
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Address {
+        pub city: String
+    }
+
+    impl DynamoDBItem for Address {
+        fn get_attribute_names() -> Vec<String> {
+            vec![String::from("city")]
+        }
+    }
+
+    impl TryFrom<HashMap<String, AttributeValue>> for Address {
+        type Error = ConversionError;
+
+        fn try_from(
+            mut attribute_map: HashMap<String, AttributeValue>
+        ) -> Result<Self, Self::Error> {
+            let city = attribute_map.remove("city").ok_or(ConversionError::MissingField)?;
+            Ok(Address { city: String::from_attribute_value(city)? })
+        }
+    }
+
+    impl From<Address> for HashMap<String, AttributeValue> {
+        fn from(address: Address) -> Self {
+            let mut attribute_map = HashMap::new();
+            attribute_map.insert(String::from("city"), address.city.to_attribute_value());
+            attribute_map
+        }
+    }
+
+    item_converter!(Address);
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct ItemWithManualItems {
+    #[hash] hash: String,
+    address: manual::Address,
+    previous: Vec<manual::Address>
+}
+
+
 #[cfg(test)]
 mod tests {
 
     use std::collections::{HashSet, HashMap};
     use std::convert::TryFrom;
     use std::default::Default;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
 
-    use rusoto_dynamodb::AttributeValue;
+    use korat::AttributeValue;
 
     use korat::{DynamoDBInsertable, DynamoDBItem};
 
     use super::{
         ItemWithAllTypes, SingleFieldItem,
-        ItemWithHashAndRange, ItemWithHashAndRangeKey
+        ItemWithHashAndRange, ItemWithHashAndRangeKey, ItemWithDynamicValue,
+        ItemWithTimestamps, ItemWithTimestampsKey, ItemWithCustomConverter,
+        Comment, ItemWithSharedConfig, ItemWithBinaryKeys, ItemWithRawIdentifiers,
+        ItemWithRawIdentifiersKey, ItemWithManualItems, manual
     };
 
     macro_rules! insert {
@@ -146,7 +272,7 @@ mod tests {
 
     #[test]
     fn fails_to_deserialize_invalid_input() {
-        let attributes = HashMap::new();
+        let attributes: HashMap<String, AttributeValue> = HashMap::new();
         let res = ItemWithAllTypes::try_from(attributes);
         assert!(res.is_err())
     }
@@ -220,6 +346,91 @@ mod tests {
         
     }
 
+    #[test]
+    fn can_deserialize_serialized_dynamic_value() {
+        let item = ItemWithDynamicValue {
+            hash: "hash value".to_string(),
+            metadata: value!({
+                "source": "import",
+                "attempts": 3,
+                "labels": ["a", "b"]
+            })
+        };
+
+        let serialized: HashMap<String, AttributeValue> = item.clone().into();
+        assert_eq!("3", &serialized["metadata"].clone().m.unwrap()["attempts"]
+            .clone().n.unwrap());
+        let deserialized = ItemWithDynamicValue::try_from(serialized).unwrap();
+
+        assert_eq!(item, deserialized);
+    }
+
+    #[test]
+    fn can_select_timestamp_encodings() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_516_991_409_453);
+        let item = ItemWithTimestamps {
+            created: time,
+            updated: time,
+            expires: Some(UNIX_EPOCH + Duration::from_secs(1_516_991_409))
+        };
+
+        let serialized: HashMap<String, AttributeValue> = item.clone().into();
+        assert_eq!("1516991409453", &serialized["created"].clone().n.unwrap());
+        assert_eq!("2018-01-26T18:30:09.453000000Z", &serialized["updated"].clone().s.unwrap());
+        assert_eq!("1516991409", &serialized["expires"].clone().n.unwrap());
+        assert_eq!(item, ItemWithTimestamps::try_from(serialized).unwrap());
+
+        let key = item.get_key();
+        assert_eq!("1516991409453", &key["created"].clone().n.unwrap());
+        let key_struct = ItemWithTimestampsKey::try_from(key).unwrap();
+        assert_eq!(time, key_struct.created);
+    }
+
+    #[test]
+    fn can_use_custom_converter_modules() {
+        let item = ItemWithCustomConverter { name: "korat".to_string() };
+
+        let serialized: HashMap<String, AttributeValue> = item.clone().into();
+        assert_eq!("tarok", &serialized["name"].clone().s.unwrap());
+        assert_eq!(item, ItemWithCustomConverter::try_from(serialized).unwrap());
+    }
+
+    #[test]
+    fn can_convert_recursive_and_shared_fields() {
+        let reply = Comment { text: "reply".to_string(), replies: vec![] };
+        let comment = Comment {
+            text: "comment".to_string(),
+            replies: vec![Box::new(reply)]
+        };
+        let item = ItemWithSharedConfig {
+            hash: "hash".to_string(),
+            config: Arc::new(SingleFieldItem { number_attribute: 12 }),
+            parent: Some(Box::new(comment))
+        };
+
+        let serialized: HashMap<String, AttributeValue> = item.clone().into();
+        let parent = serialized["parent"].clone().m.unwrap();
+        let replies = parent["replies"].clone().l.unwrap();
+        assert_eq!("reply", &replies[0].clone().m.unwrap()["text"].clone().s.unwrap());
+        assert_eq!(item, ItemWithSharedConfig::try_from(serialized).unwrap());
+    }
+
+    #[test]
+    fn can_nest_items_implemented_by_hand() {
+        let address = |city: &str| manual::Address { city: city.to_string() };
+        let item = ItemWithManualItems {
+            hash: "hash".to_string(),
+            address: address("Lisbon"),
+            previous: vec![address("Porto"), address("Faro")]
+        };
+
+        let serialized: HashMap<String, AttributeValue> = item.clone().into();
+        let nested = serialized["address"].clone().m.unwrap();
+        assert_eq!("Lisbon", &nested["city"].clone().s.unwrap());
+        assert_eq!(2, serialized["previous"].clone().l.unwrap().len());
+        assert_eq!(item, ItemWithManualItems::try_from(serialized).unwrap());
+    }
+
     #[test]
     fn can_get_attribute_names() {
         assert_eq!(
@@ -227,6 +438,50 @@ mod tests {
         )
     }
 
+    #[test]
+    fn can_use_any_scalar_type_as_key() {
+        let item = ItemWithBinaryKeys {
+            partition: Box::new(7),
+            sort: vec![1, 2, 3],
+            name: "name".to_string()
+        };
+        let key = item.get_key();
+        assert_eq!(Some("7".to_string()), key["partition"].n);
+        assert!(key["sort"].b.is_some());
+        assert_eq!(2, key.len());
+    }
+
+    #[test]
+    fn can_use_raw_identifiers_and_korat_key_attributes() {
+        let item = ItemWithRawIdentifiers {
+            r#type: "type".to_string(),
+            r#match: 3,
+            r#ref: None
+        };
+        assert_eq!(
+            vec!["type", "match", "ref"], ItemWithRawIdentifiers::get_attribute_names()
+        );
+
+        let serialized: HashMap<String, AttributeValue> = item.clone().into();
+        assert_eq!(Some("3".to_string()), serialized["match"].n);
+        assert_eq!(item, ItemWithRawIdentifiers::try_from(serialized).unwrap());
+
+        let key = ItemWithRawIdentifiersKey { r#type: "type".to_string(), r#match: 3 };
+        assert_eq!(item.get_key(), key.into());
+        assert_eq!(vec!["type", "match"], ItemWithRawIdentifiersKey::get_attribute_names());
+    }
+
+    #[test]
+    fn can_estimate_item_size() {
+        let item = ItemWithHashAndRange {
+            hash: "hash".to_string(),
+            range: "range".to_string(),
+            other: "other".to_string()
+        };
+        assert_eq!(4 + 4 + 5 + 5 + 5 + 5, item.estimated_size());
+        assert_eq!(16 + 3, SingleFieldItem { number_attribute: 1231 }.estimated_size());
+    }
+
     #[test]
     fn parent_pub_visibility_is_tranfered_to_key() {
         #[allow(unused_imports)]
@@ -0,0 +1,88 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Patient {
+    #[hash] clinic: String,
+    #[range] id: u32,
+    #[korat(encrypt)] name: String,
+    #[korat(encrypt)] diagnosis: Option<String>,
+    #[korat(sign)] insurer: String,
+    visits: u32,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use korat::AttributeValueConverter;
+    use korat::encryption::{
+        EncryptedItem, SIGNATURE_ATTRIBUTE, StaticKeyProvider, decrypt_item, encrypt_item
+    };
+    use korat::errors::EncryptionError;
+
+    use super::Patient;
+
+    fn patient(id: u32) -> Patient {
+        Patient {
+            clinic: "north".to_string(),
+            id,
+            name: format!("patient {}", id),
+            diagnosis: Some("flu".to_string()),
+            insurer: "acme".to_string(),
+            visits: 3,
+        }
+    }
+
+    fn provider() -> StaticKeyProvider {
+        StaticKeyProvider::new([7; 32], [8; 32])
+    }
+
+    #[test]
+    fn lists_encrypted_and_signed_fields() {
+        assert_eq!(vec!["clinic", "id"], Patient::key_attribute_names());
+        assert_eq!(vec!["name", "diagnosis"], Patient::encrypted_attribute_names());
+        assert_eq!(vec!["insurer"], Patient::signed_attribute_names());
+    }
+
+    #[test]
+    fn leaves_encrypted_fields_out_of_the_handles() {
+        let fields = Patient::fields();
+        assert_eq!("insurer", fields.insurer.name());
+        assert_eq!("visits", fields.visits.name());
+    }
+
+    #[test]
+    fn can_encrypt_and_decrypt_derived_items() {
+        let encrypted = encrypt_item(patient(1), &provider()).unwrap();
+        assert!(encrypted["name"].b.is_some());
+        assert!(encrypted["diagnosis"].b.is_some());
+        assert!(encrypted[SIGNATURE_ATTRIBUTE].b.is_some());
+        assert_eq!(Some("north".to_string()), encrypted["clinic"].s);
+
+        let decrypted: Patient = decrypt_item(encrypted, &provider()).unwrap();
+        assert_eq!(patient(1), decrypted);
+    }
+
+    #[test]
+    fn rejects_swapped_ciphertexts_and_tampering() {
+        let first = encrypt_item(patient(1), &provider()).unwrap();
+        let mut second = encrypt_item(patient(2), &provider()).unwrap();
+        second.insert("name".to_string(), first["name"].clone());
+        match decrypt_item::<Patient, _>(second, &provider()) {
+            Err(EncryptionError::InvalidSignature) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut tampered = first.clone();
+        tampered.insert("insurer".to_string(), "other".to_string().to_attribute_value());
+        match decrypt_item::<Patient, _>(tampered, &provider()) {
+            Err(EncryptionError::InvalidSignature) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut unsigned = first.clone();
+        unsigned.insert("visits".to_string(), 4.to_attribute_value());
+        assert_eq!(4, decrypt_item::<Patient, _>(unsigned, &provider()).unwrap().visits);
+    }
+}
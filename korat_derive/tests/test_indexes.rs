@@ -0,0 +1,67 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct User {
+    #[hash] #[korat(gsi_range = "by-team")] id: String,
+    #[range] #[korat(lsi_range = "ByCreation")] created: u32,
+    #[korat(gsi_hash = "ByEmail")] email: String,
+    #[korat(gsi_hash = "by-team", coerce)] team: Option<String>,
+    #[korat(lsi_range = "ByRole")] role: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use korat::{AttributeValue, AttributeValueConverter, DynamoDBInsertable};
+
+    use super::{User, UserByCreationKey, UserByEmailKey, UserByRoleKey, UserByTeamKey};
+
+    fn user() -> User {
+        User {
+            id: "u1".to_string(),
+            created: 42,
+            email: "korat@example.com".to_string(),
+            team: Some("cats".to_string()),
+            role: "admin".to_string(),
+        }
+    }
+
+    #[test]
+    fn generates_global_index_key_structs() {
+        let user = user();
+
+        let key = UserByEmailKey { email: "korat@example.com".to_string() };
+        let expected: HashMap<String, AttributeValue> = key.into();
+        assert_eq!(Some(expected), user.get_index_key("ByEmail"));
+
+        let key = UserByTeamKey { team: Some("cats".to_string()), id: "u1".to_string() };
+        let expected: HashMap<String, AttributeValue> = key.into();
+        assert_eq!(Some(expected), user.get_index_key("by-team"));
+    }
+
+    #[test]
+    fn generates_local_index_key_structs_with_the_table_hash_key() {
+        let user = user();
+
+        let key = UserByRoleKey { id: "u1".to_string(), role: "admin".to_string() };
+        let expected: HashMap<String, AttributeValue> = key.into();
+        assert_eq!(Some(expected), user.get_index_key("ByRole"));
+
+        let key = UserByCreationKey { id: "u1".to_string(), created: 42 };
+        let index_key = user.get_index_key("ByCreation").unwrap();
+        assert_eq!(Some(42.to_attribute_value()), index_key.get("created").cloned());
+        assert_eq!(HashMap::<String, AttributeValue>::from(key), index_key);
+    }
+
+    #[test]
+    fn leaves_items_out_of_sparse_indexes() {
+        let user = User { team: None, ..user() };
+        assert_eq!(None, user.get_index_key("by-team"));
+        assert!(user.get_index_key("ByEmail").is_some());
+        assert_eq!(None, user.get_index_key("ByName"));
+    }
+}
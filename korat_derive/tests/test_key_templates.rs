@@ -0,0 +1,103 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[korat(hash = "USER#{user_id}", range = "ORDER#{date}#{order_id}")]
+struct Order {
+    user_id: String,
+    date: String,
+    order_id: u32,
+    total: f64,
+}
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[korat(hash = "USER#{id}", hash_attribute = "pk")]
+#[korat(table = "users")]
+struct Profile {
+    id: u32,
+    #[range] section: String,
+    name: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use korat::{AttributeType, AttributeValue, AttributeValueConverter};
+    use korat::{DynamoDBInsertable, DynamoDBItem};
+    use korat::errors::ConversionError;
+    use korat::table::{DynamoDBTable, KeyAttribute};
+
+    use super::{Order, OrderKey, Profile, ProfileKey};
+
+    fn order() -> Order {
+        Order {
+            user_id: "u1".to_string(),
+            date: "2024-01-02".to_string(),
+            order_id: 42,
+            total: 9.5,
+        }
+    }
+
+    #[test]
+    fn synthesizes_keys_and_parses_them_back() {
+        let attributes: HashMap<String, AttributeValue> = order().into();
+        assert_eq!(Some("USER#u1".to_string()), attributes["PK"].s);
+        assert_eq!(Some("ORDER#2024-01-02#42".to_string()), attributes["SK"].s);
+        assert!(!attributes.contains_key("user_id"));
+        assert_eq!(vec!["PK", "SK", "total"], Order::get_attribute_names());
+
+        assert_eq!(order(), Order::try_from(attributes).unwrap());
+    }
+
+    #[test]
+    fn builds_key_structs_from_the_components() {
+        let key = OrderKey::new("u1".to_string(), "2024-01-02".to_string(), 42);
+        let key: HashMap<String, AttributeValue> = key.into();
+        assert_eq!(order().get_key(), key);
+
+        let key = ProfileKey::new(7, "settings".to_string());
+        let profile = Profile { id: 7, section: "settings".to_string(), name: "korat".to_string() };
+        let expected: HashMap<String, AttributeValue> = key.into();
+        assert_eq!(expected, profile.get_key());
+        assert_eq!(Some("USER#7".to_string()), expected["pk"].s);
+        assert_eq!(Some("settings".to_string()), expected["section"].s);
+    }
+
+    #[test]
+    fn rejects_keys_not_matching_their_template() {
+        let mut attributes: HashMap<String, AttributeValue> = order().into();
+        attributes.insert("SK".to_string(), "INVOICE#42".to_string().to_attribute_value());
+        match Order::try_from(attributes) {
+            Err(ConversionError::InvalidFormat { expected, .. }) => {
+                assert_eq!("ORDER#{date}#{order_id}", expected);
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn escapes_components_containing_their_separator() {
+        let order = Order { date: "2024#01%".to_string(), ..order() };
+        let attributes: HashMap<String, AttributeValue> = order.clone().into();
+        assert_eq!(Some("ORDER#2024%2301%25#42".to_string()), attributes["SK"].s);
+        assert_eq!(order, Order::try_from(attributes).unwrap());
+
+        let key: HashMap<String, AttributeValue> = OrderKey::new(
+            "u#1".to_string(), "2024#01%".to_string(), 42
+        ).into();
+        assert_eq!(Some("USER#u#1".to_string()), key["PK"].s);
+        assert_eq!(order.get_key()["SK"], key["SK"]);
+    }
+
+    #[test]
+    fn describes_template_keys_as_strings() {
+        let definition = Profile::table_definition();
+        assert_eq!(KeyAttribute::new("pk", AttributeType::S), definition.key_schema.hash);
+        let range = definition.key_schema.range;
+        assert_eq!(Some(KeyAttribute::new("section", AttributeType::S)), range);
+    }
+}
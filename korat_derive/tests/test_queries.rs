@@ -0,0 +1,69 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+extern crate rusoto_dynamodb;
+
+use std::time::SystemTime;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Message {
+    #[hash] channel: String,
+    #[range] #[korat(timestamp = "epoch_millis")] sent: SystemTime,
+    #[korat(gsi_hash = "ByAuthor")] author: String,
+    text: String,
+}
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[korat(hash = "USER#{user_id}", range = "ORDER#{date}#{order_id}")]
+struct Order {
+    user_id: String,
+    date: String,
+    order_id: u32,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use korat::AttributeValueConverter;
+    use korat::backend::rusoto::query_input;
+
+    use super::{MessageByAuthorKey, MessageKey, OrderKey};
+
+    #[test]
+    fn builds_range_conditions_with_the_field_converters() {
+        let since = UNIX_EPOCH + Duration::from_millis(1500);
+        let condition = MessageKey::partial("general".to_string())
+            .greater_than_or_equal(since)
+            .key_condition();
+        assert_eq!("#hash = :hash AND #range >= :range", condition.expression);
+        assert_eq!("channel", condition.names["#hash"]);
+        assert_eq!("sent", condition.names["#range"]);
+        assert_eq!(Some("1500".to_string()), condition.values[":range"].n);
+        assert_eq!("general".to_string().to_attribute_value(), condition.values[":hash"]);
+    }
+
+    #[test]
+    fn queries_secondary_indexes() {
+        let condition = MessageByAuthorKey::partial("korat".to_string()).key_condition();
+        assert_eq!(Some("ByAuthor".to_string()), condition.index_name);
+        assert_eq!("#hash = :hash", condition.expression);
+
+        let input = query_input("messages", condition);
+        assert_eq!(Some("ByAuthor".to_string()), input.index_name);
+        assert_eq!("author", input.expression_attribute_names.unwrap()["#hash"]);
+        let values = input.expression_attribute_values.unwrap();
+        assert_eq!(Some("korat".to_string()), values[":hash"].s);
+    }
+
+    #[test]
+    fn matches_prefixes_of_key_templates() {
+        let condition = OrderKey::partial("u1".to_string())
+            .begins_with("ORDER#2024-01".to_string())
+            .key_condition();
+        assert_eq!("#hash = :hash AND begins_with(#range, :range)", condition.expression);
+        assert_eq!("SK", condition.names["#range"]);
+        assert_eq!(Some("USER#u1".to_string()), condition.values[":hash"].s);
+    }
+}
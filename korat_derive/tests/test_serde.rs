@@ -0,0 +1,171 @@
+#[macro_use] extern crate korat_derive;
+#[macro_use] extern crate serde_derive;
+extern crate korat;
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+enum Status {
+    Active,
+    Suspended { reason: String },
+}
+
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+struct Preferences {
+    theme: String,
+    notifications: Vec<String>,
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Account {
+    #[hash] id: String,
+    #[korat(serde)] status: Status,
+    #[korat(serde)] preferences: Option<Preferences>,
+    #[korat(json_string)] profile: Preferences,
+    metadata: serde_json::Value,
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Measurement {
+    #[korat(serde)] readings: Vec<f64>,
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Sensor {
+    #[hash] id: String,
+    measurements: Vec<Measurement>,
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Lookup {
+    #[hash] id: String,
+    #[korat(json_string)] pairs: BTreeMap<(u8, u8), String>,
+}
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Document {
+    #[hash] id: String,
+    #[korat(compress = "gzip")] body: String,
+    #[korat(compress = "zstd")] attachment: Option<Vec<u8>>,
+    #[korat(serde, compress = "zstd")] preferences: Preferences,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use korat::{AttributeValue, DynamoDBItem};
+    use korat::errors::ConversionError;
+
+    use std::collections::BTreeMap;
+
+    use super::{Account, Document, Lookup, Measurement, Preferences, Sensor, Status};
+
+    #[test]
+    fn can_route_fields_through_serde() {
+        let account = Account {
+            id: "id".to_string(),
+            status: Status::Suspended { reason: "abuse".to_string() },
+            preferences: Some(Preferences {
+                theme: "dark".to_string(),
+                notifications: vec!["email".to_string()],
+            }),
+            profile: Preferences {
+                theme: "light".to_string(),
+                notifications: vec![],
+            },
+            metadata: serde_json::from_str(r#"{"source": "import"}"#).unwrap(),
+        };
+
+        let serialized: HashMap<String, AttributeValue> = account.clone().into();
+        let status = serialized["status"].clone().m.unwrap();
+        let suspended = status["Suspended"].clone().m.unwrap();
+        assert_eq!("abuse", &suspended["reason"].clone().s.unwrap());
+        assert_eq!(
+            r#"{"theme":"light","notifications":[]}"#,
+            &serialized["profile"].clone().s.unwrap()
+        );
+        let metadata = serialized["metadata"].clone().m.unwrap();
+        assert_eq!("import", &metadata["source"].clone().s.unwrap());
+        assert_eq!(account, Account::try_from(serialized).unwrap());
+
+        let account = Account { status: Status::Active, preferences: None, ..account };
+        let serialized: HashMap<String, AttributeValue> = account.clone().into();
+        assert_eq!("Active", &serialized["status"].clone().s.unwrap());
+        assert_eq!(account, Account::try_from(serialized).unwrap());
+    }
+
+    #[test]
+    fn reports_serde_fields_which_cant_be_stored() {
+        let measurement = |readings| Measurement { readings };
+        let sensor = Sensor {
+            id: "id".to_string(),
+            measurements: vec![measurement(vec![1.5]), measurement(vec![f64::INFINITY])],
+        };
+        match sensor.clone().try_into_attribute_map() {
+            Err(ConversionError::InvalidValue) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let sensor = Sensor { measurements: vec![measurement(vec![1.5])], ..sensor };
+        let serialized = sensor.clone().try_into_attribute_map().unwrap();
+        assert_eq!(sensor, Sensor::try_from(serialized).unwrap());
+
+        let mut pairs = BTreeMap::new();
+        pairs.insert((1, 2), "pair".to_string());
+        let lookup = Lookup { id: "id".to_string(), pairs };
+        match lookup.try_into_attribute_map() {
+            Err(ConversionError::Custom(message)) => assert!(message.contains("key must be")),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn can_import_and_export_dynamodb_json() {
+        let json = serde_json::from_str(r#"{
+            "id": {"S": "id"},
+            "status": {"S": "Active"},
+            "preferences": {"NULL": true},
+            "profile": {"S": "{\"theme\":\"dark\",\"notifications\":[]}"},
+            "metadata": {"M": {}}
+        }"#).unwrap();
+
+        let account: Account = korat::json::from_dynamodb_json(json).unwrap();
+        assert_eq!(Status::Active, account.status);
+        assert_eq!("dark", account.profile.theme);
+
+        let exported = korat::json::to_dynamodb_json(account.clone());
+        assert_eq!(account, korat::json::from_dynamodb_json(exported).unwrap());
+    }
+
+    #[test]
+    fn can_compress_fields() {
+        let document = Document {
+            id: "id".to_string(),
+            body: "korat ".repeat(100),
+            attachment: None,
+            preferences: Preferences {
+                theme: "dark".to_string(),
+                notifications: vec!["email".to_string()],
+            },
+        };
+
+        let serialized: HashMap<String, AttributeValue> = document.clone().into();
+        let body: Vec<u8> = serialized["body"].clone().b.unwrap();
+        assert_eq!(1, body[0]);
+        assert!(body.len() < 100);
+        let preferences: Vec<u8> = serialized["preferences"].clone().b.unwrap();
+        assert_eq!(2, preferences[0]);
+        assert_eq!(document, Document::try_from(serialized).unwrap());
+    }
+}
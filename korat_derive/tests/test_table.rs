@@ -0,0 +1,98 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+extern crate rusoto_dynamodb;
+
+use std::time::SystemTime;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[korat(table = "orders")]
+struct Order {
+    #[hash] customer: String,
+    #[range] number: u32,
+    #[korat(gsi_hash = "by-status")] status: String,
+    #[korat(gsi_range = "by-status", timestamp = "epoch_millis")] placed: SystemTime,
+    #[korat(lsi_range = "ByTotal")] total: f64,
+    #[korat(ttl)] expires: Option<i64>,
+}
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+#[korat(table = "sessions", billing_mode = "provisioned")]
+#[korat(read_capacity = 10, write_capacity = 5)]
+struct Session {
+    #[hash] token: Vec<u8>,
+    #[korat(gsi_hash = "ByUser", attribute_type = "S", with = "korat::json_string")] user: String,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use korat::AttributeType;
+    use korat::backend::rusoto::{create_table_input, update_time_to_live_input};
+    use korat::table::{BillingMode, DynamoDBTable, IndexKind, KeyAttribute, KeySchema};
+
+    use super::{Order, Session};
+
+    #[test]
+    fn describes_the_table_of_an_item() {
+        let definition = Order::table_definition();
+        assert_eq!("orders", Order::table_name());
+        assert_eq!(KeySchema {
+            hash: KeyAttribute::new("customer", AttributeType::S),
+            range: Some(KeyAttribute::new("number", AttributeType::N)),
+        }, definition.key_schema);
+        assert_eq!(BillingMode::PayPerRequest, definition.billing_mode);
+        assert_eq!(Some("expires".to_string()), definition.ttl_attribute);
+
+        let global = definition.global_indexes().next().unwrap();
+        assert_eq!("by-status", global.name);
+        assert_eq!(Some(KeyAttribute::new("placed", AttributeType::N)), global.key_schema.range);
+        let local = definition.local_indexes().next().unwrap();
+        assert_eq!((IndexKind::Local, "customer"), (local.kind, local.key_schema.hash.name.as_str()));
+    }
+
+    #[test]
+    fn builds_the_create_table_input() {
+        let input = create_table_input(&Order::table_definition());
+        assert_eq!("orders", input.table_name);
+        assert_eq!(Some("PAY_PER_REQUEST".to_string()), input.billing_mode);
+        assert_eq!(None, input.provisioned_throughput);
+
+        let key_schema: Vec<_> = input.key_schema.iter()
+            .map(|element| (element.attribute_name.as_str(), element.key_type.as_str()))
+            .collect();
+        assert_eq!(vec![("customer", "HASH"), ("number", "RANGE")], key_schema);
+        let attributes: Vec<_> = input.attribute_definitions.iter()
+            .map(|attribute| (attribute.attribute_name.as_str(), attribute.attribute_type.as_str()))
+            .collect();
+        assert_eq!(vec![
+            ("customer", "S"), ("number", "N"), ("status", "S"), ("placed", "N"), ("total", "N")
+        ], attributes);
+
+        let global = &input.global_secondary_indexes.unwrap()[0];
+        assert_eq!("by-status", global.index_name);
+        assert_eq!(Some("ALL".to_string()), global.projection.projection_type);
+        assert_eq!("ByTotal", input.local_secondary_indexes.unwrap()[0].index_name);
+
+        let ttl = update_time_to_live_input(&Order::table_definition()).unwrap();
+        assert_eq!(("orders", "expires"), (
+            ttl.table_name.as_str(), ttl.time_to_live_specification.attribute_name.as_str()
+        ));
+    }
+
+    #[test]
+    fn provisions_the_table_and_its_global_indexes() {
+        let definition = Session::table_definition();
+        assert_eq!(KeyAttribute::new("token", AttributeType::B), definition.key_schema.hash);
+        assert_eq!(None, update_time_to_live_input(&definition));
+
+        let input = create_table_input(&definition);
+        assert_eq!(Some("PROVISIONED".to_string()), input.billing_mode);
+        let throughput = input.provisioned_throughput.unwrap();
+        assert_eq!((10, 5), (throughput.read_capacity_units, throughput.write_capacity_units));
+        let global = &input.global_secondary_indexes.unwrap()[0];
+        assert_eq!(Some(throughput), global.provisioned_throughput);
+        assert_eq!("S", input.attribute_definitions[1].attribute_type);
+        assert_eq!(None, input.local_secondary_indexes);
+    }
+}
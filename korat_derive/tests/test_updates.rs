@@ -0,0 +1,100 @@
+#[macro_use] extern crate korat_derive;
+extern crate korat;
+extern crate rusoto_dynamodb;
+extern crate aws_sdk_dynamodb;
+
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Revision {
+    editor: String,
+}
+
+#[derive(DynamoDBItem, PartialEq, Debug, Clone)]
+struct Article {
+    #[hash] id: String,
+    title: String,
+    views: u32,
+    tags: HashSet<String>,
+    revisions: Vec<Revision>,
+    #[korat(timestamp = "rfc3339")] published: Option<SystemTime>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use aws_sdk_dynamodb::types::AttributeValue as SdkAttributeValue;
+
+    use korat::backend::{aws_sdk, rusoto};
+    use korat::update::Update;
+
+    use super::{Article, ArticleKey, Revision};
+
+    #[test]
+    fn converts_values_with_the_field_converters() {
+        let fields = Article::fields();
+        let update = Update::new()
+            .set(fields.published, Some(UNIX_EPOCH + Duration::from_secs(86400)))
+            .list_append(fields.revisions, vec![Revision { editor: "korat".to_string() }])
+            .set_if_not_exists(fields.title, "Untitled".to_string())
+            .add(fields.views, 1)
+            .delete(fields.tags, vec!["draft".to_string()].into_iter().collect())
+            .condition(fields.id.exists())
+            .build()
+            .unwrap();
+        assert_eq!(
+            "SET #n0 = :v0, #n1 = list_append(#n1, :v1), #n2 = if_not_exists(#n2, :v2) \
+             ADD #n3 :v3 DELETE #n4 :v4",
+            update.expression
+        );
+        assert_eq!(Some("1970-01-02T00:00:00.000000000Z".to_string()), update.values[":v0"].s);
+        assert_eq!(Some(vec!["draft".to_string()]), update.values[":v4"].ss);
+        assert_eq!(Some("attribute_exists(#n5)".to_string()), update.condition);
+    }
+
+    #[test]
+    fn appends_empty_lists() {
+        let fields = Article::fields();
+        let update = Update::new().list_append(fields.revisions, vec![]).build().unwrap();
+        assert_eq!("SET #n0 = list_append(#n0, :v0)", update.expression);
+        assert_eq!(Some(vec![]), update.values[":v0"].l);
+    }
+
+    #[test]
+    fn builds_update_item_requests() {
+        let fields = Article::fields();
+        let update = Update::new()
+            .remove(fields.published)
+            .build()
+            .unwrap();
+        let key = ArticleKey::new("a1".to_string());
+        let input = rusoto::update_item_input("articles", key.into(), update);
+        assert_eq!("articles", input.table_name);
+        assert_eq!(Some("a1".to_string()), input.key["id"].s);
+        assert_eq!(Some("REMOVE #n0".to_string()), input.update_expression);
+        assert_eq!("published", input.expression_attribute_names.unwrap()["#n0"]);
+        assert_eq!(None, input.expression_attribute_values);
+    }
+
+    #[test]
+    fn builds_update_item_requests_for_the_aws_sdk() {
+        let fields = Article::fields();
+        let update = Update::new()
+            .set(fields.title, "Korat".to_string())
+            .condition(fields.id.exists())
+            .build()
+            .unwrap();
+        let key = ArticleKey::new("a1".to_string());
+        let input = aws_sdk::update_item_input("articles", key.into(), update).build().unwrap();
+        assert_eq!(Some("articles"), input.table_name());
+        assert_eq!(SdkAttributeValue::S("a1".to_string()), input.key().unwrap()["id"]);
+        assert_eq!(Some("SET #n0 = :v0"), input.update_expression());
+        assert_eq!(Some("attribute_exists(#n1)"), input.condition_expression());
+        let values = input.expression_attribute_values().unwrap();
+        assert_eq!(SdkAttributeValue::S("Korat".to_string()), values[":v0"]);
+    }
+}